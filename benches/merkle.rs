@@ -1,9 +1,9 @@
 use std::time::Duration;
 
 use circuit_samples::circuits::{merkle_circuit::MerklePathCircuit, poseidon_circuit::utils::Spec};
+use circuit_samples::debug::validate_public;
 use criterion::{criterion_group, criterion_main, Criterion};
 use halo2_proofs::{
-    circuit::Value,
     pasta::{EqAffine, Fp},
     plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, ProvingKey, SingleVerifier},
     poly::commitment::Params,
@@ -13,7 +13,10 @@ use halo2_proofs::{
 #[path = "../tests/utils/mod.rs"]
 mod utils;
 use rand_core::OsRng;
-use utils::{p128_pow5_t2::P128Pow5T2, p128_pow5_t3::P128Pow5T3, poseidon_hash::gen_merkle_path};
+use utils::{
+    p128_pow5_t2::P128Pow5T2, p128_pow5_t3::P128Pow5T3, p128_pow5_t5::P128Pow5T5,
+    poseidon_hash::gen_merkle_path,
+};
 
 fn proof_criterion_256(c: &mut Criterion) {
     generate_proof_fn::<P128Pow5T3, 32, 3, 2>(c);
@@ -31,6 +34,14 @@ fn proof_criterion_128(c: &mut Criterion) {
     generate_proof_fn::<P128Pow5T2, 64, 3, 1>(c);
 }
 
+fn proof_criterion_512(c: &mut Criterion) {
+    generate_proof_fn::<P128Pow5T5, 32, 5, 4>(c);
+
+    generate_proof_fn::<P128Pow5T5, 48, 5, 4>(c);
+
+    generate_proof_fn::<P128Pow5T5, 64, 5, 4>(c);
+}
+
 fn generate_proof_fn<S: Spec<Fp, W>, const M: usize, const W: usize, const I: usize>(
     c: &mut Criterion,
 ) {
@@ -42,7 +53,7 @@ fn generate_proof_fn<S: Spec<Fp, W>, const M: usize, const W: usize, const I: us
                 create_proof(
                     &params,
                     &pk,
-                    &[prover_circuit.clone()],
+                    std::slice::from_ref(&prover_circuit),
                     &[&[&public]],
                     OsRng,
                     &mut Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]),
@@ -92,8 +103,9 @@ fn prepare_circuits<S: Spec<Fp, W>, const M: usize, const W: usize, const I: usi
 ) {
     assert_eq!(S::element_size(), I);
 
-    let row_n = (S::full_rounds() + S::partial_rounds()) * (I + 2) + 6;
-    let degree = ((row_n * M) as f64).log2().ceil() as u32;
+    // leave room for the blinding rows
+    let row_n = MerklePathCircuit::<Fp, S, M, W, I>::rows_for(false);
+    let degree = ((row_n + 10) as f64).log2().ceil() as u32;
 
     let path = gen_merkle_path::<Fp, S, W>(n, M);
 
@@ -102,10 +114,7 @@ fn prepare_circuits<S: Spec<Fp, W>, const M: usize, const W: usize, const I: usi
         path.get_right_value(),
         path.get_copy_value(M),
     );
-    let empty: Vec<Vec<Value<Fp>>> = vec![vec![Value::unknown(); I]; M];
-    let empty_copy: Vec<Value<Fp>> = vec![Value::unknown(); M + 1];
-    let empty_circuit =
-        MerklePathCircuit::<Fp, S, M, W, I>::new(empty.clone(), empty.clone(), empty_copy.clone());
+    let empty_circuit = MerklePathCircuit::<Fp, S, M, W, I>::empty();
     let public = path
         .get_leaf()
         .into_iter()
@@ -113,16 +122,19 @@ fn prepare_circuits<S: Spec<Fp, W>, const M: usize, const W: usize, const I: usi
         .chain(path.get_root())
         .collect::<Vec<_>>();
 
+    validate_public(&prover_circuit, std::slice::from_ref(&public))
+        .expect("public inputs do not fit");
+
     let params: Params<EqAffine> = Params::new(degree);
     let vk = keygen_vk(&params, &empty_circuit).expect("failed to generate vk");
     let pk = keygen_pk(&params, vk, &empty_circuit).expect("failed to generate pk");
 
-    return (params, pk, public, prover_circuit);
+    (params, pk, public, prover_circuit)
 }
 
 criterion_group! {
     name = benches;
     config = Criterion::default().measurement_time(Duration::from_secs(30)).sample_size(10);
-    targets = proof_criterion_128,proof_criterion_256
+    targets = proof_criterion_128,proof_criterion_256,proof_criterion_512
 }
 criterion_main!(benches);
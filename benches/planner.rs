@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use circuit_samples::circuits::{merkle_circuit::MerklePathCircuit, WithPlanner};
+use circuit_samples::debug::min_k;
+use criterion::{criterion_group, criterion_main, Criterion};
+use halo2_proofs::{
+    circuit::{floor_planner::V1, SimpleFloorPlanner},
+    pasta::{EqAffine, Fp},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, FloorPlanner, SingleVerifier,
+    },
+    poly::commitment::Params,
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+
+#[path = "../tests/utils/mod.rs"]
+mod utils;
+use rand_core::OsRng;
+use utils::{p128_pow5_t3::P128Pow5T3, poseidon_hash::gen_merkle_path};
+
+type Merkle = MerklePathCircuit<Fp, P128Pow5T3, 32, 3, 2>;
+
+// prove the same merkle statement with the regions laid out in order by the
+// simple planner and packed by V1, each at the smallest k it fits in
+fn proof_criterion_planner(c: &mut Criterion) {
+    for n in [8, 32] {
+        generate_proof_fn::<SimpleFloorPlanner>(c, n, "simple");
+        generate_proof_fn::<V1>(c, n, "v1");
+    }
+}
+
+fn generate_proof_fn<P: FloorPlanner>(c: &mut Criterion, n: usize, planner: &str) {
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 32);
+    let prover_circuit = WithPlanner::<_, P>::new(Merkle::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(32),
+    ));
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+
+    let k = min_k(&prover_circuit, std::slice::from_ref(&public)).expect("the statement holds");
+    println!("minimum k with the {planner} planner for n:{n} is {k}");
+
+    let params: Params<EqAffine> = Params::new(k);
+    let empty_circuit = prover_circuit.without_witnesses();
+    let vk = keygen_vk(&params, &empty_circuit).expect("failed to generate vk");
+    let pk = keygen_pk(&params, vk, &empty_circuit).expect("failed to generate pk");
+
+    c.bench_function(
+        &format!("prove with the {planner} planner for n:{n} k: {k}"),
+        |b| {
+            b.iter(|| {
+                create_proof(
+                    &params,
+                    &pk,
+                    std::slice::from_ref(&prover_circuit),
+                    &[&[&public]],
+                    OsRng,
+                    &mut Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]),
+                )
+                .expect("proof generation should not fail");
+            })
+        },
+    );
+
+    // both planners must give proofs that verify
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[prover_circuit],
+        &[&[&public]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+    assert!(verify_proof(
+        &params,
+        pk.get_vk(),
+        SingleVerifier::new(&params),
+        &[&[&public]],
+        &mut Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]),
+    )
+    .is_ok());
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(30)).sample_size(10);
+    targets = proof_criterion_planner
+}
+criterion_main!(benches);
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use circuit_samples::circuits::poseidon_circuit::{utils::Spec, PoseidonCircuit};
+use circuit_samples::native::poseidon::hash;
+use criterion::{criterion_group, criterion_main, Criterion};
+use ff::Field;
+use halo2_proofs::{
+    pasta::{EqAffine, Fp},
+    plonk::{create_proof, keygen_pk, keygen_vk, ProvingKey},
+    poly::commitment::Params,
+    transcript::{Blake2bWrite, Challenge255},
+};
+
+#[path = "../tests/utils/mod.rs"]
+mod utils;
+use rand_core::OsRng;
+use utils::p128_pow5_t3::P128Pow5T3;
+
+// compare rounds laid out per row: every lane adds W advice and W fixed
+// columns while dividing the permutation rows.
+fn proof_criterion_unrolled(c: &mut Criterion) {
+    for n in [2, 20] {
+        generate_proof_fn::<P128Pow5T3, 3, 1>(c, n);
+        generate_proof_fn::<P128Pow5T3, 3, 2>(c, n);
+        generate_proof_fn::<P128Pow5T3, 3, 4>(c, n);
+    }
+}
+
+fn generate_proof_fn<S: Spec<Fp, W> + Clone + Default, const W: usize, const R: usize>(
+    c: &mut Criterion,
+    n: usize,
+) {
+    let (params, pk, public, prover_circuit) = prepare_circuits::<S, W, R>(n);
+    c.bench_function(&format!("generate proof for n:{n} lanes: {R}"), |b| {
+        b.iter(|| {
+            create_proof(
+                &params,
+                &pk,
+                std::slice::from_ref(&prover_circuit),
+                &[&[&public]],
+                OsRng,
+                &mut Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]),
+            )
+            .expect("proof generation should not fail");
+        })
+    });
+}
+
+fn prepare_circuits<S: Spec<Fp, W> + Clone + Default, const W: usize, const R: usize>(
+    n: usize,
+) -> (
+    Params<EqAffine>,
+    ProvingKey<EqAffine>,
+    Vec<Fp>,
+    PoseidonCircuit<Fp, S, W, R>,
+) {
+    let size = S::element_size();
+    assert_eq!(n % size, 0);
+
+    let row_n = PoseidonCircuit::<Fp, S, W, R>::rows_for(n);
+    // leave room for the blinding rows
+    let degree = ((row_n + 10) as f64).log2().ceil() as u32;
+    println!(
+        "lanes: {R} uses {} advice columns and {row_n} rows, k: {degree}",
+        R * W
+    );
+
+    let inputs: Vec<Fp> = (0..n).map(|_| Fp::random(OsRng)).collect();
+    let public = hash::<Fp, S, W>(inputs.clone());
+
+    let prover_circuit = PoseidonCircuit::<Fp, S, W, R>::new(inputs);
+
+    let params: Params<EqAffine> = Params::new(degree);
+    let vk = keygen_vk(&params, &prover_circuit).expect("failed to generate vk");
+    let pk = keygen_pk(&params, vk, &prover_circuit).expect("failed to generate pk");
+
+    (params, pk, public, prover_circuit)
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(30)).sample_size(10);
+    targets = proof_criterion_unrolled
+}
+criterion_main!(benches);
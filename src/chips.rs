@@ -1,3 +1,5 @@
 pub mod arth_chips;
+pub mod arth_expr;
+pub mod hash_instruction;
 pub mod merkle_chip;
 pub mod poseidon_chip;
@@ -3,7 +3,10 @@ use std::marker::PhantomData;
 use ff::PrimeField;
 use halo2_proofs::{
     circuit::{AssignedCell, Chip, Layouter, Region, Value},
-    plonk::{Advice, Column, ConstraintSystem, Error, Instance, Selector},
+    plonk::{
+        Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Instance,
+        Selector, VirtualCells,
+    },
     poly::Rotation,
 };
 
@@ -12,6 +15,13 @@ pub struct Number<F: PrimeField> {
     value: AssignedCell<F, F>,
 }
 
+impl<F: PrimeField> Number<F> {
+    /// The cell holding the number, for linking it with other chips.
+    pub(crate) fn cell(&self) -> &AssignedCell<F, F> {
+        &self.value
+    }
+}
+
 pub trait NumericInstructions<F: PrimeField>: Chip<F> {
     /// Variable representing a number.
     type Num;
@@ -38,9 +48,416 @@ pub trait NumericInstructions<F: PrimeField>: Chip<F> {
         b: Self::Num,
     ) -> Result<Self::Num, Error>;
 
+    /// Returns `d = a * b + c`.
+    fn mul_add(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        c: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns the sum of `a[i] * b[i]` for witnessed `a` and `b` of the
+    /// same, non zero, length.
+    fn inner_product(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &[Self::Num],
+        b: &[Self::Num],
+    ) -> Result<Self::Num, Error>;
+
     /// Returns `c = a ^ 3`.
     fn cube(&self, layouter: impl Layouter<F>, a: Self::Num) -> Result<Self::Num, Error>;
 
+    /// Returns `c = a ^ exp` for a constant `exp > 0`, by square and multiply.
+    fn pow(&self, layouter: impl Layouter<F>, a: Self::Num, exp: u64) -> Result<Self::Num, Error>;
+
+    /// Returns `y = a * x + b` for constants `a` and `b`.
+    fn affine(
+        &self,
+        layouter: impl Layouter<F>,
+        a: F,
+        x: Self::Num,
+        b: F,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `c = a XOR b` for boolean `a` and `b`.
+    fn xor(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `c = a AND b` for boolean `a` and `b`.
+    fn and(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `c = a OR b` for boolean `a` and `b`.
+    fn or(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `a` if the boolean `cond` is set, otherwise `b`.
+    fn select(
+        &self,
+        layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `(a, b)` if the boolean `cond` is unset, otherwise `(b, a)`.
+    fn conditional_swap(
+        &self,
+        layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<(Self::Num, Self::Num), Error>;
+
+    /// Constrains `a < 2^bits` by decomposing it into bits.
+    fn range_check(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        bits: usize,
+    ) -> Result<(), Error>;
+
+    /// Constrains the fixed-point `a`, with `frac_bits` fractional bits and
+    /// `int_bits` integer bits counting the sign bit, to be nonnegative: it
+    /// fits in `int_bits + frac_bits` bits with the sign bit clear. Negative
+    /// values wrapped around the field modulus are rejected too. Panics if
+    /// `int_bits` is zero; with a single bit in total only zero passes.
+    fn assert_nonneg(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        int_bits: usize,
+        frac_bits: usize,
+    ) -> Result<(), Error>;
+
+    /// Returns the boolean `c = a < b`, both inputs must already be known to
+    /// be less than `2^bits`.
+    fn less_than(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        bits: usize,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns the boolean `c = (a == b)`.
+    fn is_equal(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `(hi, lo)` with `a = hi * 2^bits + lo` and both halves
+    /// range checked to `bits` bits.
+    fn split(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        bits: usize,
+    ) -> Result<(Self::Num, Self::Num), Error>;
+
+    /// Returns the `count` boolean flags packed in `packed`, flag `i` being
+    /// bit `i`. Constrains `packed < 2^count`.
+    fn unpack_flags(
+        &self,
+        layouter: impl Layouter<F>,
+        packed: Self::Num,
+        count: usize,
+    ) -> Result<Vec<Self::Num>, Error>;
+
+    /// Constrains `a` to equal one of the numbers of the non empty `set`.
+    fn assert_in_set(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        set: &[Self::Num],
+    ) -> Result<(), Error>
+    where
+        Self::Num: Clone,
+    {
+        let mut any = None;
+        for s in set {
+            let eq = self.is_equal(layouter.namespace(|| "a == s"), a.clone(), s.clone())?;
+            any = Some(match any {
+                Some(acc) => self.or(layouter.namespace(|| "any match"), acc, eq)?,
+                None => eq,
+            });
+        }
+        let any = any.expect("the set is empty");
+        self.assert_nonzero(layouter.namespace(|| "in set"), any)
+    }
+
+    /// Returns the number of bits where `a` and `b` differ, the popcount of
+    /// `a XOR b`, as the distance of two ids in a Kademlia-like table. Both
+    /// inputs are constrained to be less than `2^bits`.
+    fn bit_difference_count(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        bits: usize,
+    ) -> Result<Self::Num, Error>
+    where
+        Self::Num: Clone,
+    {
+        let a = self.unpack_flags(layouter.namespace(|| "bits of a"), a, bits)?;
+        let b = self.unpack_flags(layouter.namespace(|| "bits of b"), b, bits)?;
+
+        let mut count = None;
+        for (a, b) in a.into_iter().zip(b) {
+            let diff = self.xor(layouter.namespace(|| "a_i XOR b_i"), a, b)?;
+            count = Some(match count {
+                Some(acc) => self.add(layouter.namespace(|| "count"), acc, diff)?,
+                None => diff,
+            });
+        }
+        Ok(count.expect("at least one bit is compared"))
+    }
+
+    /// Constrains `a^order == 1`, e.g. for the generator of an FFT domain of
+    /// size `order`. For `order = 1`, `a` must be one.
+    fn assert_root_of_unity(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        order: u64,
+    ) -> Result<(), Error>
+    where
+        Self::Num: Clone,
+    {
+        assert!(order > 0, "a root of unity has a positive order");
+        let power = self.pow(layouter.namespace(|| "a^order"), a, order)?;
+        // the constant one comes from the coefficient of an affine gate
+        let one = self.affine(layouter.namespace(|| "one"), F::ZERO, power.clone(), F::ONE)?;
+        let eq = self.is_equal(layouter.namespace(|| "a^order == 1"), power, one)?;
+        self.assert_nonzero(layouter.namespace(|| "root of unity"), eq)
+    }
+
+    /// Constrains `v` to be a one-hot encoding, every element boolean and
+    /// exactly one of them set, and returns the index `Σ i·v_i` of the set
+    /// element.
+    fn assert_one_hot(
+        &self,
+        mut layouter: impl Layouter<F>,
+        v: &[Self::Num],
+    ) -> Result<Self::Num, Error>
+    where
+        Self::Num: Clone,
+    {
+        let mut sum: Option<Self::Num> = None;
+        let mut index: Option<Self::Num> = None;
+        for (i, b) in v.iter().enumerate() {
+            // b AND b = b, the gate constrains b to be boolean
+            let b = self.and(layouter.namespace(|| "boolean"), b.clone(), b.clone())?;
+            let weighted = self.affine(
+                layouter.namespace(|| "i * v_i"),
+                F::from(i as u64),
+                b.clone(),
+                F::ZERO,
+            )?;
+            sum = Some(match sum {
+                Some(acc) => self.add(layouter.namespace(|| "sum"), acc, b)?,
+                None => b,
+            });
+            index = Some(match index {
+                Some(acc) => self.add(layouter.namespace(|| "index"), acc, weighted)?,
+                None => weighted,
+            });
+        }
+        let sum = sum.expect("a one-hot vector is not empty");
+
+        // the constant one comes from the coefficient of an affine gate
+        let one = self.affine(layouter.namespace(|| "one"), F::ZERO, sum.clone(), F::ONE)?;
+        let eq = self.is_equal(layouter.namespace(|| "sum == 1"), sum, one)?;
+        self.assert_nonzero(layouter.namespace(|| "one set"), eq)?;
+        Ok(index.expect("a one-hot vector is not empty"))
+    }
+
+    /// Returns `max(a - b, 0)`, both inputs must already be known to be less
+    /// than `2^bits`.
+    fn sub_saturating(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        bits: usize,
+    ) -> Result<Self::Num, Error>
+    where
+        Self::Num: Clone,
+    {
+        let below = self.less_than(layouter.namespace(|| "a < b"), a.clone(), b.clone(), bits)?;
+        let neg_b = self.affine(layouter.namespace(|| "-b"), -F::ONE, b, F::ZERO)?;
+        let diff = self.add(layouter.namespace(|| "a - b"), a.clone(), neg_b)?;
+        // the constant zero comes from the coefficient of an affine gate
+        let zero = self.affine(layouter.namespace(|| "zero"), F::ZERO, a, F::ZERO)?;
+        self.select(layouter.namespace(|| "clamp"), below, zero, diff)
+    }
+
+    /// Constrains `|a - target| <= epsilon`. For fixed-point numbers
+    /// `epsilon` is encoded like `a` and `target`, scaled by
+    /// `2^frac_bits`. Both `a - target + epsilon` and `target - a + epsilon`
+    /// are range checked to `bits` bits, so neither may wrap around below
+    /// zero. At `|a - target| == epsilon` one of them is `2 * epsilon`, so
+    /// `epsilon` must be below `2^(bits - 1)`.
+    fn assert_approx_equal(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        target: Self::Num,
+        epsilon: F,
+        bits: usize,
+    ) -> Result<(), Error>
+    where
+        Self::Num: Clone,
+    {
+        assert!(
+            (1..=129).contains(&bits) && F::from_u128(to_u128(&epsilon, bits - 1)) == epsilon,
+            "epsilon must be below 2^{}, twice it is range checked to {bits} bits",
+            bits.saturating_sub(1)
+        );
+        // the two differences add up to 2 * epsilon without wrapping around
+        assert!(bits + 2 < F::NUM_BITS as usize);
+
+        let above = self.affine(
+            layouter.namespace(|| "eps - target"),
+            -F::ONE,
+            target.clone(),
+            epsilon,
+        )?;
+        let above = self.add(layouter.namespace(|| "a - target + eps"), a.clone(), above)?;
+        let below = self.affine(layouter.namespace(|| "eps - a"), -F::ONE, a, epsilon)?;
+        let below = self.add(layouter.namespace(|| "target - a + eps"), target, below)?;
+        self.range_check(layouter.namespace(|| "a >= target - eps"), above, bits)?;
+        self.range_check(layouter.namespace(|| "a <= target + eps"), below, bits)
+    }
+
+    /// Returns `nums` in ascending order, sorted by the comparators of
+    /// Batcher's odd-even merge sort, a network fixed by the count alone.
+    /// Every comparator is a `conditional_swap`, so the output is a
+    /// permutation of the input by construction; the order is checked by
+    /// `assert_sorted`, which also range checks every number to `bits` bits
+    /// as the comparisons need.
+    fn sort(
+        &self,
+        mut layouter: impl Layouter<F>,
+        nums: &[Self::Num],
+        bits: usize,
+    ) -> Result<Vec<Self::Num>, Error>
+    where
+        Self::Num: Clone,
+    {
+        let mut x = nums.to_vec();
+        for (i, j) in batcher_comparators(x.len()) {
+            let (a, b) = (x[i].clone(), x[j].clone());
+            let swap =
+                self.less_than(layouter.namespace(|| "b < a"), b.clone(), a.clone(), bits)?;
+            (x[i], x[j]) = self.conditional_swap(layouter.namespace(|| "min, max"), swap, a, b)?;
+        }
+        self.assert_sorted(layouter.namespace(|| "sorted"), &x, bits, false)?;
+        Ok(x)
+    }
+
+    /// Returns the next term `a + b` of a Fibonacci-like sequence from its
+    /// two previous terms `a` and `b`.
+    fn fib_step(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        self.add(layouter, a, b)
+    }
+
+    /// Returns the next term `p * a + q * b` of the linear recurrence of
+    /// constant coefficients `(p, q)` from its two previous terms `a` and
+    /// `b`; `fib_step` is the recurrence `(1, 1)`.
+    fn linear_step(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        (p, q): (F, F),
+    ) -> Result<Self::Num, Error> {
+        let pa = self.affine(layouter.namespace(|| "p * a"), p, a, F::ZERO)?;
+        let qb = self.affine(layouter.namespace(|| "q * b"), q, b, F::ZERO)?;
+        self.add(layouter.namespace(|| "p * a + q * b"), pa, qb)
+    }
+
+    /// Returns the parity of an index given by its bits, lowest first: the
+    /// boolean lowest bit, set when the node at that index is a right child.
+    /// This is the choice `load_leaves` makes from the first index bit.
+    fn index_parity(
+        &self,
+        layouter: impl Layouter<F>,
+        index_bits: &[Self::Num],
+    ) -> Result<Self::Num, Error>
+    where
+        Self::Num: Clone,
+    {
+        let lowest = index_bits.first().expect("an index has at least one bit");
+        // b AND b = b, the gate constrains b to be boolean
+        self.and(layouter, lowest.clone(), lowest.clone())
+    }
+
+    /// Returns `r = a * b mod modulus` for `a`, `b` already known to be less
+    /// than `2^bits`, with `0 < modulus <= 2^bits`. The quotient and
+    /// remainder are witnessed and range checked.
+    fn mul_mod_small(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        modulus: F,
+        bits: usize,
+    ) -> Result<Self::Num, Error>;
+
+    /// Constrains `nums` to be ascending, strictly if `strict` is set, after
+    /// range checking every number to `bits` bits.
+    fn assert_sorted(
+        &self,
+        layouter: impl Layouter<F>,
+        nums: &[Self::Num],
+        bits: usize,
+        strict: bool,
+    ) -> Result<(), Error>;
+
+    /// Constrains `nums` to be strictly increasing, as the indices of an
+    /// append only log, after range checking every number to `bits` bits.
+    fn assert_strictly_increasing(
+        &self,
+        layouter: impl Layouter<F>,
+        nums: &[Self::Num],
+        bits: usize,
+    ) -> Result<(), Error> {
+        self.assert_sorted(layouter, nums, bits, true)
+    }
+
+    /// Constrains `a != 0` by witnessing its inverse.
+    fn assert_nonzero(&self, layouter: impl Layouter<F>, a: Self::Num) -> Result<(), Error>;
+
+    /// Constrains `a != b` through `a - b != 0`.
+    fn assert_not_equal(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<(), Error>;
+
     fn expose_public(
         &self,
         layouter: impl Layouter<F>,
@@ -62,10 +479,23 @@ pub struct ArthConfig {
     /// This is the public input (instance) column.
     instance: Column<Instance>,
 
+    /// constant coefficients for the affine gate
+    coeff: [Column<Fixed>; 2],
+
     // selectors to enable the gate
     s_mul: Selector,
     s_add: Selector,
     s_cube: Selector,
+    s_mul_add: Selector,
+    s_nonzero: Selector,
+    s_affine: Selector,
+    s_xor: Selector,
+    s_and: Selector,
+    s_or: Selector,
+    s_select: Selector,
+    s_decompose: Selector,
+    s_lt: Selector,
+    s_eq: Selector,
 }
 
 impl<F: PrimeField> ArthChip<F> {
@@ -76,10 +506,16 @@ impl<F: PrimeField> ArthChip<F> {
         }
     }
 
+    /// advice columns passed to `configure`, the two fan-in inputs
+    pub const fn num_advice_columns() -> usize {
+        2
+    }
+
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         advice: [Column<Advice>; 2],
         instance: Column<Instance>,
+        coeff: [Column<Fixed>; 2],
     ) -> <Self as Chip<F>>::Config {
         meta.enable_equality(instance);
         for column in &advice {
@@ -88,6 +524,16 @@ impl<F: PrimeField> ArthChip<F> {
         let s_mul = meta.selector();
         let s_add = meta.selector();
         let s_cube = meta.selector();
+        let s_mul_add = meta.selector();
+        let s_nonzero = meta.selector();
+        let s_affine = meta.selector();
+        let s_xor = meta.selector();
+        let s_and = meta.selector();
+        let s_or = meta.selector();
+        let s_select = meta.selector();
+        let s_decompose = meta.selector();
+        let s_lt = meta.selector();
+        let s_eq = meta.selector();
 
         // Define our multiplication gate!
         meta.create_gate("mul", |meta| {
@@ -128,6 +574,22 @@ impl<F: PrimeField> ArthChip<F> {
             vec![s_add * (lhs + rhs - out)]
         });
 
+        meta.create_gate("mul add", |meta| {
+            //
+            // | a0  | a1  | s_mul_add |
+            // |-----|-----|-----------|
+            // | a   | b   | s_mul_add |
+            // | c   | out |           |
+
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let c = meta.query_advice(advice[0], Rotation::next());
+            let out = meta.query_advice(advice[1], Rotation::next());
+            let s_mul_add = meta.query_selector(s_mul_add);
+
+            vec![s_mul_add * (a * b + c - out)]
+        });
+
         meta.create_gate("cube", |meta| {
             //
             // | a0  | s_add |
@@ -142,13 +604,307 @@ impl<F: PrimeField> ArthChip<F> {
             vec![s_pow3 * (lhs.clone() * lhs.clone() * lhs - out)]
         });
 
+        meta.create_gate("nonzero", |meta| {
+            //
+            // | a0  | a1    | s_nonzero |
+            // |-----|-------|-----------|
+            // | a   | a_inv | s_nonzero |
+
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let a_inv = meta.query_advice(advice[1], Rotation::cur());
+            let s_nonzero = meta.query_selector(s_nonzero);
+
+            // a * a_inv = 1 has no solution when a = 0
+            vec![s_nonzero * (a * a_inv - Expression::Constant(F::ONE))]
+        });
+
+        meta.create_gate("affine", |meta| {
+            //
+            // | a0  | f0 | f1 | s_affine |
+            // |-----|----|----|----------|
+            // | x   | a  | b  | s_affine |
+            // | out |    |    |          |
+
+            let x = meta.query_advice(advice[0], Rotation::cur());
+            let out = meta.query_advice(advice[0], Rotation::next());
+            let a = meta.query_fixed(coeff[0]);
+            let b = meta.query_fixed(coeff[1]);
+            let s_affine = meta.query_selector(s_affine);
+
+            vec![s_affine * (a * x + b - out)]
+        });
+
+        // boolean gates share the layout of mul and add
+        //
+        // | a0  | a1  | s_xor |
+        // |-----|-----|-------|
+        // | lhs | rhs | s_xor |
+        // | out |     |       |
+        //
+        // and also constrain both inputs to be 0 or 1.
+        let boolean =
+            |meta: &mut VirtualCells<F>,
+             selector: Selector,
+             op: fn(Expression<F>, Expression<F>) -> Expression<F>| {
+                let lhs = meta.query_advice(advice[0], Rotation::cur());
+                let rhs = meta.query_advice(advice[1], Rotation::cur());
+                let out = meta.query_advice(advice[0], Rotation::next());
+                let s = meta.query_selector(selector);
+                let one = Expression::Constant(F::ONE);
+
+                vec![
+                    s.clone() * lhs.clone() * (one.clone() - lhs.clone()),
+                    s.clone() * rhs.clone() * (one - rhs.clone()),
+                    s * (op(lhs, rhs) - out),
+                ]
+            };
+
+        meta.create_gate("xor", |meta| {
+            boolean(meta, s_xor, |a, b| {
+                a.clone() + b.clone() - Expression::Constant(F::from(2)) * a * b
+            })
+        });
+        meta.create_gate("and", |meta| boolean(meta, s_and, |a, b| a * b));
+        meta.create_gate("or", |meta| {
+            boolean(meta, s_or, |a, b| a.clone() + b.clone() - a * b)
+        });
+
+        meta.create_gate("select", |meta| {
+            //
+            // | a0   | a1  | s_select |
+            // |------|-----|----------|
+            // | cond | a   | s_select |
+            // | b    | out |          |
+
+            let cond = meta.query_advice(advice[0], Rotation::cur());
+            let a = meta.query_advice(advice[1], Rotation::cur());
+            let b = meta.query_advice(advice[0], Rotation::next());
+            let out = meta.query_advice(advice[1], Rotation::next());
+            let s_select = meta.query_selector(s_select);
+
+            Constraints::with_selector(
+                s_select,
+                [
+                    cond.clone() * (Expression::Constant(F::ONE) - cond.clone()),
+                    cond * (a - b.clone()) + b - out,
+                ],
+            )
+        });
+
+        meta.create_gate("decompose", |meta| {
+            //
+            // | a0    | a1    | f0   | f1   | s_decompose |
+            // |-------|-------|------|------|-------------|
+            // | bit   | acc   | 2^j  | more | s_decompose |
+            // |       | rest  |      |      |             |
+            //
+            // acc = bit * 2^j + rest, the last row has more = 0 so that
+            // the remaining bits must be zero.
+
+            let bit = meta.query_advice(advice[0], Rotation::cur());
+            let acc = meta.query_advice(advice[1], Rotation::cur());
+            let rest = meta.query_advice(advice[1], Rotation::next());
+            let pow = meta.query_fixed(coeff[0]);
+            let more = meta.query_fixed(coeff[1]);
+            let s_decompose = meta.query_selector(s_decompose);
+
+            Constraints::with_selector(
+                s_decompose,
+                [
+                    bit.clone() * (Expression::Constant(F::ONE) - bit.clone()),
+                    acc - bit * pow - more * rest,
+                ],
+            )
+        });
+
+        meta.create_gate("less than", |meta| {
+            //
+            // | a0  | a1  | f0     | s_lt |
+            // |-----|-----|--------|------|
+            // | a   | b   | 2^bits | s_lt |
+            // |     | d   |        |      |
+            //
+            // d = b - a - 1 + 2^bits has its top bit set iff a < b
+
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let d = meta.query_advice(advice[1], Rotation::next());
+            let offset = meta.query_fixed(coeff[0]);
+            let s_lt = meta.query_selector(s_lt);
+
+            vec![s_lt * (b - a - Expression::Constant(F::ONE) + offset - d)]
+        });
+
+        meta.create_gate("is equal", |meta| {
+            //
+            // | a0  | a1  | s_eq |
+            // |-----|-----|------|
+            // | a   | b   | s_eq |
+            // | inv | out |      |
+            //
+            // out = 1 - (a - b) * inv is 1 iff a = b, since (a - b) * out = 0
+            // forces out = 0 when a != b.
+
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let inv = meta.query_advice(advice[0], Rotation::next());
+            let out = meta.query_advice(advice[1], Rotation::next());
+            let s_eq = meta.query_selector(s_eq);
+
+            let diff = a - b;
+            Constraints::with_selector(
+                s_eq,
+                [
+                    Expression::Constant(F::ONE) - diff.clone() * inv - out.clone(),
+                    diff * out,
+                ],
+            )
+        });
+
         ArthConfig {
             advice,
             instance,
+            coeff,
             s_mul,
             s_add,
             s_cube,
+            s_mul_add,
+            s_nonzero,
+            s_affine,
+            s_xor,
+            s_and,
+            s_or,
+            s_select,
+            s_decompose,
+            s_lt,
+            s_eq,
+        }
+    }
+}
+
+/// The comparators `(i, j)`, `i < j`, of Batcher's odd-even merge sort of
+/// `n` values in the order they apply. Pairs reaching past `n` are dropped,
+/// as if the missing values were larger than every other.
+pub fn batcher_comparators(n: usize) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    let mut p = 1;
+    while p < n {
+        let mut k = p;
+        while k >= 1 {
+            let mut j = k % p;
+            while j + k < n {
+                for i in 0..k.min(n - j - k) {
+                    if (i + j) / (2 * p) == (i + j + k) / (2 * p) {
+                        pairs.push((i + j, i + j + k));
+                    }
+                }
+                j += 2 * k;
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+    pairs
+}
+
+/// The lowest `bits` bits of `v` as an integer, at most 128 of them
+fn to_u128<F: PrimeField>(v: &F, bits: usize) -> u128 {
+    le_bits(v, bits.min(128))
+        .into_iter()
+        .rev()
+        .fold(0, |acc, b| acc << 1 | b as u128)
+}
+
+/// The lowest `bits` bits of `v`, least significant first
+fn le_bits<F: PrimeField>(v: &F, bits: usize) -> Vec<bool> {
+    let repr = v.to_repr();
+    // the representation of the supported fields is little endian
+    (0..bits)
+        .map(|i| repr.as_ref()[i / 8] >> (i % 8) & 1 == 1)
+        .collect()
+}
+
+impl<F: PrimeField> ArthChip<F> {
+    /// Decompose `value` into `bits` bits from the most significant one,
+    /// starting at `offset`. Returns the bit and accumulator cells per row,
+    /// the accumulator of the first row holds `value`.
+    fn decompose(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        value: Value<F>,
+        bits: usize,
+    ) -> Result<Vec<[AssignedCell<F, F>; 2]>, Error> {
+        let config = self.config();
+        assert!(bits > 0 && bits < F::NUM_BITS as usize);
+
+        let le_bits = value.map(|v| le_bits(&v, bits));
+
+        let mut acc = value;
+        let mut cells = vec![];
+        for (row, j) in (0..bits).rev().enumerate() {
+            let row = offset + row;
+            config.s_decompose.enable(region, row)?;
+
+            let bit = le_bits
+                .as_ref()
+                .map(|b| if b[j] { F::ONE } else { F::ZERO });
+            let pow = (0..j).fold(F::ONE, |p, _| p.double());
+            region.assign_fixed(|| "2^j", config.coeff[0], row, || Value::known(pow))?;
+            let more = if j > 0 { F::ONE } else { F::ZERO };
+            region.assign_fixed(|| "more", config.coeff[1], row, || Value::known(more))?;
+
+            cells.push([
+                region.assign_advice(|| "bit", config.advice[0], row, || bit)?,
+                region.assign_advice(|| "acc", config.advice[1], row, || acc)?,
+            ]);
+
+            acc = acc - bit * Value::known(pow);
         }
+        // queried by the last row but multiplied by zero
+        region.assign_advice(
+            || "rest",
+            config.advice[1],
+            offset + bits,
+            || Value::known(F::ZERO),
+        )?;
+
+        Ok(cells)
+    }
+
+    /// Assign `lhs`, `rhs` and `op(lhs, rhs)` for one of the boolean gates
+    fn boolean(
+        &self,
+        mut layouter: impl Layouter<F>,
+        name: &str,
+        selector: Selector,
+        a: Number<F>,
+        b: Number<F>,
+        op: fn(F, F) -> F,
+    ) -> Result<Number<F>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || name,
+            |mut region: Region<'_, F>| {
+                selector.enable(&mut region, 0)?;
+
+                a.value
+                    .copy_advice(|| "lhs", &mut region, config.advice[0], 0)?;
+                b.value
+                    .copy_advice(|| "rhs", &mut region, config.advice[1], 0)?;
+
+                let value = a
+                    .value
+                    .value()
+                    .zip(b.value.value())
+                    .map(|(a, b)| op(*a, *b));
+
+                region
+                    .assign_advice(|| name, config.advice[0], 1, || value)
+                    .map(|x| Number { value: x })
+            },
+        )
     }
 }
 
@@ -267,6 +1023,56 @@ impl<F: PrimeField> NumericInstructions<F> for ArthChip<F> {
         )
     }
 
+    fn mul_add(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        c: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "mul add",
+            |mut region: Region<'_, F>| {
+                config.s_mul_add.enable(&mut region, 0)?;
+
+                a.value
+                    .copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.value
+                    .copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+                c.value
+                    .copy_advice(|| "c", &mut region, config.advice[0], 1)?;
+
+                let value = a.value.value().copied() * b.value.value() + c.value.value();
+                region
+                    .assign_advice(|| "a * b + c", config.advice[1], 1, || value)
+                    .map(|x| Number { value: x })
+            },
+        )
+    }
+
+    fn inner_product(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &[Self::Num],
+        b: &[Self::Num],
+    ) -> Result<Self::Num, Error> {
+        assert_eq!(a.len(), b.len());
+        assert!(!a.is_empty());
+
+        let mut acc = self.mul(layouter.namespace(|| "a0 * b0"), a[0].clone(), b[0].clone())?;
+        for (x, y) in a.iter().zip(b).skip(1) {
+            acc = self.mul_add(
+                layouter.namespace(|| "ai * bi + acc"),
+                x.clone(),
+                y.clone(),
+                acc,
+            )?;
+        }
+        Ok(acc)
+    }
+
     fn cube(&self, mut layouter: impl Layouter<F>, a: Self::Num) -> Result<Self::Num, Error> {
         let config = self.config();
 
@@ -287,6 +1093,405 @@ impl<F: PrimeField> NumericInstructions<F> for ArthChip<F> {
         )
     }
 
+    fn pow(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        exp: u64,
+    ) -> Result<Self::Num, Error> {
+        assert!(exp > 0, "a ^ 0 is the constant 1");
+
+        // the leading bit of exp is a itself
+        let mut acc = a.clone();
+        for i in (0..exp.ilog2()).rev() {
+            acc = self.mul(layouter.namespace(|| "acc ^ 2"), acc.clone(), acc)?;
+            if (exp >> i) & 1 == 1 {
+                acc = self.mul(layouter.namespace(|| "acc * a"), acc, a.clone())?;
+            }
+        }
+        Ok(acc)
+    }
+
+    fn affine(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: F,
+        x: Self::Num,
+        b: F,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "affine",
+            |mut region: Region<'_, F>| {
+                config.s_affine.enable(&mut region, 0)?;
+
+                x.value
+                    .copy_advice(|| "x", &mut region, config.advice[0], 0)?;
+                region.assign_fixed(|| "a", config.coeff[0], 0, || Value::known(a))?;
+                region.assign_fixed(|| "b", config.coeff[1], 0, || Value::known(b))?;
+
+                let value = x.value.value().map(|x| a * x + b);
+
+                region
+                    .assign_advice(|| "a * x + b", config.advice[0], 1, || value)
+                    .map(|x| Number { value: x })
+            },
+        )
+    }
+
+    fn xor(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        self.boolean(layouter, "xor", self.config().s_xor, a, b, |a, b| {
+            a + b - a * b.double()
+        })
+    }
+
+    fn and(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        self.boolean(layouter, "and", self.config().s_and, a, b, |a, b| a * b)
+    }
+
+    fn or(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        self.boolean(layouter, "or", self.config().s_or, a, b, |a, b| {
+            a + b - a * b
+        })
+    }
+
+    fn select(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "select",
+            |mut region: Region<'_, F>| {
+                config.s_select.enable(&mut region, 0)?;
+
+                cond.value
+                    .copy_advice(|| "cond", &mut region, config.advice[0], 0)?;
+                a.value
+                    .copy_advice(|| "a", &mut region, config.advice[1], 0)?;
+                b.value
+                    .copy_advice(|| "b", &mut region, config.advice[0], 1)?;
+
+                let value = cond.value.value().copied()
+                    * (a.value.value().copied() - b.value.value())
+                    + b.value.value();
+                region
+                    .assign_advice(|| "out", config.advice[1], 1, || value)
+                    .map(|x| Number { value: x })
+            },
+        )
+    }
+
+    fn conditional_swap(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<(Self::Num, Self::Num), Error> {
+        let first = self.select(
+            layouter.namespace(|| "first"),
+            cond.clone(),
+            b.clone(),
+            a.clone(),
+        )?;
+        let second = self.select(layouter.namespace(|| "second"), cond, a, b)?;
+        Ok((first, second))
+    }
+
+    fn range_check(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        bits: usize,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "range check",
+            |mut region: Region<'_, F>| {
+                let rows = self.decompose(&mut region, 0, a.value.value().copied(), bits)?;
+                region.constrain_equal(rows[0][1].cell(), a.value.cell())
+            },
+        )
+    }
+
+    fn assert_nonneg(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        int_bits: usize,
+        frac_bits: usize,
+    ) -> Result<(), Error> {
+        assert!(
+            int_bits >= 1,
+            "assert_nonneg needs int_bits >= 1 to hold the sign bit, got 0"
+        );
+        if int_bits + frac_bits == 1 {
+            // the sign bit alone, clear only for zero
+            let zero = self.affine(layouter.namespace(|| "zero"), F::ZERO, a.clone(), F::ZERO)?;
+            let eq = self.is_equal(layouter.namespace(|| "a == 0"), a, zero)?;
+            return self.assert_nonzero(layouter.namespace(|| "nonneg"), eq);
+        }
+        self.range_check(layouter, a, int_bits + frac_bits - 1)
+    }
+
+    fn less_than(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        bits: usize,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "less than",
+            |mut region: Region<'_, F>| {
+                config.s_lt.enable(&mut region, 0)?;
+
+                a.value
+                    .copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.value
+                    .copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+                let offset = (0..bits).fold(F::ONE, |p, _| p.double());
+                region.assign_fixed(|| "2^bits", config.coeff[0], 0, || Value::known(offset))?;
+
+                let d = b.value.value().copied() - a.value.value() - Value::known(F::ONE)
+                    + Value::known(offset);
+                let rows = self.decompose(&mut region, 1, d, bits + 1)?;
+
+                // the most significant bit comes first
+                Ok(Number {
+                    value: rows[0][0].clone(),
+                })
+            },
+        )
+    }
+
+    fn is_equal(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "is equal",
+            |mut region: Region<'_, F>| {
+                config.s_eq.enable(&mut region, 0)?;
+
+                a.value
+                    .copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.value
+                    .copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+
+                // any inverse works for a = b, zero is as good as any
+                let diff = a.value.value().copied() - b.value.value();
+                let inv = diff.map(|d| Option::from(d.invert()).unwrap_or(F::ZERO));
+                let out = diff.map(|d| if d.is_zero_vartime() { F::ONE } else { F::ZERO });
+
+                region.assign_advice(|| "inv", config.advice[0], 1, || inv)?;
+                region
+                    .assign_advice(|| "a == b", config.advice[1], 1, || out)
+                    .map(|x| Number { value: x })
+            },
+        )
+    }
+
+    fn split(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        bits: usize,
+    ) -> Result<(Self::Num, Self::Num), Error> {
+        // both halves are unique only if they fit the field together
+        assert!(2 * bits < F::NUM_BITS as usize);
+        let pow = (0..bits).fold(F::ONE, |p, _| p.double());
+
+        let lo = a.value.value().map(|v| {
+            le_bits(v, bits).into_iter().rev().fold(F::ZERO, |acc, b| {
+                if b {
+                    acc.double() + F::ONE
+                } else {
+                    acc.double()
+                }
+            })
+        });
+        let hi = (a.value.value().copied() - lo) * Value::known(pow.invert().unwrap());
+
+        let hi = self.load_private(layouter.namespace(|| "hi"), hi)?;
+        let lo = self.load_private(layouter.namespace(|| "lo"), lo)?;
+        self.range_check(layouter.namespace(|| "hi range"), hi.clone(), bits)?;
+        self.range_check(layouter.namespace(|| "lo range"), lo.clone(), bits)?;
+
+        let shifted = self.affine(
+            layouter.namespace(|| "hi * 2^bits"),
+            pow,
+            hi.clone(),
+            F::ZERO,
+        )?;
+        let joined = self.add(layouter.namespace(|| "+ lo"), shifted, lo.clone())?;
+        layouter.assign_region(
+            || "split",
+            |mut region: Region<'_, F>| region.constrain_equal(joined.value.cell(), a.value.cell()),
+        )?;
+
+        Ok((hi, lo))
+    }
+
+    fn unpack_flags(
+        &self,
+        mut layouter: impl Layouter<F>,
+        packed: Self::Num,
+        count: usize,
+    ) -> Result<Vec<Self::Num>, Error> {
+        layouter.assign_region(
+            || "unpack flags",
+            |mut region: Region<'_, F>| {
+                let rows = self.decompose(&mut region, 0, packed.value.value().copied(), count)?;
+                region.constrain_equal(rows[0][1].cell(), packed.value.cell())?;
+
+                // the most significant bit comes first
+                Ok(rows
+                    .into_iter()
+                    .rev()
+                    .map(|[bit, _]| Number { value: bit })
+                    .collect())
+            },
+        )
+    }
+
+    fn mul_mod_small(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+        modulus: F,
+        bits: usize,
+    ) -> Result<Self::Num, Error> {
+        // q * modulus + r stays below 2^(3 * bits) and must not wrap around
+        assert!(bits <= 64 && 3 * bits < F::NUM_BITS as usize);
+        let m = to_u128(&modulus, 128);
+        assert!(
+            F::from_u128(m) == modulus && m > 0 && m <= 1 << bits,
+            "modulus must fit in {bits} bits"
+        );
+
+        let product = self.mul(layouter.namespace(|| "a * b"), a, b)?;
+        let ab = product.value.value().map(|v| to_u128(v, 2 * bits));
+        let q = self.load_private(
+            layouter.namespace(|| "q"),
+            ab.map(|ab| F::from_u128(ab / m)),
+        )?;
+        let r = self.load_private(
+            layouter.namespace(|| "r"),
+            ab.map(|ab| F::from_u128(ab % m)),
+        )?;
+
+        // q < 2^(2 * bits), r < 2^bits and modulus - 1 - r < 2^bits
+        self.range_check(layouter.namespace(|| "q range"), q.clone(), 2 * bits)?;
+        self.range_check(layouter.namespace(|| "r range"), r.clone(), bits)?;
+        let gap = self.affine(
+            layouter.namespace(|| "modulus - 1 - r"),
+            -F::ONE,
+            r.clone(),
+            modulus - F::ONE,
+        )?;
+        self.range_check(layouter.namespace(|| "r < modulus"), gap, bits)?;
+
+        let qm = self.affine(layouter.namespace(|| "q * modulus"), modulus, q, F::ZERO)?;
+        let joined = self.add(layouter.namespace(|| "+ r"), qm, r.clone())?;
+        layouter.assign_region(
+            || "mul mod",
+            |mut region: Region<'_, F>| {
+                region.constrain_equal(joined.value.cell(), product.value.cell())
+            },
+        )?;
+
+        Ok(r)
+    }
+
+    fn assert_sorted(
+        &self,
+        mut layouter: impl Layouter<F>,
+        nums: &[Self::Num],
+        bits: usize,
+        strict: bool,
+    ) -> Result<(), Error> {
+        for num in nums {
+            self.range_check(layouter.namespace(|| "sorted range"), num.clone(), bits)?;
+        }
+
+        for pair in nums.windows(2) {
+            let (a, b) = (pair[0].clone(), pair[1].clone());
+            let ordered = match strict {
+                // a < b
+                true => self.less_than(layouter.namespace(|| "a < b"), a, b, bits)?,
+                // not b < a
+                false => {
+                    let lt = self.less_than(layouter.namespace(|| "b < a"), b, a, bits)?;
+                    self.affine(layouter.namespace(|| "not"), -F::ONE, lt, F::ONE)?
+                }
+            };
+            self.assert_nonzero(layouter.namespace(|| "ordered"), ordered)?;
+        }
+        Ok(())
+    }
+
+    fn assert_nonzero(&self, mut layouter: impl Layouter<F>, a: Self::Num) -> Result<(), Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "nonzero",
+            |mut region: Region<'_, F>| {
+                config.s_nonzero.enable(&mut region, 0)?;
+
+                a.value
+                    .copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+
+                // zero has no inverse, witness zero and let the gate fail
+                let inv = a
+                    .value
+                    .value()
+                    .map(|v| Option::from(v.invert()).unwrap_or(F::ZERO));
+
+                region.assign_advice(|| "a_inv", config.advice[1], 0, || inv)?;
+                Ok(())
+            },
+        )
+    }
+
+    fn assert_not_equal(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<(), Error> {
+        let neg_b = self.affine(layouter.namespace(|| "-b"), -F::ONE, b, F::ZERO)?;
+        let diff = self.add(layouter.namespace(|| "a - b"), a, neg_b)?;
+        self.assert_nonzero(layouter.namespace(|| "a != b"), diff)
+    }
+
     fn expose_public(
         &self,
         mut layouter: impl Layouter<F>,
@@ -0,0 +1,66 @@
+use ff::PrimeField;
+use halo2_proofs::{circuit::Layouter, plonk::Error};
+
+use super::arth_chips::NumericInstructions;
+
+/// An arithmetic expression over loaded numbers, recorded once and replayed
+/// through `synthesize_expr` as many times as needed.
+/// e.g. `(x^3 + x) * y` is `x.clone().cube().add(x).mul(y)` with
+/// `x = Expr::input(0)` and `y = Expr::input(1)`.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    /// the i-th number handed to `synthesize_expr`
+    Input(usize),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Cube(Box<Expr>),
+}
+
+impl Expr {
+    pub fn input(i: usize) -> Self {
+        Expr::Input(i)
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, rhs: Expr) -> Self {
+        Expr::Add(Box::new(self), Box::new(rhs))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn mul(self, rhs: Expr) -> Self {
+        Expr::Mul(Box::new(self), Box::new(rhs))
+    }
+
+    pub fn cube(self) -> Self {
+        Expr::Cube(Box::new(self))
+    }
+}
+
+/// Replay `expr` with the chip, one region per operation.
+pub fn synthesize_expr<F: PrimeField, C: NumericInstructions<F>>(
+    chip: &C,
+    layouter: &mut impl Layouter<F>,
+    expr: &Expr,
+    inputs: &[C::Num],
+) -> Result<C::Num, Error>
+where
+    C::Num: Clone,
+{
+    match expr {
+        Expr::Input(i) => Ok(inputs[*i].clone()),
+        Expr::Add(a, b) => {
+            let a = synthesize_expr(chip, layouter, a, inputs)?;
+            let b = synthesize_expr(chip, layouter, b, inputs)?;
+            chip.add(layouter.namespace(|| "expr add"), a, b)
+        }
+        Expr::Mul(a, b) => {
+            let a = synthesize_expr(chip, layouter, a, inputs)?;
+            let b = synthesize_expr(chip, layouter, b, inputs)?;
+            chip.mul(layouter.namespace(|| "expr mul"), a, b)
+        }
+        Expr::Cube(a) => {
+            let a = synthesize_expr(chip, layouter, a, inputs)?;
+            chip.cube(layouter.namespace(|| "expr cube"), a)
+        }
+    }
+}
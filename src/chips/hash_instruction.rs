@@ -0,0 +1,19 @@
+use ff::PrimeField;
+use halo2_proofs::{circuit::Layouter, plonk::Error};
+
+use super::arth_chips::{ArthChip, Number};
+
+/// A hash other than poseidon, built from the gates of the arithmetic chip,
+/// together with its native counterpart. It derives the leaves of trees
+/// whose inner nodes are hashed with poseidon.
+pub trait HashInstruction<F: PrimeField> {
+    /// Hash `preimage` off circuit, as `hash` does in circuit.
+    fn hash_native(preimage: &[F]) -> Vec<F>;
+
+    /// Hash the loaded `preimage` into a digest of loaded numbers.
+    fn hash(
+        chip: &ArthChip<F>,
+        layouter: impl Layouter<F>,
+        preimage: &[Number<F>],
+    ) -> Result<Vec<Number<F>>, Error>;
+}
@@ -16,7 +16,7 @@ use halo2_proofs::{
 };
 
 #[derive(Debug, Clone)]
-pub struct Node<F: PrimeField, const I: usize>([AssignedCell<F, F>; I]);
+pub struct Node<F: PrimeField, const I: usize>(pub [AssignedCell<F, F>; I]);
 
 pub trait MerklePathInstruction<F: PrimeField, const I: usize>: Chip<F> {
     /// Variable representing a tree node
@@ -24,16 +24,35 @@ pub trait MerklePathInstruction<F: PrimeField, const I: usize>: Chip<F> {
 
     /// Loads a left child, a right child and paths
     /// return the final root
+    #[allow(clippy::too_many_arguments)]
     fn load_path(
         &self,
         layouter: &mut impl Layouter<F>,
         left: Vec<[AssignedCell<F, F>; I]>,
         right: Vec<[AssignedCell<F, F>; I]>,
         hash: Vec<[AssignedCell<F, F>; I]>,
-        copy: &Vec<Value<F>>,
+        copy: &[Value<F>],
         m: usize,
         n: usize,
-    ) -> Result<Self::Node, Error>;
+    ) -> Result<Self::Node, Error> {
+        let (root, _) = self.load_path_with_flags(layouter, left, right, hash, copy, m, n)?;
+        Ok(root)
+    }
+
+    /// Same as `load_path`, also returning the `m` assigned copy flags of
+    /// the layers below the root, zero for the hashed layers and one for the
+    /// copied ones.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    fn load_path_with_flags(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        left: Vec<[AssignedCell<F, F>; I]>,
+        right: Vec<[AssignedCell<F, F>; I]>,
+        hash: Vec<[AssignedCell<F, F>; I]>,
+        copy: &[Value<F>],
+        m: usize,
+        n: usize,
+    ) -> Result<(Self::Node, Vec<AssignedCell<F, F>>), Error>;
 
     /// Loads a left child, a right child
     /// return a node of its selection according to
@@ -43,7 +62,59 @@ pub trait MerklePathInstruction<F: PrimeField, const I: usize>: Chip<F> {
         layouter: &mut impl Layouter<F>,
         left: [AssignedCell<F, F>; I],
         right: [AssignedCell<F, F>; I],
-    ) -> Result<(), Error>;
+    ) -> Result<Self::Node, Error>;
+
+    /// Same as `load_path_with_flags`, also returning the node on the path
+    /// at each of `depths`, selected from the left and right node of that
+    /// layer by its public index bit. The leaves are at depth 0, every depth
+    /// must be below `m`.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    fn load_path_with_nodes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        left: Vec<[AssignedCell<F, F>; I]>,
+        right: Vec<[AssignedCell<F, F>; I]>,
+        hash: Vec<[AssignedCell<F, F>; I]>,
+        copy: &[Value<F>],
+        m: usize,
+        n: usize,
+        depths: &[usize],
+    ) -> Result<(Self::Node, Vec<Self::Node>, Vec<AssignedCell<F, F>>), Error> {
+        let nodes = depths
+            .iter()
+            .map(|&d| {
+                assert!(d < m, "the node at depth {d} is not below the root");
+                self.select_node(layouter, left[d].clone(), right[d].clone(), d)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let (root, flags) = self.load_path_with_flags(layouter, left, right, hash, copy, m, n)?;
+        Ok((root, nodes, flags))
+    }
+
+    /// Select the node on the path at `layer` from the left and right node
+    /// of that layer by the public index bit of the layer
+    fn select_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        left: [AssignedCell<F, F>; I],
+        right: [AssignedCell<F, F>; I],
+        layer: usize,
+    ) -> Result<Self::Node, Error>;
+
+    /// Assign a node without constraints, to be bound by later copies
+    fn witness_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        values: [Value<F>; I],
+    ) -> Result<Self::Node, Error>;
+
+    /// Assign the public index bit of `layer` without constraints, to be
+    /// bound by later copies
+    fn load_index(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        layer: usize,
+    ) -> Result<AssignedCell<F, F>, Error>;
 
     /// check the final result with index
     fn expose_public(
@@ -90,6 +161,12 @@ impl<F: PrimeField, const I: usize> MerklePathChip<F, I> {
         }
     }
 
+    /// advice columns passed to `configure`: one per value of a node, the
+    /// copy flag and the index flag
+    pub const fn num_advice_columns() -> usize {
+        I + 2
+    }
+
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         value: [Column<Advice>; I],
@@ -97,12 +174,19 @@ impl<F: PrimeField, const I: usize> MerklePathChip<F, I> {
         index_flag: Column<Advice>,
         public: Column<Instance>,
     ) -> <Self as Chip<F>>::Config {
+        assert!(
+            I >= 1,
+            "a merkle node holds at least one value, I must be at least 1"
+        );
+
         // equality checks for output and internal states
-        for i in 0..I {
-            meta.enable_equality(value[i]);
+        for column in &value {
+            meta.enable_equality(*column);
         }
 
+        meta.enable_equality(copy_flag);
         meta.enable_equality(index_flag);
+        meta.enable_equality(public);
 
         let s_hash = meta.selector();
         let s_pub = meta.selector();
@@ -153,6 +237,7 @@ impl<F: PrimeField, const I: usize> MerklePathChip<F, I> {
             // index is bool value
             // (1-copy)*(p_hash - (1-index)*left -index * right) is the hash constraint
             // copy*((1-index)*(p_left-left) + (index) (p_right - right)) is the copy constraint
+            // copy*(p_left - p_right) forces the duplicated root of padding layers to match
 
             let hash_constraint = (0..I)
                 .map(|i| {
@@ -170,6 +255,9 @@ impl<F: PrimeField, const I: usize> MerklePathChip<F, I> {
                             + index.clone() * (p_right_v[i].clone() - right_v[i].clone()))
                 })
                 .collect::<Vec<_>>();
+            let duplicate_constraint = (0..I)
+                .map(|i| copy.clone() * (p_left_v[i].clone() - p_right_v[i].clone()))
+                .collect::<Vec<_>>();
 
             let constraints = vec![
                 bool_constraint(n_copy.clone()),
@@ -179,7 +267,8 @@ impl<F: PrimeField, const I: usize> MerklePathChip<F, I> {
             ]
             .into_iter()
             .chain(hash_constraint)
-            .chain(copy_constraint);
+            .chain(copy_constraint)
+            .chain(duplicate_constraint);
             Constraints::with_selector(s_hash, constraints)
         });
 
@@ -237,16 +326,16 @@ impl<F: PrimeField, const I: usize> MerklePathChip<F, I> {
 impl<F: PrimeField, const I: usize> MerklePathInstruction<F, I> for MerklePathChip<F, I> {
     type Node = Node<F, I>;
 
-    fn load_path(
+    fn load_path_with_flags(
         &self,
         layouter: &mut impl Layouter<F>,
         left: Vec<[AssignedCell<F, F>; I]>,
         right: Vec<[AssignedCell<F, F>; I]>,
         hash: Vec<[AssignedCell<F, F>; I]>,
-        copy: &Vec<Value<F>>,
+        copy: &[Value<F>],
         m: usize,
         n: usize,
-    ) -> Result<Self::Node, Error> {
+    ) -> Result<(Self::Node, Vec<AssignedCell<F, F>>), Error> {
         let config = self.config();
         assert_eq!(m + 1, right.len());
         assert_eq!(m + 1, left.len());
@@ -254,9 +343,10 @@ impl<F: PrimeField, const I: usize> MerklePathInstruction<F, I> for MerklePathCh
         assert_eq!(m, hash.len());
         assert!(n <= m);
 
-        layouter.assign_region(
+        let (root, flags) = layouter.assign_region(
             || "load path",
             |mut region: Region<'_, F>| {
+                let mut flags = vec![];
                 // from first n row we do the following
                 //
                 // |  value  | copy | index| s_hash|
@@ -292,12 +382,12 @@ impl<F: PrimeField, const I: usize> MerklePathInstruction<F, I> for MerklePathCh
 
                     config.s_hash.enable(&mut region, hash_pos)?;
 
-                    region.assign_advice(
+                    flags.push(region.assign_advice(
                         || "assign copy",
                         config.copy_flag,
                         hash_pos,
                         || copy[i],
-                    )?;
+                    )?);
                 }
 
                 // after the pathes are handled, we need to process root
@@ -333,12 +423,12 @@ impl<F: PrimeField, const I: usize> MerklePathInstruction<F, I> for MerklePathCh
 
                     config.s_hash.enable(&mut region, hash_pos)?;
 
-                    region.assign_advice(
+                    flags.push(region.assign_advice(
                         || "assign copy",
                         config.copy_flag,
                         hash_pos,
                         || copy[i],
-                    )?;
+                    )?);
                 }
 
                 // we assign index independently since it has different position
@@ -381,7 +471,7 @@ impl<F: PrimeField, const I: usize> MerklePathInstruction<F, I> for MerklePathCh
                             )
                             .expect("failed to get right root value");
 
-                        return left_v;
+                        left_v
                     })
                     .collect::<Vec<_>>()
                     .try_into()
@@ -401,7 +491,114 @@ impl<F: PrimeField, const I: usize> MerklePathInstruction<F, I> for MerklePathCh
                     cur_pos + 2,
                     || Value::known(F::ONE),
                 )?;
-                return Ok(Node(root));
+                Ok((Node(root), flags))
+            },
+        )?;
+
+        // layer i takes rows 3i..3i+3, the duplicated root the last three rows
+        #[cfg(feature = "trace")]
+        tracing::debug!(
+            region = "load path",
+            rows = ?(0..m * 3 + 3),
+            hashed = n,
+            "assigned region"
+        );
+
+        Ok((root, flags))
+    }
+
+    fn select_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        left: [AssignedCell<F, F>; I],
+        right: [AssignedCell<F, F>; I],
+        layer: usize,
+    ) -> Result<Self::Node, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "select node",
+            |mut region: Region<'_, F>| {
+                // the layout of load_leaves, with the chosen node witnessed
+                // |  value  | copy | index|  s_pub|
+                // |  left   |  *   |   *  |    0  |
+                // |  right  |  *   |   *  |    0  |
+                // |  chosen |  0   |  0/1 |    1  |
+                config.s_pub.enable(&mut region, 2)?;
+
+                let index = region.assign_advice_from_instance(
+                    || "assign index of the layer",
+                    config.public,
+                    I + layer,
+                    config.index_flag,
+                    2,
+                )?;
+                let is_right = index.value().map(|b| *b == F::ONE);
+
+                let mut chosen = vec![];
+                for j in 0..I {
+                    let l =
+                        left[j].copy_advice(|| "assign left", &mut region, config.value[j], 0)?;
+                    let r =
+                        right[j].copy_advice(|| "assign right", &mut region, config.value[j], 1)?;
+                    let value = is_right
+                        .zip(l.value().zip(r.value()))
+                        .map(|(is_right, (l, r))| if is_right { *r } else { *l });
+                    chosen.push(region.assign_advice(
+                        || "chosen node",
+                        config.value[j],
+                        2,
+                        || value,
+                    )?);
+                }
+
+                region.assign_advice(
+                    || "assign copy",
+                    config.copy_flag,
+                    2,
+                    || Value::known(F::ZERO),
+                )?;
+
+                Ok(Node(chosen.try_into().unwrap()))
+            },
+        )
+    }
+
+    fn witness_node(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        values: [Value<F>; I],
+    ) -> Result<Self::Node, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "witness node",
+            |mut region: Region<'_, F>| {
+                let node = (0..I)
+                    .map(|j| region.assign_advice(|| "node", config.value[j], 0, || values[j]))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Node(node.try_into().unwrap()))
+            },
+        )
+    }
+
+    fn load_index(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        layer: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "load index",
+            |mut region: Region<'_, F>| {
+                region.assign_advice_from_instance(
+                    || "index",
+                    config.public,
+                    I + layer,
+                    config.index_flag,
+                    0,
+                )
             },
         )
     }
@@ -425,54 +622,52 @@ impl<F: PrimeField, const I: usize> MerklePathInstruction<F, I> for MerklePathCh
         layouter: &mut impl Layouter<F>,
         left: [AssignedCell<F, F>; I],
         right: [AssignedCell<F, F>; I],
-    ) -> Result<(), Error> {
+    ) -> Result<Self::Node, Error> {
         let config = self.config();
 
-        layouter
-            .assign_region(
-                || "load inputs",
-                |mut region: Region<'_, F>| {
-                    // pub copy layer
-                    // |  value  | copy | index|  s_pub|
-                    // |  left   |  *   |   *  |    0  |
-                    // |  right  |  *   |   *  |    0  |
-                    // |  chosen |  0   |  0/1 |    1  |
-                    // ....
-                    // chosen = pub1,pub2, ... pubI
-
-                    config.s_pub.enable(&mut region, 2)?;
-                    for j in 0..I {
-                        left[j].copy_advice(|| "assign left", &mut region, config.value[j], 0)?;
-                        right[j].copy_advice(|| "assign right", &mut region, config.value[j], 1)?;
-                        region.assign_advice_from_instance(
-                            || "copy selected leaf from instance",
-                            config.public,
-                            j,
-                            config.value[j],
-                            2,
-                        )?;
-                    }
-
-                    region.assign_advice_from_instance(
-                        || "assign index for zero layer",
+        layouter.assign_region(
+            || "load inputs",
+            |mut region: Region<'_, F>| {
+                // pub copy layer
+                // |  value  | copy | index|  s_pub|
+                // |  left   |  *   |   *  |    0  |
+                // |  right  |  *   |   *  |    0  |
+                // |  chosen |  0   |  0/1 |    1  |
+                // ....
+                // chosen = pub1,pub2, ... pubI
+
+                config.s_pub.enable(&mut region, 2)?;
+                let mut chosen = vec![];
+                for j in 0..I {
+                    left[j].copy_advice(|| "assign left", &mut region, config.value[j], 0)?;
+                    right[j].copy_advice(|| "assign right", &mut region, config.value[j], 1)?;
+                    chosen.push(region.assign_advice_from_instance(
+                        || "copy selected leaf from instance",
                         config.public,
-                        I,
-                        config.index_flag,
+                        j,
+                        config.value[j],
                         2,
-                    )?;
+                    )?);
+                }
 
-                    region.assign_advice(
-                        || "assign copy",
-                        config.copy_flag,
-                        2,
-                        || Value::known(F::ZERO),
-                    )?;
+                region.assign_advice_from_instance(
+                    || "assign index for zero layer",
+                    config.public,
+                    I,
+                    config.index_flag,
+                    2,
+                )?;
 
-                    Ok(())
-                },
-            )
-            .unwrap();
-        return Ok(());
+                region.assign_advice(
+                    || "assign copy",
+                    config.copy_flag,
+                    2,
+                    || Value::known(F::ZERO),
+                )?;
+
+                Ok(Node(chosen.try_into().unwrap()))
+            },
+        )
     }
 }
 
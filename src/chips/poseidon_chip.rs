@@ -1,4 +1,4 @@
-use std::{marker::PhantomData, vec};
+use std::{fmt, marker::PhantomData, vec};
 
 use ff::PrimeField;
 use halo2_proofs::{
@@ -9,6 +9,8 @@ use halo2_proofs::{
     poly::Rotation,
 };
 
+use crate::native::spec::Spec;
+
 #[derive(Clone)]
 pub struct States<F: PrimeField, const WIDTH: usize>(pub [Data<F>; WIDTH]);
 
@@ -30,7 +32,7 @@ pub trait PoseidonInstructions<F: PrimeField, const WIDTH: usize>: Chip<F> {
         &self,
         layouter: &mut impl Layouter<F>,
         states: Self::State,
-        inputs: &Vec<Value<F>>,
+        inputs: &[Value<F>],
     ) -> Result<(Self::State, Vec<Self::Data>), Error>;
 
     // permutation with given number of full rounds and partial rounds
@@ -49,6 +51,122 @@ pub trait PoseidonInstructions<F: PrimeField, const WIDTH: usize>: Chip<F> {
         states: Self::State,
         size: usize,
     ) -> Result<(), Error>;
+
+    /// Same as `expose_public`, starting at row `offset` of the output
+    fn expose_public_at(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        states: Self::State,
+        size: usize,
+        offset: usize,
+    ) -> Result<(), Error>;
+
+    /// Start the next of several messages hashed with the same chip from the
+    /// finished `state`: the rate lanes go back to zero and the capacity lane
+    /// to the capacity. The new state takes one row, like `initiate`, on
+    /// which a gate binds it to those values.
+    fn reset(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: Self::State,
+    ) -> Result<Self::State, Error>;
+
+    /// Pad one element of `S::element_size()` inputs, absorb it and permute.
+    fn absorb<S: Spec<F, WIDTH>>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: Self::State,
+        element: &[Value<F>],
+    ) -> Result<Self::State, Error> {
+        let (state, _) = self.absorb_loaded::<S>(layouter, state, element)?;
+        Ok(state)
+    }
+
+    /// Same as `absorb`, also returning the cells the element was loaded
+    /// into, without the padding.
+    fn absorb_loaded<S: Spec<F, WIDTH>>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: Self::State,
+        element: &[Value<F>],
+    ) -> Result<(Self::State, Vec<Self::Data>), Error> {
+        let size = S::element_size();
+        assert_eq!(element.len(), size);
+        let padded = element
+            .iter()
+            .copied()
+            .chain(S::pad().into_iter().map(Value::known))
+            .collect::<Vec<_>>();
+        let (state, loaded) = self.load_inputs(layouter, state, &padded)?;
+        let state = self.permutation(layouter, state, S::full_rounds(), S::partial_rounds())?;
+        Ok((state, loaded.into_iter().take(size).collect()))
+    }
+
+    /// Hash two nodes of one element each into a fresh sponge, absorbing the
+    /// left node then the right node. Matches the native `compress`.
+    fn hash_two<S: Spec<F, WIDTH>>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        left: &[Value<F>],
+        right: &[Value<F>],
+    ) -> Result<Self::State, Error> {
+        let (state, _) = self.hash_two_loaded::<S>(layouter, left, right)?;
+        Ok(state)
+    }
+
+    /// Same as `hash_two`, also returning the cells the left and right
+    /// nodes were loaded into.
+    #[allow(clippy::type_complexity)]
+    fn hash_two_loaded<S: Spec<F, WIDTH>>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        left: &[Value<F>],
+        right: &[Value<F>],
+    ) -> Result<(Self::State, [Vec<Self::Data>; 2]), Error> {
+        let state = self.initiate(layouter)?;
+        let (state, left) = self.absorb_loaded::<S>(layouter, state, left)?;
+        let (state, right) = self.absorb_loaded::<S>(layouter, state, right)?;
+        Ok((state, [left, right]))
+    }
+
+    /// Absorb a fixed number of inputs into a fresh sponge for spec `S`.
+    /// An empty array does not compile, `N` must be a multiple of
+    /// `S::element_size()`.
+    fn hash_array<S: Spec<F, WIDTH>, const N: usize>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        inputs: [Value<F>; N],
+    ) -> Result<Self::State, Error> {
+        const { assert!(N > 0, "nothing to hash") };
+        let size = S::element_size();
+        assert_eq!(N % size, 0, "inputs must fill whole elements");
+
+        let mut state = self.initiate(layouter)?;
+        for c in inputs.chunks(size) {
+            state = self.absorb::<S>(layouter, state, c)?;
+        }
+        Ok(state)
+    }
+
+    /// Absorb the blocks one at a time as they are produced, so the inputs
+    /// never need to be collected. `RATE` must equal `S::element_size()`
+    /// and there must be at least one block.
+    fn hash_blocks<S: Spec<F, WIDTH>, const RATE: usize>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        blocks: impl Iterator<Item = [Value<F>; RATE]>,
+    ) -> Result<Self::State, Error> {
+        assert_eq!(RATE, S::element_size(), "a block is one element");
+
+        let mut state = self.initiate(layouter)?;
+        let mut absorbed = 0;
+        for block in blocks {
+            state = self.absorb::<S>(layouter, state, &block)?;
+            absorbed += 1;
+        }
+        assert!(absorbed > 0, "nothing to hash");
+        Ok(state)
+    }
 }
 
 pub struct PoseidonChip<F: PrimeField, const WIDTH: usize> {
@@ -56,29 +174,90 @@ pub struct PoseidonChip<F: PrimeField, const WIDTH: usize> {
     _marker: PhantomData<F>,
 }
 
+/// a native poseidon round: the state after round `usize` of the input state
+type NativeRound<F, const WIDTH: usize> = fn([F; WIDTH], usize) -> [F; WIDTH];
+
 #[derive(Clone, Debug)]
 pub struct PoseidonArthConfig<F: PrimeField, const WIDTH: usize> {
-    /// one private input for states
-    state: [Column<Advice>; WIDTH],
+    /// private states, one group of WIDTH columns per lane.
+    /// lane 0 holds the loaded states, each further lane holds one more round
+    /// on the same row.
+    state: Vec<[Column<Advice>; WIDTH]>,
 
-    // two fixed colum for arc parameters and mds
-    arc: [Column<Fixed>; WIDTH],
+    // fixed columns for arc parameters, one group per lane
+    arc: Vec<[Column<Fixed>; WIDTH]>,
 
     /// This is the public input (instance) column.
     output: Column<Instance>,
 
-    // selectors to enable the gate
-    s_fbox: Selector,
-    s_pbox: Selector,
+    // selectors to enable the gate, round boxes have one per lane
+    s_fbox: Vec<Selector>,
+    s_pbox: Vec<Selector>,
     s_add_inputs: Selector,
+    s_reset: Selector,
 
     // const parameters
     arc_paras: Vec<[F; WIDTH]>,
     mds: [[F; WIDTH]; WIDTH],
     capacity: u128,
+
+    // native round the witnessed rounds are checked against with `debug-check`
+    reference: Option<NativeRound<F, WIDTH>>,
+}
+
+impl<F: PrimeField, const WIDTH: usize> PoseidonArthConfig<F, WIDTH> {
+    /// the instance column the digests are exposed to
+    pub(crate) fn output(&self) -> Column<Instance> {
+        self.output
+    }
+
+    /// With the `debug-check` feature, every round witnessed by `permutation`
+    /// is compared to the native round of spec `S`, and synthesis panics with
+    /// the index of the first round that differs.
+    pub fn check_against<S: Spec<F, WIDTH>>(mut self) -> Self {
+        self.reference = Some(crate::native::poseidon::round::<F, S, WIDTH>);
+        self
+    }
+}
+
+/// Why `PoseidonChip::configure` rejected its parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// the mds passed in is not the one of the spec, the circuit would prove
+    /// a different hash than the native one
+    MdsMismatch { row: usize, column: usize },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MdsMismatch { row, column } => write!(
+                f,
+                "the configured mds differs from the spec at row {row}, column {column}"
+            ),
+        }
+    }
 }
 
 impl<F: PrimeField, const WIDTH: usize> PoseidonChip<F, WIDTH> {
+    /// rows taken by `initiate`
+    pub const INITIATE_ROWS: usize = 1;
+
+    /// rows taken by `load_inputs`: previous state, inputs and new state
+    pub const LOAD_ROWS: usize = 3;
+
+    /// rows taken by `permutation` with `lanes` rounds per row, the last row
+    /// holds the output state
+    pub const fn permutation_rows(full_round: usize, partial_round: usize, lanes: usize) -> usize {
+        (full_round + partial_round) / lanes + 1
+    }
+
+    /// advice columns passed to `configure`, one per value of the state;
+    /// `configure_unrolled` takes that many per lane
+    pub const fn num_advice_columns() -> usize {
+        WIDTH
+    }
+
     pub fn new(config: PoseidonArthConfig<F, WIDTH>) -> Self {
         PoseidonChip {
             config,
@@ -86,7 +265,8 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonChip<F, WIDTH> {
         }
     }
 
-    pub fn configure(
+    /// Configure the chip for spec `S`, the mds must be `S::mds()`.
+    pub fn configure<S: Spec<F, WIDTH>>(
         meta: &mut ConstraintSystem<F>,
         state: [Column<Advice>; WIDTH],
         output: Column<Instance>,
@@ -94,16 +274,54 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonChip<F, WIDTH> {
         mds: [[F; WIDTH]; WIDTH],
         arc_paras: Vec<[F; WIDTH]>,
         capacity: u128,
-    ) -> <Self as Chip<F>>::Config {
+    ) -> Result<<Self as Chip<F>>::Config, ConfigError> {
+        Self::configure_unrolled::<S>(
+            meta,
+            vec![state],
+            output,
+            vec![arc],
+            mds,
+            arc_paras,
+            capacity,
+        )
+    }
+
+    /// Same as `configure`, but lays out `state.len()` rounds per row.
+    /// Every extra lane costs WIDTH advice and WIDTH fixed columns and divides
+    /// the rows of a permutation by the number of lanes.
+    pub fn configure_unrolled<S: Spec<F, WIDTH>>(
+        meta: &mut ConstraintSystem<F>,
+        state: Vec<[Column<Advice>; WIDTH]>,
+        output: Column<Instance>,
+        arc: Vec<[Column<Fixed>; WIDTH]>,
+        mds: [[F; WIDTH]; WIDTH],
+        arc_paras: Vec<[F; WIDTH]>,
+        capacity: u128,
+    ) -> Result<<Self as Chip<F>>::Config, ConfigError> {
+        assert!(
+            WIDTH >= 2,
+            "a poseidon state of width {WIDTH} has no rate, the width must be at least 2"
+        );
+        // the mds is passed separately from the spec the circuit hashes with
+        for (row, (given, expected)) in mds.iter().zip(S::mds().iter()).enumerate() {
+            if let Some(column) = given.iter().zip(expected.iter()).position(|(a, b)| a != b) {
+                return Err(ConfigError::MdsMismatch { row, column });
+            }
+        }
+        let lanes = state.len();
+        assert!(lanes > 0);
+        assert_eq!(lanes, arc.len());
+
         // equality checks for output and internal states
         meta.enable_equality(output);
-        for column in &state {
+        for column in state.iter().flatten() {
             meta.enable_equality(*column);
         }
 
-        let s_fbox = meta.selector();
-        let s_pbox = meta.selector();
+        let s_fbox = (0..lanes).map(|_| meta.selector()).collect::<Vec<_>>();
+        let s_pbox = (0..lanes).map(|_| meta.selector()).collect::<Vec<_>>();
         let s_add_inputs = meta.selector();
+        let s_reset = meta.selector();
 
         let pow_5 = |v: Expression<F>| {
             let v2 = v.clone() * v.clone();
@@ -112,23 +330,25 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonChip<F, WIDTH> {
 
         let mix = |v: Vec<Expression<F>>, i: usize| {
             (0..WIDTH)
-                .map(|j| v[j].clone() * mds[i][j].clone())
+                .map(|j| v[j].clone() * mds[i][j])
                 .reduce(|acc, f| acc + f)
                 .unwrap()
         };
 
+        let loaded = state[0];
+
         // pad WIDTH-1 inputs and copy the last input
         meta.create_gate("add-inputs", |meta| {
             let pos = WIDTH - 1;
-            let initial_state_rate = meta.query_advice(state[pos], Rotation::prev());
-            let output_state_rate = meta.query_advice(state[pos], Rotation::next());
+            let initial_state_rate = meta.query_advice(loaded[pos], Rotation::prev());
+            let output_state_rate = meta.query_advice(loaded[pos], Rotation::next());
 
             let s_add_inputs = meta.query_selector(s_add_inputs);
 
             let pad_and_add = |idx: usize| {
-                let initial_state = meta.query_advice(state[idx], Rotation::prev());
-                let input = meta.query_advice(state[idx], Rotation::cur());
-                let output_state = meta.query_advice(state[idx], Rotation::next());
+                let initial_state = meta.query_advice(loaded[idx], Rotation::prev());
+                let input = meta.query_advice(loaded[idx], Rotation::cur());
+                let output_state = meta.query_advice(loaded[idx], Rotation::next());
 
                 // We pad the input by storing the required padding in fixed columns and
                 // then constraining the corresponding input columns to be equal to it.
@@ -145,70 +365,104 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonChip<F, WIDTH> {
             )
         });
 
-        // Apply full round over the states
-        meta.create_gate("full box", |meta| {
-            let states: Vec<Expression<F>> = (0..WIDTH)
-                .map(|i| meta.query_advice(state[i], Rotation::cur()))
-                .collect();
-            let next_states: Vec<Expression<F>> = (0..WIDTH)
-                .map(|i| meta.query_advice(state[i], Rotation::next()))
-                .collect();
-            let arcs: Vec<Expression<F>> = (0..WIDTH)
-                .map(|i| meta.query_any(arc[i], Rotation::cur()))
-                .collect();
-
-            let s_fbox = meta.query_selector(s_fbox);
-
-            // (s[i]+arc[i])^5
-            let mid: Vec<Expression<F>> = (0..WIDTH)
-                .map(|i| pow_5(states[i].clone() + arcs[i].clone()))
-                .collect();
-
-            (0..WIDTH).map(move |i| s_fbox.clone() * (mix(mid.clone(), i) - next_states[i].clone()))
+        // a reset state is zero on the rate and the capacity on the last value
+        meta.create_gate("reset", |meta| {
+            let s_reset = meta.query_selector(s_reset);
+            let initial = (0..WIDTH)
+                .map(|i| {
+                    let value = meta.query_advice(loaded[i], Rotation::cur());
+                    if i + 1 < WIDTH {
+                        value
+                    } else {
+                        value - Expression::Constant(F::from_u128(capacity))
+                    }
+                })
+                .collect::<Vec<_>>();
+            Constraints::with_selector(s_reset, initial)
         });
 
-        // Apply partial round over the states
-        meta.create_gate("partial box", |meta| {
-            let states: Vec<Expression<F>> = (0..WIDTH)
-                .map(|i| meta.query_advice(state[i], Rotation::cur()))
-                .collect();
-            let next_states: Vec<Expression<F>> = (0..WIDTH)
-                .map(|i| meta.query_advice(state[i], Rotation::next()))
-                .collect();
-            let arcs: Vec<Expression<F>> = (0..WIDTH)
-                .map(|i| meta.query_any(arc[i], Rotation::cur()))
-                .collect();
-            let s_pbox = meta.query_selector(s_pbox);
-            let mut mid = vec![pow_5(states[0].clone() + arcs[0].clone())];
-            mid.append(
-                &mut (1..WIDTH)
-                    .map(|i| states[i].clone() + arcs[i].clone())
-                    .collect(),
-            );
-
-            (0..WIDTH).map(move |i| s_pbox.clone() * (mix(mid.clone(), i) - next_states[i].clone()))
-        });
+        for lane in 0..lanes {
+            // a round reads its lane and writes the next lane on the same row,
+            // the last lane writes the first lane of the next row.
+            let (next, rotation) = if lane + 1 < lanes {
+                (state[lane + 1], Rotation::cur())
+            } else {
+                (state[0], Rotation::next())
+            };
+            let current = state[lane];
+            let arks = arc[lane];
+
+            // Apply full round over the states
+            meta.create_gate("full box", |meta| {
+                let states: Vec<Expression<F>> = (0..WIDTH)
+                    .map(|i| meta.query_advice(current[i], Rotation::cur()))
+                    .collect();
+                let next_states: Vec<Expression<F>> = (0..WIDTH)
+                    .map(|i| meta.query_advice(next[i], rotation))
+                    .collect();
+                let arcs: Vec<Expression<F>> = (0..WIDTH)
+                    .map(|i| meta.query_any(arks[i], Rotation::cur()))
+                    .collect();
+
+                let s_fbox = meta.query_selector(s_fbox[lane]);
+
+                // (s[i]+arc[i])^5
+                let mid: Vec<Expression<F>> = (0..WIDTH)
+                    .map(|i| pow_5(states[i].clone() + arcs[i].clone()))
+                    .collect();
+
+                (0..WIDTH)
+                    .map(move |i| s_fbox.clone() * (mix(mid.clone(), i) - next_states[i].clone()))
+            });
+
+            // Apply partial round over the states
+            meta.create_gate("partial box", |meta| {
+                let states: Vec<Expression<F>> = (0..WIDTH)
+                    .map(|i| meta.query_advice(current[i], Rotation::cur()))
+                    .collect();
+                let next_states: Vec<Expression<F>> = (0..WIDTH)
+                    .map(|i| meta.query_advice(next[i], rotation))
+                    .collect();
+                let arcs: Vec<Expression<F>> = (0..WIDTH)
+                    .map(|i| meta.query_any(arks[i], Rotation::cur()))
+                    .collect();
+                let s_pbox = meta.query_selector(s_pbox[lane]);
+                let mut mid = vec![pow_5(states[0].clone() + arcs[0].clone())];
+                mid.append(
+                    &mut (1..WIDTH)
+                        .map(|i| states[i].clone() + arcs[i].clone())
+                        .collect(),
+                );
+
+                (0..WIDTH)
+                    .map(move |i| s_pbox.clone() * (mix(mid.clone(), i) - next_states[i].clone()))
+            });
+        }
 
-        PoseidonArthConfig {
+        Ok(PoseidonArthConfig {
             state,
             arc,
             output,
             s_fbox,
             s_pbox,
             s_add_inputs,
+            s_reset,
             mds,
             arc_paras,
             capacity,
-        }
+            reference: None,
+        })
     }
-}
 
-impl<F: PrimeField, const WIDTH: usize> PoseidonInstructions<F, WIDTH> for PoseidonChip<F, WIDTH> {
-    type Data = Data<F>;
-
-    type State = States<F, WIDTH>;
-
-    fn initiate(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error> {
+    /// Assign the initial state on one row, zeros for the rate and the
+    /// capacity last, with `selector` enabled on that row if any.
+    fn assign_initial(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        name: &str,
+        cell: &str,
+        selector: Option<Selector>,
+    ) -> Result<States<F, WIDTH>, Error> {
         let config = self.config();
         let rate = WIDTH - 1;
         let mut init = vec![F::ZERO; rate];
@@ -216,42 +470,60 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonInstructions<F, WIDTH> for Posei
         // capacity element
         init.push(F::from_u128(config.capacity));
 
-        let states = layouter
-            .assign_region(
-                || "initiate states",
-                |mut region| {
-                    let state = (0..WIDTH)
-                        .map(|i| {
-                            region
-                                .assign_advice(
-                                    || format!("initial state {i}"),
-                                    config.state[i],
-                                    0,
-                                    || Value::known(init[i]),
-                                )
-                                .unwrap()
-                        })
-                        .map(Data)
-                        .collect::<Vec<_>>();
-                    Ok(States(state.try_into().unwrap()))
-                },
-            )
-            .unwrap();
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                if let Some(selector) = selector {
+                    selector.enable(&mut region, 0)?;
+                }
+                let state = (0..WIDTH)
+                    .map(|i| {
+                        region
+                            .assign_advice(
+                                || format!("{cell} {i}"),
+                                config.state[0][i],
+                                0,
+                                || Value::known(init[i]),
+                            )
+                            .map(Data)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(States(state.try_into().unwrap()))
+            },
+        )
+    }
+}
 
-        Ok(states)
+impl<F: PrimeField, const WIDTH: usize> PoseidonInstructions<F, WIDTH> for PoseidonChip<F, WIDTH> {
+    type Data = Data<F>;
+
+    type State = States<F, WIDTH>;
+
+    fn initiate(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error> {
+        self.assign_initial(layouter, "initiate states", "initial state", None)
+    }
+
+    fn reset(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        // nothing of the finished message carries over
+        _state: Self::State,
+    ) -> Result<Self::State, Error> {
+        let s_reset = self.config().s_reset;
+        self.assign_initial(layouter, "reset states", "reset state", Some(s_reset))
     }
 
     fn load_inputs(
         &self,
         layouter: &mut impl Layouter<F>,
         states: Self::State,
-        inputs: &Vec<Value<F>>,
+        inputs: &[Value<F>],
     ) -> Result<(Self::State, Vec<Self::Data>), Error> {
         let config = self.config();
 
         let rate = WIDTH - 1;
         // padding are done at circuit layer
-        assert_eq!(inputs.len() % rate, 0);
+        assert_eq!(inputs.len(), rate, "load one padded element at a time");
         layouter.assign_region(
             || "load inputs",
             |mut region: Region<'_, F>| {
@@ -261,51 +533,42 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonInstructions<F, WIDTH> for Posei
                     states.0[i].0.copy_advice(
                         || format!("load state {i}"),
                         &mut region,
-                        config.state[i],
+                        config.state[0][i],
                         0,
                     )?;
                 }
                 let input_data = (0..rate)
                     .map(|i| {
-                        Data(
-                            region
-                                .assign_advice(
-                                    || format!("load inputs {i}"),
-                                    config.state[i],
-                                    1,
-                                    || inputs[i].clone(),
-                                )
-                                .expect("failed to read inputs"),
-                        )
+                        region
+                            .assign_advice(
+                                || format!("load inputs {i}"),
+                                config.state[0][i],
+                                1,
+                                || inputs[i],
+                            )
+                            .map(Data)
                     })
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>, _>>()?;
                 let results = (0..WIDTH)
                     .map(|i| {
                         if i < rate {
-                            Data(
-                                region
-                                    .assign_advice(
-                                        || format!("load outputs {i}"),
-                                        config.state[i],
-                                        2,
-                                        || states.0[i].0.value().copied() + inputs[i],
-                                    )
-                                    .unwrap(),
+                            region.assign_advice(
+                                || format!("load outputs {i}"),
+                                config.state[0][i],
+                                2,
+                                || states.0[i].0.value().copied() + inputs[i],
                             )
                         } else {
-                            Data(
-                                region
-                                    .assign_advice(
-                                        || format!("load outputs {rate}"),
-                                        config.state[rate],
-                                        2,
-                                        || states.0[rate].0.value().copied(),
-                                    )
-                                    .unwrap(),
+                            region.assign_advice(
+                                || format!("load outputs {rate}"),
+                                config.state[0][rate],
+                                2,
+                                || states.0[rate].0.value().copied(),
                             )
                         }
+                        .map(Data)
                     })
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>, _>>()?;
 
                 Ok((States(results.try_into().unwrap()), input_data))
             },
@@ -339,85 +602,108 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonInstructions<F, WIDTH> for Posei
 
                 let pbox = |x: Value<F>| x * x * x * x * x;
                 // copy advices from previous state.
-                for i in 0..WIDTH {
+                for (i, output) in round_output.iter_mut().enumerate() {
                     states.0[i].0.copy_advice(
                         || format!("full round load state {i}"),
                         &mut region,
-                        config.state[i],
+                        config.state[0][i],
                         0,
                     )?;
-                    round_output[i] = states.0[i].0.value().copied();
+                    *output = states.0[i].0.value().copied();
                 }
+                // round r sits in lane r % lanes of row r / lanes
+                let lanes = config.state.len();
                 for r in 0..all {
                     let arc = config.arc_paras[r];
+                    let (row, lane) = (r / lanes, r % lanes);
+                    let (next_row, next_lane) = ((r + 1) / lanes, (r + 1) % lanes);
+                    #[cfg(feature = "debug-check")]
+                    let input = round_output;
 
                     // fill in fixed column
                     for i in 0..WIDTH {
                         region.assign_fixed(
                             || format!("round arcs {r}-{i}"),
-                            config.arc[i],
-                            r,
-                            || Value::known(arc[i].clone()),
+                            config.arc[lane][i],
+                            row,
+                            || Value::known(arc[i]),
                         )?;
 
-                        temp[i] = round_output[i] + Value::known(arc[i].clone());
+                        temp[i] = round_output[i] + Value::known(arc[i]);
                     }
 
                     if r < half_rounds || r >= mid {
                         // full rounds
-                        config.s_fbox.enable(&mut region, r)?;
+                        config.s_fbox[lane].enable(&mut region, row)?;
                         temp = temp
                             .into_iter()
-                            .map(|x| pbox(x))
+                            .map(pbox)
                             .collect::<Vec<_>>()
                             .try_into()
                             .unwrap();
                     } else {
                         // partial rounds
-                        config.s_pbox.enable(&mut region, r)?;
+                        config.s_pbox[lane].enable(&mut region, row)?;
                         temp[0] = pbox(temp[0]);
                     }
 
                     // apply mds
                     for i in 0..WIDTH {
                         let mut sum = Value::known(F::ZERO);
-                        for j in 0..WIDTH {
-                            sum = sum + temp[j] * Value::known(config.mds[i][j].clone());
+                        for (t, m) in temp.iter().zip(config.mds[i].iter()) {
+                            sum = sum + *t * Value::known(*m);
                         }
                         round_output[i] = sum;
 
-                        // fill in next row
+                        // fill in next lane
                         if outputs.len() < WIDTH {
-                            outputs.push(Data(
-                                region
-                                    .assign_advice(
-                                        || format!("round output {r}-{i}"),
-                                        config.state[i],
-                                        r + 1,
-                                        || round_output[i],
-                                    )
-                                    .unwrap(),
-                            ));
+                            outputs.push(Data(region.assign_advice(
+                                || format!("round output {r}-{i}"),
+                                config.state[next_lane][i],
+                                next_row,
+                                || round_output[i],
+                            )?));
                         } else {
-                            outputs[i] = Data(
-                                region
-                                    .assign_advice(
-                                        || format!("round output {r}-{i}"),
-                                        config.state[i],
-                                        r + 1,
-                                        || round_output[i],
-                                    )
-                                    .unwrap(),
-                            );
+                            outputs[i] = Data(region.assign_advice(
+                                || format!("round output {r}-{i}"),
+                                config.state[next_lane][i],
+                                next_row,
+                                || round_output[i],
+                            )?);
                         }
                     }
+
+                    #[cfg(feature = "debug-check")]
+                    if let Some(reference) = config.reference {
+                        let input: Value<Vec<F>> = input.into_iter().collect();
+                        let output: Value<Vec<F>> = round_output.into_iter().collect();
+                        input.zip(output).map(|(input, output)| {
+                            let expected = reference(input.try_into().unwrap(), r);
+                            assert_eq!(
+                                output, expected,
+                                "round {r} differs from the native permutation"
+                            );
+                        });
+                    }
                 }
 
                 Ok(States::<F, WIDTH>(outputs.clone().try_into().unwrap()))
             },
         )?;
 
-        return Ok(output_state);
+        // round r is in lane r % lanes of row r / lanes, outputs follow the last round
+        #[cfg(feature = "trace")]
+        {
+            let lanes = config.state.len();
+            tracing::debug!(
+                region = "permutation",
+                rows = ?(0..all / lanes + 1),
+                lanes,
+                "assigned region"
+            );
+        }
+
+        Ok(output_state)
     }
 
     fn expose_public(
@@ -425,14 +711,24 @@ impl<F: PrimeField, const WIDTH: usize> PoseidonInstructions<F, WIDTH> for Posei
         layouter: &mut impl Layouter<F>,
         states: Self::State,
         size: usize,
+    ) -> Result<(), Error> {
+        self.expose_public_at(layouter, states, size, 0)
+    }
+
+    fn expose_public_at(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        states: Self::State,
+        size: usize,
+        offset: usize,
     ) -> Result<(), Error> {
         let config = self.config();
 
         assert!(size < WIDTH);
         for i in 0..size {
-            layouter.constrain_instance(states.0[i].0.cell(), config.output, i)?;
+            layouter.constrain_instance(states.0[i].0.cell(), config.output, offset + i)?;
         }
-        return Ok(());
+        Ok(())
     }
 }
 
@@ -1,3 +1,95 @@
 pub mod arth_circuit;
+pub mod committed_root_circuit;
+pub mod disjoint_circuit;
+pub mod distinct_circuit;
+pub mod dual_merkle_circuit;
+pub mod hybrid_leaf_circuit;
+pub mod insert_circuit;
+pub mod intersection_circuit;
+pub mod leaf_count_circuit;
+pub mod median_circuit;
+pub mod merge_circuit;
 pub mod merkle_circuit;
+mod merkle_common;
+pub mod nested_circuit;
+pub mod note_circuit;
+pub mod nullifier_circuit;
+pub mod owner_circuit;
+pub mod pipeline;
 pub mod poseidon_circuit;
+pub mod pow_circuit;
+pub mod prefix_circuit;
+pub mod prf_circuit;
+pub mod quad_merkle_circuit;
+pub mod range_tree_circuit;
+pub mod sibling_path_circuit;
+pub mod sort_circuit;
+pub mod split_circuit;
+pub mod subtree_circuit;
+pub mod whitelist_circuit;
+
+use std::marker::PhantomData;
+
+use ff::Field;
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error, FloorPlanner};
+
+/// Known witnesses of `x`, as circuit constructors store them
+pub fn to_values<F: Field>(x: &[F]) -> Vec<Value<F>> {
+    x.iter().copied().map(Value::known).collect()
+}
+
+/// The witnesses of `values`, or `None` if any of them is unknown, as in a
+/// circuit built by `without_witnesses`
+pub fn unwrap_values<F: Field>(values: &[Value<F>]) -> Option<Vec<F>> {
+    values
+        .iter()
+        .map(|v| {
+            let mut known = None;
+            v.map(|x| known = Some(x));
+            known
+        })
+        .collect()
+}
+
+/// Circuit `C` laid out by the floor planner `P` instead of its own, e.g.
+/// `floor_planner::V1` to pack the regions of a circuit written for the
+/// `SimpleFloorPlanner`.
+pub struct WithPlanner<C, P> {
+    circuit: C,
+    _marker: PhantomData<P>,
+}
+
+impl<C, P> WithPlanner<C, P> {
+    pub fn new(circuit: C) -> Self {
+        WithPlanner {
+            circuit,
+            _marker: PhantomData,
+        }
+    }
+}
+
+// the planners are not `Clone`, only the circuit needs to be
+impl<C: Clone, P> Clone for WithPlanner<C, P> {
+    fn clone(&self) -> Self {
+        WithPlanner::new(self.circuit.clone())
+    }
+}
+
+impl<F: Field, C: Circuit<F>, P: FloorPlanner> Circuit<F> for WithPlanner<C, P> {
+    type Config = C::Config;
+
+    type FloorPlanner = P;
+
+    fn without_witnesses(&self) -> Self {
+        WithPlanner::new(self.circuit.without_witnesses())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        C::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.circuit.synthesize(config, layouter)
+    }
+}
@@ -1,4 +1,5 @@
 use super::super::chips::arth_chips::*;
+use super::to_values;
 
 use ff::PrimeField;
 use halo2_proofs::circuit::{SimpleFloorPlanner, Value};
@@ -9,7 +10,25 @@ pub struct DemoConfig1 {
     arth_config: ArthConfig,
 }
 
+impl DemoConfig1 {
+    fn configure<F: PrimeField>(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self {
+        // We create the two advice columns that uses for 2 fan-in inputs.
+        let advice = [meta.advice_column(), meta.advice_column()];
+
+        // We also need an instance column to store public inputs.
+        let instance = meta.instance_column();
+
+        // constant coefficients
+        let coeff = [meta.fixed_column(), meta.fixed_column()];
+
+        DemoConfig1 {
+            arth_config: ArthChip::configure(meta, advice, instance, coeff),
+        }
+    }
+}
+
 // (x^3+x)y=z
+// for a private x, with y and z in the first two rows of the instance column
 #[derive(Default)]
 pub struct DemoCircuit1<F: PrimeField> {
     x: Value<F>,
@@ -25,15 +44,7 @@ impl<F: PrimeField> Circuit<F> for DemoCircuit1<F> {
     }
 
     fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
-        // We create the two advice columns that uses for 2 fan-in inputs.
-        let advice = [meta.advice_column(), meta.advice_column()];
-
-        // We also need an instance column to store public inputs.
-        let instance = meta.instance_column();
-
-        DemoConfig1 {
-            arth_config: ArthChip::configure(meta, advice, instance),
-        }
+        DemoConfig1::configure(meta)
     }
 
     fn synthesize(
@@ -60,3 +71,273 @@ impl<F: PrimeField> DemoCircuit1<F> {
         }
     }
 }
+
+// x0 * x1 + x2 + ... + xn = y
+#[derive(Clone)]
+pub struct DemoCircuit2<F: PrimeField> {
+    xs: Vec<Value<F>>,
+}
+
+impl<F: PrimeField> Circuit<F> for DemoCircuit2<F> {
+    type Config = DemoConfig1;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        DemoCircuit2 {
+            xs: vec![Value::unknown(); self.xs.len()],
+        }
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
+        DemoConfig1::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl halo2_proofs::circuit::Layouter<F>,
+    ) -> Result<(), halo2_proofs::plonk::Error> {
+        let chip = ArthChip::new(config.arth_config);
+        let xs = self
+            .xs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| chip.load_private(layouter.namespace(|| format!("load x{i}")), *x))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut y = chip.mul(
+            layouter.namespace(|| "x0 * x1"),
+            xs[0].clone(),
+            xs[1].clone(),
+        )?;
+        for x in xs.into_iter().skip(2) {
+            y = chip.add(layouter.namespace(|| "y + xi"), y, x)?;
+        }
+
+        chip.expose_public(layouter.namespace(|| "expose y"), y, 0)
+    }
+}
+
+impl<F: PrimeField> DemoCircuit2<F> {
+    /// Proves the public `y = x0 * x1 + x2 + ...` for at least two private
+    /// inputs.
+    pub fn new(inputs: Vec<F>) -> DemoCircuit2<F> {
+        assert!(inputs.len() >= 2, "x0 * x1 takes two inputs");
+        DemoCircuit2 {
+            xs: to_values(&inputs),
+        }
+    }
+}
+
+// y = sum c_i * x^e_i
+#[derive(Clone)]
+pub struct PolyCircuit<F: PrimeField> {
+    x: Value<F>,
+    /// monomials as (coefficient, exponent)
+    terms: Vec<(F, u64)>,
+}
+
+impl<F: PrimeField> Circuit<F> for PolyCircuit<F> {
+    type Config = DemoConfig1;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        PolyCircuit {
+            x: Value::unknown(),
+            terms: self.terms.clone(),
+        }
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
+        DemoConfig1::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl halo2_proofs::circuit::Layouter<F>,
+    ) -> Result<(), halo2_proofs::plonk::Error> {
+        let chip = ArthChip::new(config.arth_config);
+        let x = chip.load_private(layouter.namespace(|| "load x"), self.x)?;
+
+        // constant terms are added once at the end
+        let mut constant = F::ZERO;
+        let mut sum = None;
+        for &(c, e) in &self.terms {
+            if e == 0 {
+                constant += c;
+                continue;
+            }
+            let power = chip.pow(layouter.namespace(|| format!("x ^ {e}")), x.clone(), e)?;
+            let term = chip.affine(layouter.namespace(|| "c * x ^ e"), c, power, F::ZERO)?;
+            sum = Some(match sum {
+                Some(acc) => chip.add(layouter.namespace(|| "acc + term"), acc, term)?,
+                None => term,
+            });
+        }
+
+        let y = match sum {
+            Some(acc) => chip.affine(layouter.namespace(|| "acc + c"), F::ONE, acc, constant)?,
+            None => chip.affine(layouter.namespace(|| "c"), F::ZERO, x, constant)?,
+        };
+        chip.expose_public(layouter.namespace(|| "expose y"), y, 0)
+    }
+}
+
+impl<F: PrimeField> PolyCircuit<F> {
+    /// Proves the public `y` is the sum of `c * x ^ e` over the `(c, e)` of
+    /// `terms`, for the private `x`.
+    pub fn new(terms: Vec<(F, u64)>, x: F) -> PolyCircuit<F> {
+        PolyCircuit {
+            x: Value::known(x),
+            terms,
+        }
+    }
+}
+
+// met = (sum w_i * v_i >= threshold)
+// for private boolean selections v_i and public weights w_i below 2^bits.
+// The instance column holds the weights, the threshold and then the result,
+// so a holder of voting power can show a quorum is met, or not, without
+// revealing which of the weights are theirs.
+#[derive(Clone)]
+pub struct ThresholdCircuit<F: PrimeField> {
+    votes: Vec<Value<F>>,
+    bits: usize,
+}
+
+impl<F: PrimeField> Circuit<F> for ThresholdCircuit<F> {
+    type Config = DemoConfig1;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ThresholdCircuit {
+            votes: vec![Value::unknown(); self.votes.len()],
+            bits: self.bits,
+        }
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
+        DemoConfig1::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl halo2_proofs::circuit::Layouter<F>,
+    ) -> Result<(), halo2_proofs::plonk::Error> {
+        let chip = ArthChip::new(config.arth_config);
+        let n = self.votes.len();
+
+        let mut votes = vec![];
+        let mut weights = vec![];
+        for (i, v) in self.votes.iter().enumerate() {
+            let v = chip.load_private(layouter.namespace(|| "load vote"), *v)?;
+            chip.range_check(layouter.namespace(|| "vote is boolean"), v.clone(), 1)?;
+            votes.push(v);
+
+            let w = chip.load_public(layouter.namespace(|| "load weight"), i)?;
+            chip.range_check(layouter.namespace(|| "weight bits"), w.clone(), self.bits)?;
+            weights.push(w);
+        }
+
+        // the total weight stays below 2^sum_bits
+        let sum_bits = self.bits + n.next_power_of_two().ilog2() as usize;
+        let total = chip.inner_product(layouter.namespace(|| "sum w * v"), &weights, &votes)?;
+        let threshold = chip.load_public(layouter.namespace(|| "load threshold"), n)?;
+        chip.range_check(
+            layouter.namespace(|| "threshold bits"),
+            threshold.clone(),
+            sum_bits,
+        )?;
+
+        let below = chip.less_than(
+            layouter.namespace(|| "total < threshold"),
+            total,
+            threshold,
+            sum_bits,
+        )?;
+        let met = chip.affine(layouter.namespace(|| "met"), -F::ONE, below, F::ONE)?;
+        chip.expose_public(layouter.namespace(|| "expose met"), met, n + 1)
+    }
+}
+
+impl<F: PrimeField> ThresholdCircuit<F> {
+    /// `votes` selects the weights counted toward the threshold, every public
+    /// weight must fit in `bits` bits.
+    pub fn new(votes: Vec<bool>, bits: usize) -> ThresholdCircuit<F> {
+        assert!(!votes.is_empty(), "at least one weight is needed");
+        ThresholdCircuit {
+            votes: votes
+                .into_iter()
+                .map(|v| Value::known(F::from(v as u64)))
+                .collect(),
+            bits,
+        }
+    }
+}
+
+// a_n of a_{k+2} = p * a_k + q * a_{k+1}
+// from the public first terms a_0 and a_1, with (p, q) = (1, 1) for the
+// Fibonacci-like sequences. Every step is its own region chained to the
+// previous ones by copies. The instance column is [a_0, a_1, a_n].
+#[derive(Clone)]
+pub struct SequenceCircuit<F: PrimeField> {
+    n: usize,
+    coeffs: Option<(F, F)>,
+}
+
+impl<F: PrimeField> Circuit<F> for SequenceCircuit<F> {
+    type Config = DemoConfig1;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
+        DemoConfig1::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl halo2_proofs::circuit::Layouter<F>,
+    ) -> Result<(), halo2_proofs::plonk::Error> {
+        let chip = ArthChip::new(config.arth_config);
+        let mut a = chip.load_public(layouter.namespace(|| "load a0"), 0)?;
+        let mut b = chip.load_public(layouter.namespace(|| "load a1"), 1)?;
+
+        for k in 2..=self.n {
+            let layouter = layouter.namespace(|| format!("a{k}"));
+            let next = match self.coeffs {
+                None => chip.fib_step(layouter, a, b.clone())?,
+                Some(coeffs) => chip.linear_step(layouter, a, b.clone(), coeffs)?,
+            };
+            (a, b) = (b, next);
+        }
+
+        let last = if self.n == 0 { a } else { b };
+        chip.expose_public(layouter.namespace(|| "expose an"), last, 2)
+    }
+}
+
+impl<F: PrimeField> SequenceCircuit<F> {
+    /// Proves the public `a_n` is the n-th term of the Fibonacci-like
+    /// sequence starting at the public `a_0` and `a_1`.
+    pub fn new(n: usize) -> SequenceCircuit<F> {
+        SequenceCircuit { n, coeffs: None }
+    }
+
+    /// Same as `new` for the recurrence `a_{k+2} = p * a_k + q * a_{k+1}`
+    pub fn with_coeffs(n: usize, p: F, q: F) -> SequenceCircuit<F> {
+        SequenceCircuit {
+            n,
+            coeffs: Some((p, q)),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::chips::merkle_chip::{MerklePathChip, MerklePathInstruction, Node};
+
+use super::super::chips::poseidon_chip::*;
+use super::merkle_circuit::{MerkleConfig, MerklePathCircuit};
+use super::poseidon_circuit::utils::Spec;
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// Same statement as MerklePathCircuit, but instead of the root the public
+// input holds poseidon(root || nonce), hiding the root from the verifier.
+#[derive(Clone, Default)]
+pub struct CommittedRootCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    path: MerklePathCircuit<F, S, M, W, I>,
+    nonce: Vec<Value<F>>,
+}
+
+/// A merkle path whose root is only public salted, as poseidon(root || salt)
+/// with a private salt of `I` elements: the nonce of `CommittedRootCircuit`
+pub type SaltedRootCircuit<F, S, const M: usize, const W: usize, const I: usize> =
+    CommittedRootCircuit<F, S, M, W, I>;
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for CommittedRootCircuit<F, S, M, W, I>
+{
+    type Config = MerkleConfig<F, S, M, W, I>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        CommittedRootCircuit {
+            path: self.path.without_witnesses(),
+            nonce: vec![Value::unknown(); I],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        MerklePathCircuit::<F, S, M, W, I>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: MerkleConfig<F, S, M, W, I>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let fr = S::full_rounds();
+        let pr = S::partial_rounds();
+        let poseidon_chip = PoseidonChip::new(config.poseidon_config);
+        let merkle_chip = MerklePathChip::new(config.merkle_config);
+
+        let (_, root_node) = self
+            .path
+            .load_root(&poseidon_chip, &merkle_chip, &mut layouter)?;
+
+        // commitment = poseidon(root || nonce)
+        let pad = |v: Vec<Value<F>>| {
+            v.into_iter()
+                .chain(S::pad().into_iter().map(Value::known))
+                .collect::<Vec<_>>()
+        };
+        let root_values = root_node.0.iter().map(|c| c.value().copied()).collect();
+
+        let s = poseidon_chip.initiate(&mut layouter)?;
+        let (s, loaded_root) = poseidon_chip.load_inputs(&mut layouter, s, &pad(root_values))?;
+        let s = poseidon_chip.permutation(&mut layouter, s, fr, pr)?;
+        let (s, _) = poseidon_chip.load_inputs(&mut layouter, s, &pad(self.nonce.clone()))?;
+        let commitment = poseidon_chip.permutation(&mut layouter, s, fr, pr)?;
+
+        layouter.assign_region(
+            || "link root to commitment",
+            |mut region| {
+                for (l, r) in loaded_root.iter().zip(root_node.0.iter()) {
+                    region.constrain_equal(l.0.cell(), r.cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        let commitment = Node(
+            commitment
+                .0
+                .into_iter()
+                .map(|d| d.0)
+                .take(I)
+                .collect::<Vec<_>>()
+                .try_into()
+                .expect("commitment node is not correct"),
+        );
+        merkle_chip.expose_public(&mut layouter, commitment, M + I)
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > CommittedRootCircuit<F, S, M, W, I>
+{
+    /// the path is laid out as in `MerklePathCircuit::new`, the nonce has `I` elements
+    pub fn new(path: MerklePathCircuit<F, S, M, W, I>, nonce: Vec<F>) -> Self {
+        assert_eq!(nonce.len(), I);
+        CommittedRootCircuit {
+            path,
+            nonce: to_values(&nonce),
+        }
+    }
+}
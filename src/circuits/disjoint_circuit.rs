@@ -0,0 +1,153 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::super::chips::poseidon_chip::*;
+use super::merkle_common::adjacent_leaves;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// None of N private elements is a leaf of a tree of depth M whose leaves are
+// sorted strictly increasing: each element lies strictly between two adjacent
+// leaves, proven as in RangeTreeCircuit. The smallest and largest values of
+// `bits` bits should be leaves so that every other value has a gap around
+// it. The nodes are single values, so S must absorb one value per element.
+// The instance column is [commitment, root] where the commitment is the
+// poseidon hash of the elements, the rest stays private. The elements and
+// the leaves must fit in `bits` bits.
+#[derive(Clone)]
+pub struct DisjointCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const N: usize,
+> {
+    elements: [Value<F>; N],
+    endpoints: [[Value<F>; 2]; N],
+    indices: [Value<F>; N],
+    siblings: Vec<[Vec<Value<F>>; 2]>,
+    bits: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const N: usize,
+    > Circuit<F> for DisjointCircuit<F, S, M, W, N>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        DisjointCircuit {
+            elements: [Value::unknown(); N],
+            endpoints: [[Value::unknown(); 2]; N],
+            indices: [Value::unknown(); N],
+            siblings: vec![[vec![Value::unknown(); M], vec![Value::unknown(); M]]; N],
+            bits: self.bits,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), 1, "a node is a single value");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        // commit to the set
+        let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.elements)?;
+        poseidon.expose_public(&mut layouter, digest, 1)?;
+
+        for (i, l) in loaded.iter().enumerate() {
+            let element =
+                arth.load_private(layouter.namespace(|| "load element"), self.elements[i])?;
+            let (endpoints, root) = adjacent_leaves::<F, S, W, M>(
+                &poseidon,
+                &arth,
+                &mut layouter,
+                &self.endpoints[i],
+                self.indices[i],
+                &self.siblings[i],
+            )?;
+            layouter.assign_region(
+                || "link commitment",
+                |mut region| region.constrain_equal(l.0.cell(), element.cell().cell()),
+            )?;
+
+            // lo < element < hi, the comparisons need every input in range
+            for n in [&element, &endpoints[0], &endpoints[1]] {
+                arth.range_check(layouter.namespace(|| "range"), n.clone(), self.bits)?;
+            }
+            let above = arth.less_than(
+                layouter.namespace(|| "lo < element"),
+                endpoints[0].clone(),
+                element.clone(),
+                self.bits,
+            )?;
+            arth.assert_nonzero(layouter.namespace(|| "lo < element"), above)?;
+            let under = arth.less_than(
+                layouter.namespace(|| "element < hi"),
+                element,
+                endpoints[1].clone(),
+                self.bits,
+            )?;
+            arth.assert_nonzero(layouter.namespace(|| "element < hi"), under)?;
+
+            arth.expose_public(layouter.namespace(|| "expose root"), root, 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const N: usize,
+    > DisjointCircuit<F, S, M, W, N>
+{
+    /// For each element, `endpoints` are the leaves at `indices` and the
+    /// next index, `siblings` the `M` nodes next to the path of each of them
+    /// up to the root
+    pub fn new(
+        elements: [F; N],
+        endpoints: [[F; 2]; N],
+        indices: [u64; N],
+        siblings: Vec<[Vec<F>; 2]>,
+        bits: usize,
+    ) -> Self {
+        assert_eq!(siblings.len(), N, "every element needs its two paths");
+        for s in siblings.iter().flatten() {
+            assert_eq!(s.len(), M, "the path must have the full depth M = {M}");
+        }
+        DisjointCircuit {
+            elements: elements.map(Value::known),
+            endpoints: endpoints.map(|e| e.map(Value::known)),
+            indices: indices.map(|i| Value::known(F::from(i))),
+            siblings: siblings
+                .into_iter()
+                .map(|pair| pair.map(|s| to_values(&s)))
+                .collect(),
+            bits,
+            _marker: PhantomData,
+        }
+    }
+}
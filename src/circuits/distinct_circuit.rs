@@ -0,0 +1,89 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, PoseidonConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+#[derive(Clone)]
+pub struct DistinctConfig<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    poseidon_config: PoseidonConfig<F, S, W>,
+    arth_config: ArthConfig,
+}
+
+// poseidon(a) = x, poseidon(b) = y and a != b
+// both digests go to the first instance column, x then y. Each preimage is a
+// single value, so S must absorb one value per element.
+#[derive(Clone, Default)]
+pub struct DistinctCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    a: Value<F>,
+    b: Value<F>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for DistinctCircuit<F, S, W>
+{
+    type Config = DistinctConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let poseidon_config = PoseidonConfig::configure(meta);
+
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let instance = meta.instance_column();
+        let coeff = [meta.fixed_column(), meta.fixed_column()];
+
+        DistinctConfig {
+            poseidon_config,
+            arth_config: ArthChip::configure(meta, advice, instance, coeff),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: DistinctConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let size = S::element_size();
+        assert_eq!(size, 1, "each preimage is a single value");
+        let poseidon = PoseidonChip::new(config.poseidon_config.arth_config);
+        let arth = ArthChip::new(config.arth_config);
+
+        let (digest_a, loaded_a) = sponge::<F, S, W>(&poseidon, &mut layouter, &[self.a])?;
+        let (digest_b, loaded_b) = sponge::<F, S, W>(&poseidon, &mut layouter, &[self.b])?;
+        poseidon.expose_public_at(&mut layouter, digest_a, size, 0)?;
+        poseidon.expose_public_at(&mut layouter, digest_b, size, size)?;
+
+        let a = arth.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = arth.load_private(layouter.namespace(|| "load b"), self.b)?;
+        layouter.assign_region(
+            || "link preimages",
+            |mut region| {
+                region.constrain_equal(loaded_a[0].0.cell(), a.cell().cell())?;
+                region.constrain_equal(loaded_b[0].0.cell(), b.cell().cell())
+            },
+        )?;
+
+        arth.assert_not_equal(layouter.namespace(|| "a != b"), a, b)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> DistinctCircuit<F, S, W> {
+    pub fn new(a: F, b: F) -> DistinctCircuit<F, S, W> {
+        DistinctCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::marker::PhantomData;
+
+use crate::chips::merkle_chip::{MerklePathChip, MerklePathConfig, MerklePathInstruction};
+
+use super::super::chips::poseidon_chip::*;
+use super::merkle_circuit::MerklePathCircuit;
+use super::poseidon_circuit::utils::Spec;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+#[derive(Clone)]
+pub struct DualMerkleConfig<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M1: usize,
+    const M2: usize,
+    const W: usize,
+    const I: usize,
+> {
+    pub(crate) first: MerklePathConfig<I>,
+    pub(crate) second: MerklePathConfig<I>,
+    pub(crate) poseidon_config: PoseidonArthConfig<F, W>,
+    _marker: PhantomData<S>,
+}
+
+// The same leaf is included in two independent trees of max depth M1 and M2.
+// Each tree has its own instance column laid out as in MerklePathCircuit,
+// [leaf, index, root], and both leaves are constrained to be the same.
+#[derive(Clone, Default)]
+pub struct DualMerkleCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M1: usize,
+    const M2: usize,
+    const W: usize,
+    const I: usize,
+> {
+    first: MerklePathCircuit<F, S, M1, W, I>,
+    second: MerklePathCircuit<F, S, M2, W, I>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M1: usize,
+        const M2: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for DualMerkleCircuit<F, S, M1, M2, W, I>
+{
+    type Config = DualMerkleConfig<F, S, M1, M2, W, I>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        DualMerkleCircuit {
+            first: self.first.without_witnesses(),
+            second: self.second.without_witnesses(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        S::check_rate();
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+
+        // both merkle chips share the advice columns, their regions never overlap
+        let value: [_; I] = [(); I].map(|_| meta.advice_column());
+        let copy_flag = meta.advice_column();
+        let index_flag = meta.advice_column();
+
+        let states = [(); W].map(|_| meta.advice_column());
+        let arks = [(); W].map(|_| meta.fixed_column());
+
+        // one public column per tree
+        let first = meta.instance_column();
+        let second = meta.instance_column();
+
+        DualMerkleConfig {
+            first: MerklePathChip::configure(meta, value, copy_flag, index_flag, first),
+            second: MerklePathChip::configure(meta, value, copy_flag, index_flag, second),
+            poseidon_config: PoseidonChip::configure::<S>(
+                meta,
+                states,
+                first,
+                arks,
+                S::mds(),
+                S::arks(),
+                S::capacity(),
+            )
+            .expect("the mds comes from the spec")
+            .check_against::<S>(),
+            _marker: PhantomData,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: DualMerkleConfig<F, S, M1, M2, W, I>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let poseidon_chip = PoseidonChip::new(config.poseidon_config);
+        let first_chip = MerklePathChip::new(config.first);
+        let second_chip = MerklePathChip::new(config.second);
+
+        let (first_leaf, first_root) =
+            self.first
+                .load_root(&poseidon_chip, &first_chip, &mut layouter)?;
+        let (second_leaf, second_root) =
+            self.second
+                .load_root(&poseidon_chip, &second_chip, &mut layouter)?;
+
+        layouter.assign_region(
+            || "shared leaf",
+            |mut region| {
+                for (a, b) in first_leaf.0.iter().zip(second_leaf.0.iter()) {
+                    region.constrain_equal(a.cell(), b.cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        first_chip.expose_public(&mut layouter, first_root, M1 + I)?;
+        second_chip.expose_public(&mut layouter, second_root, M2 + I)
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M1: usize,
+        const M2: usize,
+        const W: usize,
+        const I: usize,
+    > DualMerkleCircuit<F, S, M1, M2, W, I>
+{
+    /// both paths are laid out as in `MerklePathCircuit::new`
+    pub fn new(
+        first: MerklePathCircuit<F, S, M1, W, I>,
+        second: MerklePathCircuit<F, S, M2, W, I>,
+    ) -> Self {
+        DualMerkleCircuit { first, second }
+    }
+}
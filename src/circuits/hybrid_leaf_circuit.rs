@@ -0,0 +1,123 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+use crate::chips::hash_instruction::HashInstruction;
+
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::CombinedConfig;
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// The leaf of a tree of depth M is H(preimage) for a private preimage and a
+// leaf hash H other than poseidon, while the inner nodes are hashed with
+// poseidon. The instance column is [index, root], the preimage and the
+// siblings stay private.
+#[derive(Clone)]
+pub struct HybridLeafCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    H: HashInstruction<F>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    preimage: Vec<Value<F>>,
+    siblings: Vec<[Value<F>; I]>,
+    _marker: PhantomData<(S, H)>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        H: HashInstruction<F> + Clone,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for HybridLeafCircuit<F, S, H, M, W, I>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        HybridLeafCircuit {
+            preimage: vec![Value::unknown(); self.preimage.len()],
+            siblings: vec![[Value::unknown(); I]; M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        let preimage = self
+            .preimage
+            .iter()
+            .map(|p| arth.load_private(layouter.namespace(|| "load preimage"), *p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut current = H::hash(&arth, layouter.namespace(|| "leaf hash"), &preimage)?;
+        assert_eq!(current.len(), I, "the leaf hash must give a node");
+
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            let right = arth.load_public(layouter.namespace(|| "load index"), i)?;
+            let sibling = sibling
+                .iter()
+                .map(|s| arth.load_private(layouter.namespace(|| "load sibling"), *s))
+                .collect::<Result<Vec<_>, _>>()?;
+            current =
+                hash_layer::<F, S, W>(&poseidon, &arth, &mut layouter, current, sibling, right)?;
+        }
+
+        for (j, node) in current.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose root"), node, M + j)?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        H: HashInstruction<F> + Clone,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > HybridLeafCircuit<F, S, H, M, W, I>
+{
+    /// `siblings` holds the `M` nodes next to the path from the leaf
+    /// `H(preimage)` up to the root
+    pub fn new(preimage: Vec<F>, siblings: Vec<Vec<F>>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        HybridLeafCircuit {
+            preimage: to_values(&preimage),
+            siblings: siblings
+                .into_iter()
+                .map(|s| {
+                    s.into_iter()
+                        .map(Value::known)
+                        .collect::<Vec<_>>()
+                        .try_into()
+                        .expect("a sibling is one node")
+                })
+                .collect(),
+            _marker: PhantomData,
+        }
+    }
+}
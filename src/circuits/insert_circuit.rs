@@ -0,0 +1,135 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::CombinedConfig;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// A leaf inserted at the empty position `index` of a tree of depth M turns
+// the old root into the new root. The empty leaf is all zeros. Both roots are
+// computed from the same siblings and from the bits of the public index, so
+// the insertion cannot be claimed at another position. The instance column is
+// [index, old root, new root], the inserted leaf stays private.
+#[derive(Clone)]
+pub struct InsertCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    leaf: [Value<F>; I],
+    siblings: Vec<[Value<F>; I]>,
+    _marker: PhantomData<S>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for InsertCircuit<F, S, M, W, I>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        InsertCircuit {
+            leaf: [Value::unknown(); I],
+            siblings: vec![[Value::unknown(); I]; M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        // index < 2^M, bit i orders layer i
+        let index = arth.load_public(layouter.namespace(|| "load index"), 0)?;
+        let bits = arth.unpack_flags(layouter.namespace(|| "index bits"), index.clone(), M)?;
+
+        let siblings = self
+            .siblings
+            .iter()
+            .map(|sibling| {
+                sibling
+                    .iter()
+                    .map(|s| arth.load_private(layouter.namespace(|| "load sibling"), *s))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let zero = arth.affine(layouter.namespace(|| "zero"), F::ZERO, index, F::ZERO)?;
+        let empty = vec![zero; I];
+        let leaf = self
+            .leaf
+            .iter()
+            .map(|l| arth.load_private(layouter.namespace(|| "load leaf"), *l))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (offset, mut current) in [(1, empty), (1 + I, leaf)] {
+            for (sibling, right) in siblings.iter().zip(bits.iter()) {
+                current = hash_layer::<F, S, W>(
+                    &poseidon,
+                    &arth,
+                    &mut layouter,
+                    current,
+                    sibling.clone(),
+                    right.clone(),
+                )?;
+            }
+            for (j, node) in current.into_iter().enumerate() {
+                arth.expose_public(layouter.namespace(|| "expose root"), node, offset + j)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > InsertCircuit<F, S, M, W, I>
+{
+    /// `siblings` holds the `M` nodes next to the inserted position from the
+    /// bottom to the top, the position itself is read from the instance
+    /// column
+    pub fn new(leaf: Vec<F>, siblings: Vec<Vec<F>>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        let node = |n: Vec<F>| -> [Value<F>; I] {
+            n.into_iter()
+                .map(Value::known)
+                .collect::<Vec<_>>()
+                .try_into()
+                .expect("a node has I values")
+        };
+        InsertCircuit {
+            leaf: node(leaf),
+            siblings: siblings.into_iter().map(node).collect(),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// poseidon(a) = x, poseidon(b) = y and a[i] = b[j] for some i, j
+// both digests go to the instance column, x then y. Every pair of elements is
+// compared, so the sets are kept small, and N must be a multiple of
+// `S::element_size()`.
+#[derive(Clone)]
+pub struct IntersectionCircuit<F: PrimeField, S: Spec<F, W>, const N: usize, const W: usize> {
+    a: [Value<F>; N],
+    b: [Value<F>; N],
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const N: usize, const W: usize> Default
+    for IntersectionCircuit<F, S, N, W>
+{
+    fn default() -> Self {
+        IntersectionCircuit {
+            a: [Value::unknown(); N],
+            b: [Value::unknown(); N],
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone, const N: usize, const W: usize> Circuit<F>
+    for IntersectionCircuit<F, S, N, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let size = S::element_size();
+        let (poseidon, arth) = config.chips();
+
+        // commit to both sets
+        let (digest_a, loaded_a) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.a)?;
+        let (digest_b, loaded_b) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.b)?;
+        poseidon.expose_public_at(&mut layouter, digest_a, size, 0)?;
+        poseidon.expose_public_at(&mut layouter, digest_b, size, size)?;
+
+        let [a, b] = [&self.a, &self.b].map(|x| {
+            x.iter()
+                .map(|v| arth.load_private(layouter.namespace(|| "load element"), *v))
+                .collect::<Result<Vec<_>, _>>()
+        });
+        let (a, b) = (a?, b?);
+
+        layouter.assign_region(
+            || "link commitments",
+            |mut region| {
+                for (l, v) in loaded_a.iter().chain(&loaded_b).zip(a.iter().chain(&b)) {
+                    region.constrain_equal(l.0.cell(), v.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        // or over all pairwise matches
+        let mut any = None;
+        for x in &a {
+            for y in &b {
+                let eq =
+                    arth.is_equal(layouter.namespace(|| "a[i] == b[j]"), x.clone(), y.clone())?;
+                any = Some(match any {
+                    Some(acc) => arth.or(layouter.namespace(|| "any match"), acc, eq)?,
+                    None => eq,
+                });
+            }
+        }
+
+        arth.assert_nonzero(layouter.namespace(|| "a match"), any.unwrap())
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const N: usize, const W: usize> IntersectionCircuit<F, S, N, W> {
+    pub fn new(a: [F; N], b: [F; N]) -> IntersectionCircuit<F, S, N, W> {
+        assert!(N > 0, "empty sets never intersect");
+        assert_eq!(
+            N % S::element_size(),
+            0,
+            "the sets must fill whole elements"
+        );
+        IntersectionCircuit {
+            a: a.map(Value::known),
+            b: b.map(Value::known),
+            _marker: PhantomData,
+        }
+    }
+}
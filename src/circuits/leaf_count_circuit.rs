@@ -0,0 +1,146 @@
+use crate::chips::arth_chips::NumericInstructions;
+use crate::chips::merkle_chip::MerklePathInstruction;
+
+use super::merkle_circuit::{MerkleArthConfig, MerklePathCircuit};
+use super::poseidon_circuit::utils::Spec;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// A merkle path whose depth is fixed by a public leaf count instead of
+// being chosen by the prover: the path hashes exactly ceil(log2(count))
+// layers, so a shorter or longer path of a tree of another shape is
+// rejected. The instance column is laid out as in MerklePathCircuit,
+// [leaf, index, root], followed by the leaf count at row M + 2 * I.
+#[derive(Clone, Default)]
+pub struct LeafCountCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    path: MerklePathCircuit<F, S, M, W, I>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for LeafCountCircuit<F, S, M, W, I>
+{
+    // the merkle and arth chips sharing one instance column
+    type Config = MerkleArthConfig<F, S, M, W, I>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        LeafCountCircuit {
+            path: self.path.without_witnesses(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        MerkleArthConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: MerkleArthConfig<F, S, M, W, I>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon_chip, merkle_chip, arth) = config.chips();
+
+        let layers = self
+            .path
+            .load_layers(&poseidon_chip, &merkle_chip, &mut layouter)?;
+        merkle_chip.expose_public(&mut layouter, layers.root, M + I)?;
+
+        // 2^depth = prod of (2 - copy_i), a copied layer does not double
+        let mut flags = vec![];
+        for flag in layers.copy.iter() {
+            flags.push(arth.load_private(
+                layouter.namespace(|| "load copy flag"),
+                flag.value().copied(),
+            )?);
+        }
+        layouter.assign_region(
+            || "link copy flags",
+            |mut region| {
+                for (flag, n) in layers.copy.iter().zip(flags.iter()) {
+                    region.constrain_equal(flag.cell(), n.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+        let mut width = None;
+        for flag in flags {
+            let factor = arth.affine(
+                layouter.namespace(|| "2 - copy"),
+                -F::ONE,
+                flag,
+                F::ONE.double(),
+            )?;
+            width = Some(match width {
+                None => factor,
+                Some(w) => arth.mul(layouter.namespace(|| "double"), w, factor)?,
+            });
+        }
+        let width = width.expect("the tree has at least one layer");
+
+        // 2^(depth - 1) < count <= 2^depth, so depth = ceil(log2(count))
+        let bits = M + 2;
+        let count = arth.load_public(layouter.namespace(|| "load leaf count"), M + 2 * I)?;
+        arth.range_check(layouter.namespace(|| "count bits"), count.clone(), M + 1)?;
+        let double = arth.affine(
+            layouter.namespace(|| "2 * count"),
+            F::ONE.double(),
+            count.clone(),
+            F::ZERO,
+        )?;
+        let too_deep = arth.less_than(
+            layouter.namespace(|| "2^depth < count"),
+            width.clone(),
+            count,
+            bits,
+        )?;
+        let not_too_deep = arth.affine(
+            layouter.namespace(|| "not too deep"),
+            -F::ONE,
+            too_deep,
+            F::ONE,
+        )?;
+        arth.assert_nonzero(layouter.namespace(|| "count fits"), not_too_deep)?;
+        let shallow_enough = arth.less_than(
+            layouter.namespace(|| "2^depth < 2 * count"),
+            width,
+            double,
+            bits,
+        )?;
+        arth.assert_nonzero(layouter.namespace(|| "no empty half"), shallow_enough)
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > LeafCountCircuit<F, S, M, W, I>
+{
+    /// the path is laid out as in `MerklePathCircuit::new`
+    pub fn new(path: MerklePathCircuit<F, S, M, W, I>) -> Self {
+        LeafCountCircuit { path }
+    }
+
+    /// Depth of a tree of `count` leaves, the number of hashed layers of its
+    /// paths
+    pub fn depth_for(count: u64) -> usize {
+        assert!(count > 0, "a tree has at least one leaf");
+        (u64::BITS - (count - 1).leading_zeros()) as usize
+    }
+}
@@ -0,0 +1,126 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, PoseidonConfig};
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+#[derive(Clone)]
+pub struct MedianConfig<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    poseidon_config: PoseidonConfig<F, S, W>,
+    arth_config: ArthConfig,
+}
+
+// median(x) = m where poseidon(x) = c
+// the commitment c goes to the first instance column, the median m to the
+// second one. The private values are sorted in circuit by compare and swap,
+// so they can be committed in any order, and must all fit in `bits` bits.
+#[derive(Clone)]
+pub struct MedianCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    x: Vec<Value<F>>,
+    bits: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for MedianCircuit<F, S, W>
+{
+    type Config = MedianConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        MedianCircuit {
+            x: vec![Value::unknown(); self.x.len()],
+            bits: self.bits,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let poseidon_config = PoseidonConfig::configure(meta);
+
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let instance = meta.instance_column();
+        let coeff = [meta.fixed_column(), meta.fixed_column()];
+
+        MedianConfig {
+            poseidon_config,
+            arth_config: ArthChip::configure(meta, advice, instance, coeff),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: MedianConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let poseidon = PoseidonChip::new(config.poseidon_config.arth_config);
+        let arth = ArthChip::new(config.arth_config);
+
+        // commit to the values
+        let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.x)?;
+        poseidon.expose_public(&mut layouter, digest, S::element_size())?;
+
+        let mut x = self
+            .x
+            .iter()
+            .map(|v| arth.load_private(layouter.namespace(|| "load x"), *v))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        layouter.assign_region(
+            || "link commitment",
+            |mut region| {
+                for (l, v) in loaded.iter().zip(x.iter()) {
+                    region.constrain_equal(l.0.cell(), v.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        // bubble sort, every swap keeps x a permutation of the inputs
+        for i in 0..x.len() {
+            for j in 0..x.len() - 1 - i {
+                let (a, b) = (x[j].clone(), x[j + 1].clone());
+                let swap = arth.less_than(
+                    layouter.namespace(|| "b < a"),
+                    b.clone(),
+                    a.clone(),
+                    self.bits,
+                )?;
+                (x[j], x[j + 1]) =
+                    arth.conditional_swap(layouter.namespace(|| "min, max"), swap, a, b)?;
+            }
+        }
+
+        // the comparisons above are only meaningful for values in range,
+        // which is checked here along with the final order.
+        arth.assert_sorted(layouter.namespace(|| "sorted"), &x, self.bits, false)?;
+
+        // the middle position is a constant, its cell is exposed as is
+        let median = x[x.len() / 2].clone();
+        arth.expose_public(layouter.namespace(|| "expose median"), median, 0)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> MedianCircuit<F, S, W> {
+    /// Panics unless there is an odd number of values.
+    pub fn new(x: Vec<F>, bits: usize) -> MedianCircuit<F, S, W> {
+        assert_eq!(
+            x.len() % 2,
+            1,
+            "the median needs an odd count, got {} values",
+            x.len()
+        );
+        MedianCircuit {
+            x: to_values(&x),
+            bits,
+            _marker: PhantomData,
+        }
+    }
+}
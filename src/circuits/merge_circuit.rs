@@ -0,0 +1,71 @@
+use std::marker::PhantomData;
+
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::CombinedConfig;
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Chip, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// Merging two subtrees: the public root is poseidon(left, right) of the two
+// public subtree roots, as built by `compress`. The instance column holds
+// the left root, the right root and the merged root, one node of I values
+// each.
+#[derive(Clone)]
+pub struct MergeCircuit<F: PrimeField, S: Spec<F, W>, const W: usize, const I: usize> {
+    left: [Value<F>; I],
+    right: [Value<F>; I],
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize, const I: usize> Circuit<F>
+    for MergeCircuit<F, S, W, I>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        MergeCircuit {
+            left: [Value::unknown(); I],
+            right: [Value::unknown(); I],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, _) = config.chips();
+        let instance = poseidon.config().output();
+
+        let (root, subroots) =
+            poseidon.hash_two_loaded::<S>(&mut layouter, &self.left, &self.right)?;
+        for (row, subroot) in subroots.iter().flatten().enumerate() {
+            layouter.constrain_instance(subroot.0.cell(), instance, row)?;
+        }
+        poseidon.expose_public_at(&mut layouter, root, I, 2 * I)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize, const I: usize> MergeCircuit<F, S, W, I> {
+    /// The merge of the subtrees of roots `left` and `right`
+    pub fn new(left: Vec<F>, right: Vec<F>) -> Self {
+        let node =
+            |n: Vec<F>| -> [Value<F>; I] { to_values(&n).try_into().expect("a node is I values") };
+        MergeCircuit {
+            left: node(left),
+            right: node(right),
+            _marker: PhantomData,
+        }
+    }
+}
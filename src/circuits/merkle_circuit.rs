@@ -1,14 +1,101 @@
 use std::marker::PhantomData;
 
-use crate::chips::merkle_chip::{MerklePathChip, MerklePathConfig, MerklePathInstruction};
+use crate::chips::arth_chips::{ArthChip, ArthConfig};
+use crate::chips::merkle_chip::{MerklePathChip, MerklePathConfig, MerklePathInstruction, Node};
+use crate::debug::CircuitError;
 
 use super::super::chips::poseidon_chip::*;
 use super::poseidon_circuit::utils::Spec;
+use super::to_values;
+use crate::native::merkle::{MerklePath, MerkleTree};
+use crate::native::poseidon::hash;
 
 use ff::PrimeField;
 use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
 use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
 
+/// Build the copy flags for a path of real depth `n` in a circuit of max depth `m`.
+/// The first `n` layers are hashed (flag 0) and the remaining `m - n + 1` rows
+/// copy the root upwards (flag 1).
+pub fn copy_flags<F: PrimeField>(n: usize, m: usize) -> Vec<Value<F>> {
+    assert!(n <= m);
+    (0..=m)
+        .map(|i| match i < n {
+            true => Value::known(F::ZERO),
+            false => Value::known(F::ONE),
+        })
+        .collect::<Vec<_>>()
+}
+
+/// The witness of `MerklePathCircuit` for a generated path
+impl<F: PrimeField> MerklePath<F> {
+    pub fn get_left_value(&self) -> Vec<Vec<Value<F>>> {
+        let known = |v: &Vec<F>| v.iter().copied().map(Value::known).collect();
+        self.get_left().iter().map(known).collect()
+    }
+
+    pub fn get_right_value(&self) -> Vec<Vec<Value<F>>> {
+        let known = |v: &Vec<F>| v.iter().copied().map(Value::known).collect();
+        self.get_right().iter().map(known).collect()
+    }
+
+    pub fn get_copy_value(&self, m: usize) -> Vec<Value<F>> {
+        self.get_copy_flags(m)
+            .into_iter()
+            .map(Value::known)
+            .collect()
+    }
+}
+
+/// Public inputs of `MerklePathCircuit`, laid out as `[leaf, index, root]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleInstance<F: PrimeField, const M: usize> {
+    leaf: Vec<F>,
+    index: Vec<F>,
+    root: Vec<F>,
+}
+
+impl<F: PrimeField, const M: usize> MerkleInstance<F, M> {
+    /// `index` holds the position of the node at each layer (`true` for
+    /// right) and is padded with `false` up to `M` layers.
+    pub fn from_bools(leaf: Vec<F>, index: &[bool], root: Vec<F>) -> Self {
+        assert!(index.len() <= M);
+        MerkleInstance {
+            leaf,
+            index: index
+                .iter()
+                .copied()
+                .chain(std::iter::repeat(false))
+                .take(M)
+                .map(|b| match b {
+                    true => F::ONE,
+                    false => F::ZERO,
+                })
+                .collect(),
+            root,
+        }
+    }
+
+    /// The leaf at `leaf_index` of the tree, its position and the root
+    pub fn from_tree(tree: &MerkleTree<F>, leaf_index: usize) -> Self {
+        Self::from_bools(
+            tree.leaf(leaf_index),
+            &tree.index_bits(leaf_index),
+            tree.root(),
+        )
+    }
+
+    /// The instance column of the circuit
+    pub fn to_vec(&self) -> Vec<F> {
+        self.leaf
+            .iter()
+            .chain(self.index.iter())
+            .chain(self.root.iter())
+            .copied()
+            .collect()
+    }
+}
+
 #[derive(Clone)]
 pub struct MerkleConfig<
     F: PrimeField,
@@ -17,11 +104,58 @@ pub struct MerkleConfig<
     const W: usize,
     const I: usize,
 > {
-    merkle_config: MerklePathConfig<I>,
-    poseidon_config: PoseidonArthConfig<F, W>,
+    pub(crate) merkle_config: MerklePathConfig<I>,
+    pub(crate) poseidon_config: PoseidonArthConfig<F, W>,
     _marker: PhantomData<S>,
 }
 
+/// The merkle path config and an arth chip on its own advice and fixed
+/// columns, both bound to the merkle instance column.
+#[derive(Clone)]
+pub struct MerkleArthConfig<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    merkle: MerkleConfig<F, S, M, W, I>,
+    arth_config: ArthConfig,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > MerkleArthConfig<F, S, M, W, I>
+{
+    /// Allocate the merkle path columns, then the arth columns reusing the
+    /// merkle instance column
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let merkle = MerklePathCircuit::<F, S, M, W, I>::configure(meta);
+
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let instance = merkle.poseidon_config.output();
+        let coeff = [meta.fixed_column(), meta.fixed_column()];
+
+        MerkleArthConfig {
+            merkle,
+            arth_config: ArthChip::configure(meta, advice, instance, coeff),
+        }
+    }
+
+    /// The three chips of the config
+    pub fn chips(&self) -> (PoseidonChip<F, W>, MerklePathChip<F, I>, ArthChip<F>) {
+        (
+            PoseidonChip::new(self.merkle.poseidon_config.clone()),
+            MerklePathChip::new(self.merkle.merkle_config.clone()),
+            ArthChip::new(self.arth_config.clone()),
+        )
+    }
+}
+
 // implementation for 5-posiedon
 // For each input, we fixed the padding as [x,1,0,0,...,0]
 // inputs permutation rounds will go for all abosrb
@@ -36,6 +170,11 @@ pub struct MerklePathCircuit<
     left: Vec<[Value<F>; I]>,
     right: Vec<[Value<F>; I]>,
     copy: Vec<Value<F>>,
+    /// witness the duplicated root directly instead of loading it through
+    /// the poseidon chip
+    skip_root_layer: bool,
+    /// depths of the nodes on the path exposed after the root
+    nodes: Vec<usize>,
     _marker: PhantomData<S>,
 }
 
@@ -52,10 +191,17 @@ impl<
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
-        Self::default()
+        MerklePathCircuit {
+            skip_root_layer: self.skip_root_layer,
+            nodes: self.nodes.clone(),
+            ..Self::empty()
+        }
     }
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        S::check_rate();
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+
         let value = (0..I)
             .map(|_| meta.advice_column())
             .collect::<Vec<_>>()
@@ -75,14 +221,8 @@ impl<
         let ark_paras = S::arks();
 
         MerkleConfig {
-            merkle_config: MerklePathChip::configure(
-                meta,
-                value,
-                copy_flag,
-                index_flag,
-                output.clone(),
-            ),
-            poseidon_config: PoseidonChip::configure(
+            merkle_config: MerklePathChip::configure(meta, value, copy_flag, index_flag, output),
+            poseidon_config: PoseidonChip::configure::<S>(
                 meta,
                 states.try_into().unwrap(),
                 output,
@@ -90,7 +230,9 @@ impl<
                 mds,
                 ark_paras,
                 S::capacity(),
-            ),
+            )
+            .expect("the mds comes from the spec")
+            .check_against::<S>(),
             _marker: PhantomData,
         }
     }
@@ -100,6 +242,185 @@ impl<
         config: MerkleConfig<F, S, M, W, I>,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
+        let poseidon_chip = PoseidonChip::new(config.poseidon_config);
+        let merkle_chip = MerklePathChip::new(config.merkle_config);
+
+        let layers = self.load_layers(&poseidon_chip, &merkle_chip, &mut layouter)?;
+
+        merkle_chip.expose_public(&mut layouter, layers.root, M + I)?;
+        for (k, node) in layers.nodes.into_iter().enumerate() {
+            merkle_chip.expose_public(&mut layouter, node, M + 2 * I + k * I)?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > MerklePathCircuit<F, S, M, W, I>
+{
+    /// input the real path
+    /// [left leave, right leave]
+    /// [left node, right node]
+    /// ...
+    /// [root, root]
+    pub fn new(
+        left: Vec<Vec<Value<F>>>,
+        right: Vec<Vec<Value<F>>>,
+        copy: Vec<Value<F>>,
+    ) -> MerklePathCircuit<F, S, M, W, I> {
+        assert_eq!(left.len(), right.len());
+        assert_eq!(
+            copy.len(),
+            M + 1,
+            "a circuit of depth M = {M} needs M + 1 copy flags, build them with copy_flags(n, {M})"
+        );
+        MerklePathCircuit {
+            left: left
+                .into_iter()
+                .map(|v| v.try_into().expect("left inputs error"))
+                .collect(),
+            right: right
+                .into_iter()
+                .map(|v| v.try_into().expect("right inputs error"))
+                .collect(),
+            copy,
+            skip_root_layer: false,
+            nodes: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Same as `new`, with the copy flags derived from the path length and `M`
+    pub fn from_layers(left: Vec<Vec<Value<F>>>, right: Vec<Vec<Value<F>>>) -> Self {
+        assert!(!left.is_empty());
+        let n = left.len() - 1;
+        Self::new(left, right, copy_flags(n, M))
+    }
+
+    /// A path of full depth `M` without any witness, shaped like every real
+    /// path so that it can be used for key generation.
+    pub fn empty() -> Self {
+        MerklePathCircuit {
+            left: vec![[Value::unknown(); I]; M + 1],
+            right: vec![[Value::unknown(); I]; M + 1],
+            copy: vec![Value::unknown(); M + 1],
+            skip_root_layer: false,
+            nodes: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Skip hashing the trailing layer that duplicates the root. The root is
+    /// witnessed in the merkle chip instead and still bound to the top hash by
+    /// the path constraints, saving one poseidon permutation of rows.
+    pub fn without_root_layer(mut self) -> Self {
+        self.skip_root_layer = true;
+        self
+    }
+
+    /// Also expose the node on the path at each of `depths`, e.g. subtree
+    /// roots a protocol commits to, from row `M + 2 * I` of the instance
+    /// column, `I` rows each. The leaves are at depth 0, every depth must be
+    /// below `M`.
+    pub fn with_nodes(mut self, depths: Vec<usize>) -> Self {
+        for &d in &depths {
+            assert!(d < M, "the node at depth {d} is not below the root");
+        }
+        self.nodes = depths;
+        self
+    }
+
+    /// Rows used by the poseidon regions, which dominate the merkle regions
+    /// laid out next to them. Every layer below the root hashes its left and
+    /// right node, the root layer only loads them unless it is skipped.
+    pub fn rows_for(skip_root_layer: bool) -> usize {
+        let initiate = PoseidonChip::<F, W>::INITIATE_ROWS;
+        let load = PoseidonChip::<F, W>::LOAD_ROWS;
+        let permutation =
+            PoseidonChip::<F, W>::permutation_rows(S::full_rounds(), S::partial_rounds(), 1);
+        let root_layer = match skip_root_layer {
+            true => 0,
+            false => initiate + 2 * load + permutation,
+        };
+        M * (initiate + 2 * (load + permutation)) + root_layer
+    }
+
+    /// Number of permutations run whatever the real depth of the path: every
+    /// layer below the root hashes its left and right node, the root layer
+    /// absorbs its left node unless it is skipped.
+    pub fn permutations(&self) -> usize {
+        2 * M + usize::from(!self.skip_root_layer)
+    }
+
+    /// Build the path from a leaf, its siblings from bottom to top and the
+    /// position of the node at each layer (`true` for right), hashing the
+    /// inner nodes natively.
+    pub fn from_siblings(leaf: Vec<F>, siblings: &[Vec<F>], index: &[bool]) -> Self {
+        assert_eq!(siblings.len(), index.len());
+        let n = siblings.len();
+        assert!(n <= M);
+
+        let mut left = vec![];
+        let mut right = vec![];
+        let mut node = leaf;
+        for (sibling, &is_right) in siblings.iter().zip(index) {
+            let (l, r) = match is_right {
+                true => (sibling.clone(), node),
+                false => (node, sibling.clone()),
+            };
+            node = hash::<F, S, W>(l.iter().chain(r.iter()).copied().collect());
+            left.push(l);
+            right.push(r);
+        }
+
+        // the root is duplicated on the last layer
+        left.push(node.clone());
+        right.push(node);
+
+        let known = |v: Vec<Vec<F>>| v.into_iter().map(|n| to_values(&n)).collect();
+        Self::new(known(left), known(right), copy_flags(n, M))
+    }
+
+    /// The path of the leaf at `leaf_index` of the tree, whose depth must
+    /// not exceed `M`. Its public inputs are `MerkleInstance::from_tree`.
+    pub fn from_tree(tree: &MerkleTree<F>, leaf_index: usize) -> Self {
+        assert!(
+            tree.depth() <= M,
+            "the tree of depth {} is deeper than M = {M}",
+            tree.depth()
+        );
+        Self::from_siblings(
+            tree.leaf(leaf_index),
+            &tree.siblings(leaf_index),
+            &tree.index_bits(leaf_index),
+        )
+    }
+
+    /// Hash every layer of the path and constrain it with the merkle chip,
+    /// returning the selected leaf and the root
+    pub(crate) fn load_root(
+        &self,
+        poseidon_chip: &PoseidonChip<F, W>,
+        merkle_chip: &MerklePathChip<F, I>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(Node<F, I>, Node<F, I>), Error> {
+        let layers = self.load_layers(poseidon_chip, merkle_chip, layouter)?;
+        Ok((layers.leaf, layers.root))
+    }
+
+    /// Same as `load_root`, also returning the left and right nodes of every
+    /// layer
+    pub(crate) fn load_layers(
+        &self,
+        poseidon_chip: &PoseidonChip<F, W>,
+        merkle_chip: &MerklePathChip<F, I>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<Layers<F, I>, Error> {
         let size = S::element_size();
 
         // element size is correct
@@ -109,20 +430,16 @@ impl<
         let n = self.left.len() - 1;
         assert!(n <= M);
 
-        let poseidon_chip = PoseidonChip::new(config.poseidon_config);
         let fr = S::full_rounds();
         let pr = S::partial_rounds();
 
-        let merkle_chip = MerklePathChip::new(config.merkle_config);
-
         // chunks and pad
         let padded_left = self
             .left
             .clone()
             .into_iter()
             .map(|c| {
-                c.to_vec()
-                    .into_iter()
+                c.into_iter()
                     .chain(S::pad().into_iter().map(Value::known))
                     .collect::<Vec<_>>()
             })
@@ -132,8 +449,7 @@ impl<
             .clone()
             .into_iter()
             .map(|c| {
-                c.to_vec()
-                    .into_iter()
+                c.into_iter()
                     .chain(S::pad().into_iter().map(Value::known))
                     .collect::<Vec<_>>()
             })
@@ -146,20 +462,20 @@ impl<
 
         for i in 0..n {
             let s = poseidon_chip
-                .initiate(&mut layouter)
+                .initiate(layouter)
                 .expect("failed to init hasher");
             let (s, l) = poseidon_chip
-                .load_inputs(&mut layouter, s.clone(), &padded_left[i])
+                .load_inputs(layouter, s.clone(), &padded_left[i])
                 .expect("failed to load left input");
             let s = poseidon_chip
-                .permutation(&mut layouter, s, fr, pr)
+                .permutation(layouter, s, fr, pr)
                 .expect("failed to permutate left input");
             let (s, r) = poseidon_chip
-                .load_inputs(&mut layouter, s.clone(), &padded_right[i])
+                .load_inputs(layouter, s.clone(), &padded_right[i])
                 .expect("failed to load right input");
 
             let h = poseidon_chip
-                .permutation(&mut layouter, s, fr, pr)
+                .permutation(layouter, s, fr, pr)
                 .expect("failed to permutate right input");
             left_nodes.push(
                 l.into_iter()
@@ -190,17 +506,24 @@ impl<
         // now process root
 
         for i in n..M + 1 {
+            if i == M && self.skip_root_layer {
+                let root = merkle_chip.witness_node(layouter, self.left[n])?;
+                left_nodes.push(root.0.clone());
+                right_nodes.push(root.0);
+                break;
+            }
+
             let s = poseidon_chip
-                .initiate(&mut layouter)
+                .initiate(layouter)
                 .expect("failed to init hasher");
             let (s, l) = poseidon_chip
-                .load_inputs(&mut layouter, s.clone(), &padded_left[n])
+                .load_inputs(layouter, s.clone(), &padded_left[n])
                 .expect("failed to load left root");
             let s = poseidon_chip
-                .permutation(&mut layouter, s, fr, pr)
+                .permutation(layouter, s, fr, pr)
                 .expect("failed to permutate left root");
             let (s, r) = poseidon_chip
-                .load_inputs(&mut layouter, s.clone(), &padded_right[n])
+                .load_inputs(layouter, s.clone(), &padded_right[n])
                 .expect("failed to load right root");
 
             left_nodes.push(
@@ -222,7 +545,7 @@ impl<
 
             if i < M {
                 let h = poseidon_chip
-                    .permutation(&mut layouter, s, fr, pr)
+                    .permutation(layouter, s, fr, pr)
                     .expect("failed to permutate right root");
 
                 hash_nodes.push(
@@ -236,54 +559,76 @@ impl<
             }
         }
 
-        merkle_chip.load_leaves(&mut layouter, left_nodes[0].clone(), right_nodes[0].clone())?;
+        let leaf =
+            merkle_chip.load_leaves(layouter, left_nodes[0].clone(), right_nodes[0].clone())?;
 
-        let root_node = merkle_chip.load_path(
-            &mut layouter,
-            left_nodes,
-            right_nodes,
+        let (root, nodes, copy) = merkle_chip.load_path_with_nodes(
+            layouter,
+            left_nodes.clone(),
+            right_nodes.clone(),
             hash_nodes,
             &self.copy,
             M,
             n,
+            &self.nodes,
         )?;
-
-        merkle_chip.expose_public(&mut layouter, root_node, M + I)?;
-        return Ok(());
+        Ok(Layers {
+            leaf,
+            root,
+            nodes,
+            left: left_nodes,
+            right: right_nodes,
+            copy,
+        })
     }
 }
 
+/// Cells of a path loaded by `MerklePathCircuit::load_layers`
+pub(crate) struct Layers<F: PrimeField, const I: usize> {
+    pub(crate) leaf: Node<F, I>,
+    pub(crate) root: Node<F, I>,
+    /// nodes on the path at the depths of `MerklePathCircuit::with_nodes`
+    pub(crate) nodes: Vec<Node<F, I>>,
+    /// left node of every layer, from the leaves up to the duplicated root
+    pub(crate) left: Vec<[AssignedCell<F, F>; I]>,
+    pub(crate) right: Vec<[AssignedCell<F, F>; I]>,
+    /// copy flag of every layer below the root
+    pub(crate) copy: Vec<AssignedCell<F, F>>,
+}
+
+/// The layers of the path as in `MerklePathCircuit::from_layers`, each
+/// holding the `I` values of its left node then of its right node, from the
+/// leaves up to the duplicated root.
 impl<
         F: PrimeField,
         S: Spec<F, W> + Clone + Default,
         const M: usize,
         const W: usize,
         const I: usize,
-    > MerklePathCircuit<F, S, M, W, I>
+    > TryFrom<&[F]> for MerklePathCircuit<F, S, M, W, I>
 {
-    /// input the real path
-    /// [left leave, right leave]
-    /// [left node, right node]
-    /// ...
-    /// [root, root]
-    pub fn new(
-        left: Vec<Vec<Value<F>>>,
-        right: Vec<Vec<Value<F>>>,
-        copy: Vec<Value<F>>,
-    ) -> MerklePathCircuit<F, S, M, W, I> {
-        assert_eq!(left.len(), right.len());
-        assert_eq!(copy.len(), M + 1);
-        MerklePathCircuit {
-            left: left
-                .into_iter()
-                .map(|v| v.try_into().expect("left inputs error"))
-                .collect(),
-            right: right
-                .into_iter()
-                .map(|v| v.try_into().expect("right inputs error"))
-                .collect(),
-            copy: copy,
-            _marker: PhantomData,
+    type Error = CircuitError;
+
+    fn try_from(layers: &[F]) -> Result<Self, CircuitError> {
+        let chunk = 2 * I;
+        if layers.is_empty() || !layers.len().is_multiple_of(chunk) {
+            return Err(CircuitError::WitnessNotChunked {
+                chunk,
+                supplied: layers.len(),
+            });
         }
+        if layers.len() > (M + 1) * chunk {
+            return Err(CircuitError::WitnessTooLong {
+                expected: (M + 1) * chunk,
+                supplied: layers.len(),
+            });
+        }
+
+        let node = |v: &[F]| v.iter().copied().map(Value::known).collect::<Vec<_>>();
+        let (left, right) = layers
+            .chunks(chunk)
+            .map(|layer| (node(&layer[..I]), node(&layer[I..])))
+            .unzip();
+        Ok(Self::from_layers(left, right))
     }
 }
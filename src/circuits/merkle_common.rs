@@ -0,0 +1,104 @@
+//! Gadgets shared by the merkle circuits built from the arth and poseidon
+//! chips.
+
+use crate::chips::arth_chips::{ArthChip, Number, NumericInstructions};
+
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::sponge;
+use super::poseidon_circuit::utils::Spec;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::Error;
+
+/// Order `(current, sibling)` by the boolean `right`, set when the current
+/// node is the right one, and hash the pair into the node of the next layer.
+pub(crate) fn hash_layer<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    poseidon: &PoseidonChip<F, W>,
+    arth: &ArthChip<F>,
+    layouter: &mut impl Layouter<F>,
+    current: Vec<Number<F>>,
+    sibling: Vec<Number<F>>,
+    right: Number<F>,
+) -> Result<Vec<Number<F>>, Error> {
+    let size = current.len();
+
+    let mut left_nodes = vec![];
+    let mut right_nodes = vec![];
+    for (c, s) in current.into_iter().zip(sibling) {
+        let (l, r) = arth.conditional_swap(layouter.namespace(|| "order"), right.clone(), c, s)?;
+        left_nodes.push(l);
+        right_nodes.push(r);
+    }
+
+    let nodes = left_nodes.iter().chain(right_nodes.iter());
+    let values = nodes.clone().map(|n| n.cell().value().copied());
+    let (digest, loaded) = sponge::<F, S, W>(poseidon, layouter, &values.collect::<Vec<_>>())?;
+
+    let next = digest.0[..size]
+        .iter()
+        .map(|d| arth.load_private(layouter.namespace(|| "load node"), d.0.value().copied()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    layouter.assign_region(
+        || "link layer",
+        |mut region| {
+            for (l, n) in loaded.iter().zip(nodes.clone()) {
+                region.constrain_equal(l.0.cell(), n.cell().cell())?;
+            }
+            for (d, n) in digest.0.iter().zip(next.iter()) {
+                region.constrain_equal(d.0.cell(), n.cell().cell())?;
+            }
+            Ok(())
+        },
+    )?;
+    Ok(next)
+}
+
+/// Load the leaves `endpoints` at the private `index` and `index + 1` of a
+/// tree of depth M with single value nodes, hash each up through its `M`
+/// siblings and constrain both paths to the same root. Returns the loaded
+/// endpoints and the root.
+pub(crate) fn adjacent_leaves<F: PrimeField, S: Spec<F, W>, const W: usize, const M: usize>(
+    poseidon: &PoseidonChip<F, W>,
+    arth: &ArthChip<F>,
+    layouter: &mut impl Layouter<F>,
+    endpoints: &[Value<F>; 2],
+    index: Value<F>,
+    siblings: &[Vec<Value<F>>; 2],
+) -> Result<(Vec<Number<F>>, Number<F>), Error> {
+    // hi sits right after lo
+    let lo_index = arth.load_private(layouter.namespace(|| "load index"), index)?;
+    let hi_index = arth.affine(
+        layouter.namespace(|| "index + 1"),
+        F::ONE,
+        lo_index.clone(),
+        F::ONE,
+    )?;
+
+    let mut roots = vec![];
+    let mut loaded = vec![];
+    for ((endpoint, index), siblings) in endpoints
+        .iter()
+        .zip([lo_index, hi_index])
+        .zip(siblings.iter())
+    {
+        let endpoint = arth.load_private(layouter.namespace(|| "load endpoint"), *endpoint)?;
+        let index_bits = arth.unpack_flags(layouter.namespace(|| "index bits"), index, M)?;
+
+        let mut current = vec![endpoint.clone()];
+        for (right, sibling) in index_bits.into_iter().zip(siblings) {
+            let sibling = arth.load_private(layouter.namespace(|| "load sibling"), *sibling)?;
+            current =
+                hash_layer::<F, S, W>(poseidon, arth, layouter, current, vec![sibling], right)?;
+        }
+        roots.push(current[0].clone());
+        loaded.push(endpoint);
+    }
+
+    layouter.assign_region(
+        || "link roots",
+        |mut region| region.constrain_equal(roots[0].cell().cell(), roots[1].cell().cell()),
+    )?;
+    Ok((loaded, roots[0].clone()))
+}
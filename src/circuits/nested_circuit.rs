@@ -0,0 +1,85 @@
+use std::marker::PhantomData;
+
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, PoseidonConfig};
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// poseidon(poseidon(a), poseidon(b))
+// both inner digests are absorbed by the outer sponge and only the outer
+// digest is exposed.
+#[derive(Clone, Default)]
+pub struct NestedHashCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    a: Vec<Value<F>>,
+    b: Vec<Value<F>>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for NestedHashCircuit<F, S, W>
+{
+    type Config = PoseidonConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        NestedHashCircuit {
+            a: vec![Value::unknown(); self.a.len()],
+            b: vec![Value::unknown(); self.b.len()],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        PoseidonConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: PoseidonConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let size = S::element_size();
+        let chip = PoseidonChip::new(config.arth_config);
+
+        let (digest_a, _) = sponge::<F, S, W>(&chip, &mut layouter, &self.a)?;
+        let (digest_b, _) = sponge::<F, S, W>(&chip, &mut layouter, &self.b)?;
+
+        // the outer sponge absorbs both inner digests
+        let inner = digest_a
+            .0
+            .iter()
+            .take(size)
+            .chain(digest_b.0.iter().take(size))
+            .map(|d| d.0.clone())
+            .collect::<Vec<_>>();
+        let inner_values = inner.iter().map(|c| c.value().copied()).collect::<Vec<_>>();
+        let (digest, loaded) = sponge::<F, S, W>(&chip, &mut layouter, &inner_values)?;
+
+        layouter.assign_region(
+            || "link inner digests",
+            |mut region| {
+                for (l, d) in loaded.iter().zip(inner.iter()) {
+                    region.constrain_equal(l.0.cell(), d.cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        chip.expose_public(&mut layouter, digest, size)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> NestedHashCircuit<F, S, W> {
+    pub fn new(a: Vec<F>, b: Vec<F>) -> NestedHashCircuit<F, S, W> {
+        NestedHashCircuit {
+            a: to_values(&a),
+            b: to_values(&b),
+            _marker: PhantomData,
+        }
+    }
+}
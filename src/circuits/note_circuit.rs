@@ -0,0 +1,130 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// A shielded note: the commitment poseidon(value, blinding) of a private
+// value is a leaf of a tree of depth M. The instance column only holds the
+// root, the value, the blinding, the position of the note and the siblings
+// stay private.
+#[derive(Clone)]
+pub struct NoteCircuit<F: PrimeField, S: Spec<F, W>, const M: usize, const W: usize, const I: usize>
+{
+    value: Value<F>,
+    blinding: Value<F>,
+    index: Value<F>,
+    siblings: Vec<[Value<F>; I]>,
+    _marker: PhantomData<S>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for NoteCircuit<F, S, M, W, I>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        NoteCircuit {
+            value: Value::unknown(),
+            blinding: Value::unknown(),
+            index: Value::unknown(),
+            siblings: vec![[Value::unknown(); I]; M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+        assert_eq!(2 % I, 0, "the value and blinding are whole elements");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        // the note commitment is the leaf
+        let (commitment, _) =
+            sponge::<F, S, W>(&poseidon, &mut layouter, &[self.value, self.blinding])?;
+        let mut current = commitment.0[..I]
+            .iter()
+            .map(|d| arth.load_private(layouter.namespace(|| "load leaf"), d.0.value().copied()))
+            .collect::<Result<Vec<_>, _>>()?;
+        layouter.assign_region(
+            || "link leaf",
+            |mut region| {
+                for (d, l) in commitment.0.iter().zip(current.iter()) {
+                    region.constrain_equal(d.0.cell(), l.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        let index = arth.load_private(layouter.namespace(|| "load index"), self.index)?;
+        let bits = arth.unpack_flags(layouter.namespace(|| "index bits"), index, M)?;
+        for (right, sibling) in bits.into_iter().zip(self.siblings.iter()) {
+            let sibling = sibling
+                .iter()
+                .map(|s| arth.load_private(layouter.namespace(|| "load sibling"), *s))
+                .collect::<Result<Vec<_>, _>>()?;
+            current =
+                hash_layer::<F, S, W>(&poseidon, &arth, &mut layouter, current, sibling, right)?;
+        }
+
+        for (j, node) in current.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose root"), node, j)?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > NoteCircuit<F, S, M, W, I>
+{
+    /// The note `(value, blinding)` at leaf `index`, `siblings` holds the
+    /// `M` nodes next to the path from its commitment up to the root
+    pub fn new(value: F, blinding: F, index: u64, siblings: Vec<Vec<F>>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        NoteCircuit {
+            value: Value::known(value),
+            blinding: Value::known(blinding),
+            index: Value::known(F::from(index)),
+            siblings: siblings
+                .into_iter()
+                .map(|s| {
+                    s.into_iter()
+                        .map(Value::known)
+                        .collect::<Vec<_>>()
+                        .try_into()
+                        .expect("a sibling is one node")
+                })
+                .collect(),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// leaf = poseidon(secret) is a leaf of a tree of depth M and
+// nullifier = poseidon(secret, index) where index is the position of the leaf.
+// The index element is [index, 0, ...] to fill a node. Every one of the M
+// layers is hashed and the index is decomposed into exactly M bits, one per
+// layer, so a position has a single index and a single nullifier. The
+// instance column only holds [root, nullifier], the secret, the leaf, its
+// index and the siblings stay private.
+#[derive(Clone)]
+pub struct NullifierCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    secret: [Value<F>; I],
+    index: Value<F>,
+    siblings: Vec<[Value<F>; I]>,
+    _marker: PhantomData<S>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for NullifierCircuit<F, S, M, W, I>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        NullifierCircuit {
+            secret: [Value::unknown(); I],
+            index: Value::unknown(),
+            siblings: vec![[Value::unknown(); I]; M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert!(M > 0, "the tree has at least one layer");
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        // leaf = poseidon(secret)
+        let (digest, secret) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.secret)?;
+        let mut current = digest.0[..I]
+            .iter()
+            .map(|d| arth.load_private(layouter.namespace(|| "load leaf"), d.0.value().copied()))
+            .collect::<Result<Vec<_>, _>>()?;
+        layouter.assign_region(
+            || "link leaf",
+            |mut region| {
+                for (d, l) in digest.0.iter().zip(current.iter()) {
+                    region.constrain_equal(d.0.cell(), l.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        // index < 2^M, bit i picks the side of layer i
+        let index = arth.load_private(layouter.namespace(|| "load index"), self.index)?;
+        let bits = arth.unpack_flags(layouter.namespace(|| "index bits"), index.clone(), M)?;
+        for (right, sibling) in bits.into_iter().zip(self.siblings.iter()) {
+            let sibling = sibling
+                .iter()
+                .map(|s| arth.load_private(layouter.namespace(|| "load sibling"), *s))
+                .collect::<Result<Vec<_>, _>>()?;
+            current =
+                hash_layer::<F, S, W>(&poseidon, &arth, &mut layouter, current, sibling, right)?;
+        }
+        for (j, node) in current.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose root"), node, j)?;
+        }
+
+        // nullifier = poseidon(secret, [index, 0, ...])
+        let zero = arth.affine(
+            layouter.namespace(|| "zero"),
+            F::ZERO,
+            index.clone(),
+            F::ZERO,
+        )?;
+        let element = std::iter::once(&index)
+            .chain(std::iter::repeat_n(&zero, I - 1))
+            .collect::<Vec<_>>();
+        let inputs = self
+            .secret
+            .iter()
+            .copied()
+            .chain(element.iter().map(|n| n.cell().value().copied()))
+            .collect::<Vec<_>>();
+        let (hashed, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &inputs)?;
+        let nullifier = hashed.0[..I]
+            .iter()
+            .map(|d| {
+                arth.load_private(
+                    layouter.namespace(|| "load nullifier"),
+                    d.0.value().copied(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        layouter.assign_region(
+            || "link nullifier",
+            |mut region| {
+                for (l, s) in loaded.iter().zip(secret.iter()) {
+                    region.constrain_equal(l.0.cell(), s.0.cell())?;
+                }
+                for (l, n) in loaded[I..].iter().zip(element.iter()) {
+                    region.constrain_equal(l.0.cell(), n.cell().cell())?;
+                }
+                for (d, n) in hashed.0.iter().zip(nullifier.iter()) {
+                    region.constrain_equal(d.0.cell(), n.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+        for (j, node) in nullifier.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose nullifier"), node, I + j)?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > NullifierCircuit<F, S, M, W, I>
+{
+    /// The owner of `secret`, whose leaf `poseidon(secret)` is at `index` of
+    /// a tree whose `M` nodes next to the path are `siblings`
+    pub fn new(secret: Vec<F>, index: u64, siblings: Vec<Vec<F>>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        let node = |n: Vec<F>| -> [Value<F>; I] {
+            n.into_iter()
+                .map(Value::known)
+                .collect::<Vec<_>>()
+                .try_into()
+                .expect("a node is I values")
+        };
+        NullifierCircuit {
+            secret: node(secret),
+            index: Value::known(F::from(index)),
+            siblings: siblings.into_iter().map(node).collect(),
+            _marker: PhantomData,
+        }
+    }
+}
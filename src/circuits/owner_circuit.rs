@@ -0,0 +1,137 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// Ownership of a public key: the public key is the poseidon digest of a
+// private secret key of one element. With M > 0 the public key is also a
+// leaf at a private position of a registry tree of depth M, proving a
+// registered owner. The instance column holds the public key followed by
+// the root of the registry.
+#[derive(Clone)]
+pub struct OwnerCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    secret: [Value<F>; I],
+    index: Value<F>,
+    siblings: Vec<[Value<F>; I]>,
+    _marker: PhantomData<S>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for OwnerCircuit<F, S, M, W, I>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        OwnerCircuit {
+            secret: [Value::unknown(); I],
+            index: Value::unknown(),
+            siblings: vec![[Value::unknown(); I]; M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), I, "a key is one element of the spec");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        let (digest, _) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.secret)?;
+        let key = digest.0[..I]
+            .iter()
+            .map(|d| arth.load_private(layouter.namespace(|| "load key"), d.0.value().copied()))
+            .collect::<Result<Vec<_>, _>>()?;
+        layouter.assign_region(
+            || "link key",
+            |mut region| {
+                for (d, k) in digest.0.iter().zip(key.iter()) {
+                    region.constrain_equal(d.0.cell(), k.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+        for (j, k) in key.iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose key"), k.clone(), j)?;
+        }
+        if M == 0 {
+            return Ok(());
+        }
+
+        // the key is the leaf of the registry
+        let index = arth.load_private(layouter.namespace(|| "load index"), self.index)?;
+        let bits = arth.unpack_flags(layouter.namespace(|| "index bits"), index, M)?;
+        let mut current = key;
+        for (right, sibling) in bits.into_iter().zip(self.siblings.iter()) {
+            let sibling = sibling
+                .iter()
+                .map(|s| arth.load_private(layouter.namespace(|| "load sibling"), *s))
+                .collect::<Result<Vec<_>, _>>()?;
+            current =
+                hash_layer::<F, S, W>(&poseidon, &arth, &mut layouter, current, sibling, right)?;
+        }
+
+        for (j, node) in current.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose root"), node, I + j)?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > OwnerCircuit<F, S, M, W, I>
+{
+    /// The owner of `secret`, registered at leaf `index` of a tree whose
+    /// `M` nodes next to the path are `siblings`. Without a registry, M is 0
+    /// and both are ignored.
+    pub fn new(secret: Vec<F>, index: u64, siblings: Vec<Vec<F>>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        let node = |n: Vec<F>| -> [Value<F>; I] {
+            n.into_iter()
+                .map(Value::known)
+                .collect::<Vec<_>>()
+                .try_into()
+                .expect("a node is I values")
+        };
+        OwnerCircuit {
+            secret: node(secret),
+            index: Value::known(F::from(index)),
+            siblings: siblings.into_iter().map(node).collect(),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+/// One operation of a `Pipeline`, working on a stack of numbers
+#[derive(Clone, Debug)]
+pub enum Step<F: PrimeField> {
+    /// push a private value
+    LoadPrivate(Value<F>),
+    /// push the value at a row of the instance column
+    LoadPublic(usize),
+    /// pop that many numbers, the first popped being the last input, and
+    /// push the `S::element_size()` values of their poseidon digest
+    Hash(usize),
+    /// pop a node of `S::element_size()` numbers and push the root of the
+    /// path through the private siblings, from the leaves up. The index bit
+    /// of layer `i` is read from the instance row `index_row + i`.
+    MerklePath {
+        siblings: Vec<Vec<Value<F>>>,
+        index_row: usize,
+    },
+    /// pop that many numbers and expose them in order from the instance row
+    Expose { count: usize, row: usize },
+}
+
+// A circuit synthesized from a linear list of steps over the arth and
+// poseidon chips of a CombinedConfig, for composing the chips without a
+// Circuit impl of its own. Every step gets its own namespace.
+#[derive(Clone, Debug)]
+pub struct Pipeline<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    steps: Vec<Step<F>>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> Default for Pipeline<F, S, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> Pipeline<F, S, W> {
+    pub fn new() -> Self {
+        Pipeline {
+            steps: vec![],
+            _marker: PhantomData,
+        }
+    }
+
+    /// The steps declared so far
+    pub fn steps(&self) -> &[Step<F>] {
+        &self.steps
+    }
+
+    fn then(mut self, step: Step<F>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// See `Step::LoadPrivate`
+    pub fn load_private(self, value: F) -> Self {
+        self.then(Step::LoadPrivate(Value::known(value)))
+    }
+
+    /// See `Step::LoadPublic`
+    pub fn load_public(self, row: usize) -> Self {
+        self.then(Step::LoadPublic(row))
+    }
+
+    /// See `Step::Hash`
+    pub fn hash(self, count: usize) -> Self {
+        self.then(Step::Hash(count))
+    }
+
+    /// See `Step::MerklePath`
+    pub fn merkle_path(self, siblings: Vec<Vec<F>>, index_row: usize) -> Self {
+        let siblings = siblings.into_iter().map(|s| to_values(&s)).collect();
+        self.then(Step::MerklePath {
+            siblings,
+            index_row,
+        })
+    }
+
+    /// See `Step::Expose`
+    pub fn expose(self, count: usize, row: usize) -> Self {
+        self.then(Step::Expose { count, row })
+    }
+}
+
+/// Pop the last `count` numbers of the stack, in the order they were pushed
+fn pop<F: PrimeField>(stack: &mut Vec<Number<F>>, count: usize) -> Vec<Number<F>> {
+    assert!(
+        stack.len() >= count,
+        "a step needs {count} numbers but the stack holds {}",
+        stack.len()
+    );
+    stack.split_off(stack.len() - count)
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for Pipeline<F, S, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        let unknown = |v: &[Value<F>]| vec![Value::unknown(); v.len()];
+        Pipeline {
+            steps: self
+                .steps
+                .iter()
+                .map(|step| match step {
+                    Step::LoadPrivate(_) => Step::LoadPrivate(Value::unknown()),
+                    Step::MerklePath {
+                        siblings,
+                        index_row,
+                    } => Step::MerklePath {
+                        siblings: siblings.iter().map(|s| unknown(s)).collect(),
+                        index_row: *index_row,
+                    },
+                    step => step.clone(),
+                })
+                .collect(),
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+        let size = S::element_size();
+
+        let mut stack = vec![];
+        for (i, step) in self.steps.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("step {i}"));
+            match step {
+                Step::LoadPrivate(value) => {
+                    stack.push(arth.load_private(layouter.namespace(|| "load private"), *value)?);
+                }
+                Step::LoadPublic(row) => {
+                    stack.push(arth.load_public(layouter.namespace(|| "load public"), *row)?);
+                }
+                Step::Hash(count) => {
+                    let inputs = pop(&mut stack, *count);
+                    let values = inputs
+                        .iter()
+                        .map(|n| n.cell().value().copied())
+                        .collect::<Vec<_>>();
+                    let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &values)?;
+                    let lanes = digest.0[..size]
+                        .iter()
+                        .map(|d| {
+                            arth.load_private(
+                                layouter.namespace(|| "load digest"),
+                                d.0.value().copied(),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    layouter.assign_region(
+                        || "link hash",
+                        |mut region| {
+                            for (l, n) in loaded.iter().zip(inputs.iter()) {
+                                region.constrain_equal(l.0.cell(), n.cell().cell())?;
+                            }
+                            for (d, n) in digest.0.iter().zip(lanes.iter()) {
+                                region.constrain_equal(d.0.cell(), n.cell().cell())?;
+                            }
+                            Ok(())
+                        },
+                    )?;
+                    stack.extend(lanes);
+                }
+                Step::MerklePath {
+                    siblings,
+                    index_row,
+                } => {
+                    let mut current = pop(&mut stack, size);
+                    for (layer, sibling) in siblings.iter().enumerate() {
+                        let right = arth
+                            .load_public(layouter.namespace(|| "load index"), index_row + layer)?;
+                        let sibling = sibling
+                            .iter()
+                            .map(|s| arth.load_private(layouter.namespace(|| "load sibling"), *s))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        current = hash_layer::<F, S, W>(
+                            &poseidon,
+                            &arth,
+                            &mut layouter,
+                            current,
+                            sibling,
+                            right,
+                        )?;
+                    }
+                    stack.extend(current);
+                }
+                Step::Expose { count, row } => {
+                    for (j, n) in pop(&mut stack, *count).into_iter().enumerate() {
+                        arth.expose_public(layouter.namespace(|| "expose"), n, row + j)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,42 +2,35 @@ use std::marker::PhantomData;
 
 use self::utils::Spec;
 
+use super::super::chips::arth_chips::{ArthChip, ArthConfig, NumericInstructions};
 use super::super::chips::poseidon_chip::*;
+use super::to_values;
+use crate::debug::CircuitError;
+use crate::native::poseidon::{hash, hash_squeeze, length_prefix};
 use ff::PrimeField;
 use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
-use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use halo2_proofs::plonk::{Circuit, Column, ConstraintSystem, Error, Instance};
 
 pub mod utils;
 
 #[derive(Clone)]
-pub struct PoseidonConfig<F: PrimeField, S: Spec<F, W>, const W: usize> {
-    arth_config: PoseidonArthConfig<F, W>,
+pub struct PoseidonConfig<F: PrimeField, S: Spec<F, W>, const W: usize, const R: usize = 1> {
+    pub(crate) arth_config: PoseidonArthConfig<F, W>,
     _marker: PhantomData<S>,
 }
 
-// implementation for 5-posiedon
-// For each input, we fixed the padding as [x,1,0,0,...,0]
-// inputs permutation rounds will go for all abosrb
-#[derive(Clone, Default)]
-pub struct PoseidonCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
-    x: Vec<Value<F>>,
-    _marker: PhantomData<S>,
-}
-
-impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
-    for PoseidonCircuit<F, S, W>
-{
-    type Config = PoseidonConfig<F, S, W>;
-
-    type FloorPlanner = SimpleFloorPlanner;
-
-    fn without_witnesses(&self) -> Self {
-        Self::default()
-    }
+impl<F: PrimeField, S: Spec<F, W>, const W: usize, const R: usize> PoseidonConfig<F, S, W, R> {
+    /// Allocate the columns of a single poseidon chip for spec `S`,
+    /// laying out `R` rounds per row
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        S::check_rate();
 
-    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-        let states: Vec<_> = (0..W).map(|_| meta.advice_column()).collect();
-        let arks: Vec<_> = (0..W).map(|_| meta.fixed_column()).collect();
+        let states: Vec<[_; W]> = (0..R)
+            .map(|_| [(); W].map(|_| meta.advice_column()))
+            .collect();
+        let arks: Vec<[_; W]> = (0..R)
+            .map(|_| [(); W].map(|_| meta.fixed_column()))
+            .collect();
 
         // public column for output
         let output = meta.instance_column();
@@ -47,22 +40,101 @@ impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
         let ark_paras = S::arks();
 
         PoseidonConfig {
-            arth_config: PoseidonChip::configure(
+            arth_config: PoseidonChip::configure_unrolled::<S>(
                 meta,
-                states.try_into().unwrap(),
+                states,
                 output,
-                arks.try_into().unwrap(),
+                arks,
                 mds,
                 ark_paras,
                 S::capacity(),
-            ),
+            )
+            .expect("the mds comes from the spec")
+            .check_against::<S>(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A poseidon chip for spec `S` and an arth chip on their own advice and
+/// fixed columns, both bound to the same instance column.
+#[derive(Clone)]
+pub struct CombinedConfig<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    poseidon_config: PoseidonConfig<F, S, W>,
+    arth_config: ArthConfig,
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> CombinedConfig<F, S, W> {
+    /// Allocate the poseidon columns, then the arth columns reusing the
+    /// poseidon instance column
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let poseidon_config = PoseidonConfig::configure(meta);
+
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let instance = poseidon_config.arth_config.output();
+        let coeff = [meta.fixed_column(), meta.fixed_column()];
+
+        CombinedConfig {
+            poseidon_config,
+            arth_config: ArthChip::configure(meta, advice, instance, coeff),
+        }
+    }
+
+    /// Both chips of the config
+    pub fn chips(&self) -> (PoseidonChip<F, W>, ArthChip<F>) {
+        (
+            PoseidonChip::new(self.poseidon_config.arth_config.clone()),
+            ArthChip::new(self.arth_config.clone()),
+        )
+    }
+}
+
+// implementation for 5-posiedon
+// For each input, we fixed the padding as [x,1,0,0,...,0]
+// inputs permutation rounds will go for all abosrb
+// R rounds are laid out per row, trading W advice columns per lane for rows
+#[derive(Clone)]
+pub struct PoseidonCircuit<F: PrimeField, S: Spec<F, W>, const W: usize, const R: usize = 1> {
+    x: Vec<Value<F>>,
+    /// elements squeezed to the instance column, the first is the digest
+    outputs: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize, const R: usize> Default
+    for PoseidonCircuit<F, S, W, R>
+{
+    fn default() -> Self {
+        PoseidonCircuit {
+            x: vec![],
+            outputs: 1,
             _marker: PhantomData,
         }
     }
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize, const R: usize> Circuit<F>
+    for PoseidonCircuit<F, S, W, R>
+{
+    type Config = PoseidonConfig<F, S, W, R>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        PoseidonCircuit {
+            x: vec![Value::unknown(); self.x.len()],
+            outputs: self.outputs,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        PoseidonConfig::configure(meta)
+    }
 
     fn synthesize(
         &self,
-        config: PoseidonConfig<F, S, W>,
+        config: PoseidonConfig<F, S, W, R>,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
         let size = S::element_size();
@@ -80,8 +152,8 @@ impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
             .x
             .chunks(size)
             .map(|c| {
-                c.to_vec()
-                    .into_iter()
+                c.iter()
+                    .copied()
                     .chain(S::pad().into_iter().map(|v| Value::known(v.to_owned())))
                     .collect::<Vec<_>>()
             })
@@ -93,21 +165,245 @@ impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
             state = chip.permutation(&mut layouter, state, fr, pr)?;
         }
 
-        // squeeze
+        // squeeze, one more permutation for every next element
         chip.expose_public(&mut layouter, state.clone(), size)?;
+        for i in 1..self.outputs {
+            state = chip.permutation(&mut layouter, state, fr, pr)?;
+            chip.expose_public_at(&mut layouter, state.clone(), size, i * size)?;
+        }
 
-        return Ok(());
+        Ok(())
     }
 }
 
-impl<F: PrimeField, S: Spec<F, W>, const W: usize> PoseidonCircuit<F, S, W> {
-    pub fn new(input: Vec<F>) -> PoseidonCircuit<F, S, W> {
+/// The inputs to hash, a non-empty multiple of `S::element_size()` values
+impl<F: PrimeField, S: Spec<F, W>, const W: usize, const R: usize> TryFrom<&[F]>
+    for PoseidonCircuit<F, S, W, R>
+{
+    type Error = CircuitError;
+
+    fn try_from(x: &[F]) -> Result<Self, CircuitError> {
+        let chunk = S::element_size();
+        if x.is_empty() || !x.len().is_multiple_of(chunk) {
+            return Err(CircuitError::WitnessNotChunked {
+                chunk,
+                supplied: x.len(),
+            });
+        }
+        Ok(Self::new(x.to_vec()))
+    }
+}
+
+/// absorb all inputs into a fresh sponge, returning the final state and
+/// the cells that the inputs were loaded into.
+pub(crate) fn sponge<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    chip: &PoseidonChip<F, W>,
+    layouter: &mut impl Layouter<F>,
+    x: &[Value<F>],
+) -> Result<(States<F, W>, Vec<Data<F>>), Error> {
+    let size = S::element_size();
+    assert_eq!(x.len() % size, 0);
+    assert!(!x.is_empty());
+
+    let mut state = chip.initiate(layouter)?;
+    let mut loaded = vec![];
+    for c in x.chunks(size) {
+        let padded = c
+            .iter()
+            .copied()
+            .chain(S::pad().into_iter().map(Value::known))
+            .collect::<Vec<_>>();
+        let (s, l) = chip.load_inputs(layouter, state, &padded)?;
+        loaded.extend(l.into_iter().take(size));
+        state = chip.permutation(layouter, s, S::full_rounds(), S::partial_rounds())?;
+    }
+
+    Ok((state, loaded))
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize, const R: usize> PoseidonCircuit<F, S, W, R> {
+    /// Rows used to hash `n` inputs: one initial state, then a load and a
+    /// permutation for every chunk of `S::element_size()` inputs.
+    pub fn rows_for(n: usize) -> usize {
+        Self::rows_for_outputs(n, 1)
+    }
+
+    /// Rows used to hash `n` inputs and squeeze `outputs` elements, one
+    /// more permutation for every element after the first.
+    pub fn rows_for_outputs(n: usize, outputs: usize) -> usize {
+        let chunks = n.div_ceil(S::element_size());
+        let permutation_rows =
+            PoseidonChip::<F, W>::permutation_rows(S::full_rounds(), S::partial_rounds(), R);
+        PoseidonChip::<F, W>::INITIATE_ROWS
+            + chunks * (PoseidonChip::<F, W>::LOAD_ROWS + permutation_rows)
+            + (outputs - 1) * permutation_rows
+    }
+
+    /// Number of permutations run to hash the inputs, one per chunk of
+    /// `S::element_size()` inputs, and one per squeezed element after the
+    /// first.
+    pub fn permutations(&self) -> usize {
+        self.x.len().div_ceil(S::element_size()) + self.outputs - 1
+    }
+
+    /// The instance column of the circuit made by
+    /// `new_with_outputs(input, outputs)`, see `native::poseidon::hash_squeeze`.
+    pub fn expected_public_with_outputs(input: &[F], outputs: usize) -> Vec<F> {
+        hash_squeeze::<F, S, W>(input.to_vec(), outputs)
+    }
+
+    /// Hash `input` prefixed with its length, see
+    /// `native::poseidon::hash_with_length`. Like the padding, the prefix is
+    /// witnessed with the inputs, hashing `n` inputs takes
+    /// `rows_for(n + S::element_size())` rows.
+    pub fn hash_with_length(input: Vec<F>) -> PoseidonCircuit<F, S, W, R> {
+        let mut prefixed = length_prefix::<F, S, W>(input.len());
+        prefixed.extend(input);
+        Self::new(prefixed)
+    }
+
+    /// The instance column of the circuit made by `new(input)`: the digest
+    /// of `input`, one element from row 0.
+    pub fn expected_public(input: &[F]) -> Vec<F> {
+        hash::<F, S, W>(input.to_vec())
+    }
+
+    pub fn new(input: Vec<F>) -> PoseidonCircuit<F, S, W, R> {
+        Self::new_with_outputs(input, 1)
+    }
+
+    /// Hash `input` and squeeze `outputs` elements of `S::element_size()`
+    /// values to consecutive rows of the instance column, from row 0.
+    pub fn new_with_outputs(input: Vec<F>, outputs: usize) -> PoseidonCircuit<F, S, W, R> {
+        assert!(outputs > 0, "squeeze at least one element");
         PoseidonCircuit {
             x: input
                 .into_iter()
                 .map(|x| -> Value<F> { Value::known(x) })
                 .collect(),
+            outputs,
             _marker: PhantomData,
         }
     }
 }
+
+/// A `CombinedConfig` with a second instance column for the low limbs
+#[derive(Clone)]
+pub struct LimbConfig<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    combined: CombinedConfig<F, S, W>,
+    lo: Column<Instance>,
+}
+
+// digest = hi * 2^128 + lo for every value of the digest of the inputs
+// The high limbs go to the first instance column and the low limbs to the
+// second one, row i of both holding the limbs of value i, as EVM verifiers
+// read a digest in two 128 bits words. hi is checked below
+// 2^(NUM_BITS - 129) so that the limbs stay below the modulus and are the
+// canonical ones; a digest of NUM_BITS bits has no such limbs and is
+// rejected, which happens with negligible probability.
+#[derive(Clone)]
+pub struct PoseidonLimbCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    x: Vec<Value<F>>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for PoseidonLimbCircuit<F, S, W>
+{
+    type Config = LimbConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        PoseidonLimbCircuit {
+            x: vec![Value::unknown(); self.x.len()],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert!(F::NUM_BITS > 129, "the digest must not fit in one limb");
+        let combined = CombinedConfig::configure(meta);
+        let lo = meta.instance_column();
+        meta.enable_equality(lo);
+        LimbConfig { combined, lo }
+    }
+
+    fn synthesize(
+        &self,
+        config: LimbConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.combined.chips();
+        let (digest, _) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.x)?;
+
+        for (i, lane) in digest.0[..S::element_size()].iter().enumerate() {
+            let value = arth.load_private(
+                layouter.namespace(|| "load digest"),
+                lane.0.value().copied(),
+            )?;
+            layouter.assign_region(
+                || "link digest",
+                |mut region| region.constrain_equal(lane.0.cell(), value.cell().cell()),
+            )?;
+
+            // split unevenly, the limbs only bound by the recombination
+            let limbs = value.cell().value().map(|v| limbs(v));
+            let hi = arth.load_private(layouter.namespace(|| "load hi"), limbs.map(|l| l.0))?;
+            let lo = arth.load_private(layouter.namespace(|| "load lo"), limbs.map(|l| l.1))?;
+            arth.range_check(layouter.namespace(|| "lo bits"), lo.clone(), 128)?;
+            arth.range_check(
+                layouter.namespace(|| "canonical hi"),
+                hi.clone(),
+                F::NUM_BITS as usize - 129,
+            )?;
+            let shifted = arth.affine(
+                layouter.namespace(|| "hi * 2^128"),
+                F::from_u128(1 << 64).square(),
+                hi.clone(),
+                F::ZERO,
+            )?;
+            let sum = arth.add(
+                layouter.namespace(|| "hi * 2^128 + lo"),
+                shifted,
+                lo.clone(),
+            )?;
+            layouter.assign_region(
+                || "link limbs",
+                |mut region| region.constrain_equal(sum.cell().cell(), value.cell().cell()),
+            )?;
+            arth.expose_public(layouter.namespace(|| "expose hi"), hi, i)?;
+            layouter.constrain_instance(lo.cell().cell(), config.lo, i)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> PoseidonLimbCircuit<F, S, W> {
+    pub fn new(input: Vec<F>) -> Self {
+        assert_eq!(input.len() % S::element_size(), 0);
+        assert!(!input.is_empty());
+        PoseidonLimbCircuit {
+            x: to_values(&input),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The two instance columns of the circuit made by `new(input)`, the
+    /// high then the low limbs of the digest of `input`
+    pub fn expected_public(input: &[F]) -> Vec<Vec<F>> {
+        let (hi, lo) = hash::<F, S, W>(input.to_vec()).iter().map(limbs).unzip();
+        vec![hi, lo]
+    }
+}
+
+/// The high and low 128 bits limbs of `v`, which must fit in 256 bits
+fn limbs<F: PrimeField>(v: &F) -> (F, F) {
+    let repr = v.to_repr();
+    let limb = |bytes: &[u8]| {
+        let mut le = [0u8; 16];
+        le[..bytes.len()].copy_from_slice(bytes);
+        F::from_u128(u128::from_le_bytes(le))
+    };
+    (limb(&repr.as_ref()[16..]), limb(&repr.as_ref()[..16]))
+}
@@ -0,0 +1,132 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// poseidon(nonce, data)[0] = 2^k * q
+// for a private nonce and public data, so the low k bits of the first value
+// of the digest are zero. q is witnessed below 2^(NUM_BITS - 1 - k), so
+// 2^k * q is below the modulus and the product cannot wrap around it. A
+// digest of NUM_BITS bits has no such q and is rejected even with its low
+// bits zero, which happens with negligible probability. The instance column
+// is [data, k], the difficulty k shapes the circuit and is checked against
+// the public one.
+#[derive(Clone)]
+pub struct ProofOfWorkCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    nonce: Value<F>,
+    data_len: usize,
+    k: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for ProofOfWorkCircuit<F, S, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ProofOfWorkCircuit {
+            nonce: Value::unknown(),
+            ..self.clone()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+        let k = self.k;
+
+        let mut inputs = vec![arth.load_private(layouter.namespace(|| "load nonce"), self.nonce)?];
+        for row in 0..self.data_len {
+            inputs.push(arth.load_public(layouter.namespace(|| "load data"), row)?);
+        }
+        let values = inputs
+            .iter()
+            .map(|n| n.cell().value().copied())
+            .collect::<Vec<_>>();
+        let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &values)?;
+        let first = &digest.0[0].0;
+
+        // q = digest >> k, left unconstrained but by the product below
+        let q = first.value().map(|d| shift_right(d, k));
+        let q = arth.load_private(layouter.namespace(|| "load quotient"), q)?;
+        arth.range_check(
+            layouter.namespace(|| "quotient bits"),
+            q.clone(),
+            F::NUM_BITS as usize - 1 - k,
+        )?;
+        let product = arth.affine(
+            layouter.namespace(|| "2^k * q"),
+            F::from(2).pow([k as u64]),
+            q,
+            F::ZERO,
+        )?;
+
+        layouter.assign_region(
+            || "link digest",
+            |mut region| {
+                for (l, n) in loaded.iter().zip(inputs.iter()) {
+                    region.constrain_equal(l.0.cell(), n.cell().cell())?;
+                }
+                region.constrain_equal(first.cell(), product.cell().cell())
+            },
+        )?;
+
+        // the public difficulty is the one of the circuit
+        let public = arth.load_public(layouter.namespace(|| "load k"), self.data_len)?;
+        let constant = arth.affine(
+            layouter.namespace(|| "k"),
+            F::ZERO,
+            public.clone(),
+            F::from(k as u64),
+        )?;
+        let same = arth.is_equal(layouter.namespace(|| "k matches"), public, constant)?;
+        arth.assert_nonzero(layouter.namespace(|| "assert k"), same)
+    }
+}
+
+/// The integer value of `v` shifted right by `k` bits
+fn shift_right<F: PrimeField>(v: &F, k: usize) -> F {
+    let repr = v.to_repr();
+    let bits = repr
+        .as_ref()
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .collect::<Vec<_>>();
+    bits[k..].iter().rev().fold(F::ZERO, |acc, &bit| {
+        acc.double() + if bit { F::ONE } else { F::ZERO }
+    })
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> ProofOfWorkCircuit<F, S, W> {
+    /// `1 + data_len` must be a multiple of `S::element_size()`, and the
+    /// difficulty `k` below `F::NUM_BITS - 1`.
+    pub fn new(nonce: F, data_len: usize, k: usize) -> Self {
+        assert_eq!(
+            (1 + data_len) % S::element_size(),
+            0,
+            "the nonce and data are whole elements"
+        );
+        assert!(k < F::NUM_BITS as usize - 1, "the difficulty is too high");
+        ProofOfWorkCircuit {
+            nonce: Value::known(nonce),
+            data_len,
+            k,
+            _marker: PhantomData,
+        }
+    }
+}
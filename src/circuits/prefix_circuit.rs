@@ -0,0 +1,118 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::CombinedConfig;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// d = poseidon(x[..k]) where c = poseidon(x)
+// The N private inputs are absorbed one element at a time, and the state
+// after the element ending at the public k is selected in circuit, so k must
+// be a positive multiple of S::element_size() up to N. The instance column
+// is [k, d, c], the commitment c to the whole vector binds the prefix.
+#[derive(Clone)]
+pub struct PrefixHashCircuit<F: PrimeField, S: Spec<F, W>, const N: usize, const W: usize> {
+    x: [Value<F>; N],
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const N: usize, const W: usize> Circuit<F>
+    for PrefixHashCircuit<F, S, N, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        PrefixHashCircuit {
+            x: [Value::unknown(); N],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(N % S::element_size(), 0, "the inputs are whole elements");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+        let size = S::element_size();
+
+        let k = arth.load_public(layouter.namespace(|| "load k"), 0)?;
+
+        let mut state = poseidon.initiate(&mut layouter)?;
+        let mut digest: Option<Vec<Number<F>>> = None;
+        let mut found = None;
+        for (j, element) in self.x.chunks(size).enumerate() {
+            state = poseidon.absorb::<S>(&mut layouter, state, element)?;
+
+            // the squeezed lanes, copied to the arth chip
+            let lanes = state.0[..size]
+                .iter()
+                .map(|d| {
+                    arth.load_private(layouter.namespace(|| "load lane"), d.0.value().copied())
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            layouter.assign_region(
+                || "link lanes",
+                |mut region| {
+                    for (d, l) in state.0.iter().zip(lanes.iter()) {
+                        region.constrain_equal(d.0.cell(), l.cell().cell())?;
+                    }
+                    Ok(())
+                },
+            )?;
+
+            // 1 if the prefix ends with this element, the fixed length comes
+            // from the coefficient of an affine gate
+            let end = F::from(((j + 1) * size) as u64);
+            let end = arth.affine(layouter.namespace(|| "prefix end"), F::ZERO, k.clone(), end)?;
+            let ends = arth.is_equal(layouter.namespace(|| "k == end"), k.clone(), end)?;
+
+            digest = Some(match digest {
+                Some(acc) => acc
+                    .into_iter()
+                    .zip(lanes)
+                    .map(|(a, l)| {
+                        arth.mul_add(layouter.namespace(|| "select lane"), ends.clone(), l, a)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => lanes
+                    .into_iter()
+                    .map(|l| arth.mul(layouter.namespace(|| "select lane"), ends.clone(), l))
+                    .collect::<Result<Vec<_>, _>>()?,
+            });
+            // the ends are distinct, so at most one flag is set
+            found = Some(match found {
+                Some(acc) => arth.add(layouter.namespace(|| "any end"), acc, ends)?,
+                None => ends,
+            });
+        }
+
+        let found = found.expect("at least one element is absorbed");
+        arth.assert_nonzero(layouter.namespace(|| "k ends an element"), found)?;
+        for (i, d) in digest.into_iter().flatten().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose prefix digest"), d, 1 + i)?;
+        }
+        poseidon.expose_public_at(&mut layouter, state, size, 1 + size)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const N: usize, const W: usize> PrefixHashCircuit<F, S, N, W> {
+    /// hash a prefix of `x`, its length is read from the instance column
+    pub fn new(x: [F; N]) -> PrefixHashCircuit<F, S, N, W> {
+        PrefixHashCircuit {
+            x: x.map(Value::known),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// y = prf_key(x), the W - 1 rate lanes of the sponge after absorbing
+// (key, x). The key is private, the instance column is [x, y].
+#[derive(Clone, Default)]
+pub struct PrfCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    key: Value<F>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for PrfCircuit<F, S, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        let input = arth.load_public(layouter.namespace(|| "load x"), 0)?;
+        let (state, loaded) = sponge::<F, S, W>(
+            &poseidon,
+            &mut layouter,
+            &[self.key, input.cell().value().copied()],
+        )?;
+        layouter.assign_region(
+            || "link x",
+            |mut region| region.constrain_equal(loaded[1].0.cell(), input.cell().cell()),
+        )?;
+
+        // squeeze every rate lane, not only one element
+        poseidon.expose_public_at(&mut layouter, state, W - 1, 1)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> PrfCircuit<F, S, W> {
+    /// evaluate the prf of `key`, the input is read from the instance column
+    pub fn new(key: F) -> PrfCircuit<F, S, W> {
+        PrfCircuit {
+            key: Value::known(key),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// A leaf is in a 4-ary tree of depth M, each node being one value and the
+// parent of c0..c3 being poseidon(c0, c1, c2, c3), so S absorbs 4 values per
+// element. The witness holds the 3 siblings of every layer in tree order.
+// The instance column is [leaf, position bits, root], the position p of the
+// node at each layer given as p = b0 + 2 * b1 with the lowest layer first.
+#[derive(Clone, Default)]
+pub struct QuadMerkleCircuit<F: PrimeField, S: Spec<F, W>, const M: usize, const W: usize> {
+    siblings: Vec<[Value<F>; 3]>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const M: usize, const W: usize> Circuit<F>
+    for QuadMerkleCircuit<F, S, M, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        QuadMerkleCircuit {
+            siblings: vec![[Value::unknown(); 3]; M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), 4, "the 4 children form one element");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        let mut current = arth.load_public(layouter.namespace(|| "load leaf"), 0)?;
+
+        for (i, siblings) in self.siblings.iter().enumerate() {
+            let b0 = arth.load_public(layouter.namespace(|| "load b0"), 1 + 2 * i)?;
+            let b1 = arth.load_public(layouter.namespace(|| "load b1"), 2 + 2 * i)?;
+
+            let mut children = vec![current];
+            for s in siblings {
+                children.push(arth.load_private(layouter.namespace(|| "load sibling"), *s)?);
+            }
+
+            // move the node from the first slot to slot p, keeping the
+            // siblings in order: it passes slot k when p > k
+            let passes = [
+                arth.or(layouter.namespace(|| "p > 0"), b0.clone(), b1.clone())?,
+                b1.clone(),
+                arth.and(layouter.namespace(|| "p > 2"), b0, b1)?,
+            ];
+            for (k, pass) in passes.into_iter().enumerate() {
+                let (a, b) = (children[k].clone(), children[k + 1].clone());
+                (children[k], children[k + 1]) =
+                    arth.conditional_swap(layouter.namespace(|| "move node"), pass, a, b)?;
+            }
+
+            let values = children
+                .iter()
+                .map(|c| c.cell().value().copied())
+                .collect::<Vec<_>>();
+            let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &values)?;
+            current = arth.load_private(
+                layouter.namespace(|| "load parent"),
+                digest.0[0].0.value().copied(),
+            )?;
+
+            layouter.assign_region(
+                || "link layer",
+                |mut region| {
+                    for (l, c) in loaded.iter().zip(children.iter()) {
+                        region.constrain_equal(l.0.cell(), c.cell().cell())?;
+                    }
+                    region.constrain_equal(digest.0[0].0.cell(), current.cell().cell())
+                },
+            )?;
+        }
+
+        arth.expose_public(layouter.namespace(|| "expose root"), current, 1 + 2 * M)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const M: usize, const W: usize> QuadMerkleCircuit<F, S, M, W> {
+    /// `siblings` holds the 3 other children of every layer in tree order,
+    /// from the leaf up to the root
+    pub fn new(siblings: Vec<[F; 3]>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        QuadMerkleCircuit {
+            siblings: siblings.into_iter().map(|s| s.map(Value::known)).collect(),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::super::chips::poseidon_chip::*;
+use super::merkle_common::adjacent_leaves;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// A private value committed to by a public poseidon(value) lies in the
+// public interval [lo, hi) of two adjacent leaves of a tree of depth M whose
+// leaves are sorted range endpoints, so the tree splits the values into
+// intervals and the proof shows the value is in the claimed one without
+// telling it. The nodes are single values, so S must absorb one value per
+// element. The instance column is [commitment, lo, hi, root], the index of
+// the endpoints and their siblings stay private. The value and the
+// endpoints must fit in `bits` bits.
+#[derive(Clone)]
+pub struct RangeTreeCircuit<F: PrimeField, S: Spec<F, W>, const M: usize, const W: usize> {
+    value: Value<F>,
+    endpoints: [Value<F>; 2],
+    index: Value<F>,
+    siblings: [Vec<Value<F>>; 2],
+    bits: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const M: usize, const W: usize> Circuit<F>
+    for RangeTreeCircuit<F, S, M, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        RangeTreeCircuit {
+            value: Value::unknown(),
+            endpoints: [Value::unknown(); 2],
+            index: Value::unknown(),
+            siblings: [vec![Value::unknown(); M], vec![Value::unknown(); M]],
+            bits: self.bits,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), 1, "a node is a single value");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        // commit to the value
+        let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &[self.value])?;
+        poseidon.expose_public(&mut layouter, digest, 1)?;
+        let value = arth.load_private(layouter.namespace(|| "load value"), self.value)?;
+
+        let (endpoints, root) = adjacent_leaves::<F, S, W, M>(
+            &poseidon,
+            &arth,
+            &mut layouter,
+            &self.endpoints,
+            self.index,
+            &self.siblings,
+        )?;
+
+        layouter.assign_region(
+            || "link commitment",
+            |mut region| region.constrain_equal(loaded[0].0.cell(), value.cell().cell()),
+        )?;
+
+        // lo <= value < hi, the comparisons need every input in range
+        for n in [&value, &endpoints[0], &endpoints[1]] {
+            arth.range_check(layouter.namespace(|| "range"), n.clone(), self.bits)?;
+        }
+        let below = arth.less_than(
+            layouter.namespace(|| "value < lo"),
+            value.clone(),
+            endpoints[0].clone(),
+            self.bits,
+        )?;
+        let not_below = arth.affine(layouter.namespace(|| "1 - below"), -F::ONE, below, F::ONE)?;
+        arth.assert_nonzero(layouter.namespace(|| "lo <= value"), not_below)?;
+        let under = arth.less_than(
+            layouter.namespace(|| "value < hi"),
+            value,
+            endpoints[1].clone(),
+            self.bits,
+        )?;
+        arth.assert_nonzero(layouter.namespace(|| "value < hi"), under)?;
+
+        for (row, endpoint) in endpoints.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose endpoint"), endpoint, 1 + row)?;
+        }
+        arth.expose_public(layouter.namespace(|| "expose root"), root, 3)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const M: usize, const W: usize>
+    RangeTreeCircuit<F, S, M, W>
+{
+    /// `endpoints` are the leaves at `index` and `index + 1`, `siblings` the
+    /// `M` nodes next to the path of each of them up to the root
+    pub fn new(
+        value: F,
+        endpoints: [F; 2],
+        index: u64,
+        siblings: [Vec<F>; 2],
+        bits: usize,
+    ) -> Self {
+        for s in &siblings {
+            assert_eq!(s.len(), M, "the path must have the full depth M = {M}");
+        }
+        RangeTreeCircuit {
+            value: Value::known(value),
+            endpoints: endpoints.map(Value::known),
+            index: Value::known(F::from(index)),
+            siblings: siblings.map(|s| to_values(&s)),
+            bits,
+            _marker: PhantomData,
+        }
+    }
+}
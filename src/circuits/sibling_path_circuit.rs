@@ -0,0 +1,112 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::CombinedConfig;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// Same statement as MerklePathCircuit for a path of full depth M, with the
+// witness given as the siblings of the leaf from bottom to top. Each layer
+// orders (current, sibling) by the public index bit before hashing, so the
+// left and right nodes are never witnessed. The instance column is laid out
+// as in MerklePathCircuit, [leaf, index, root].
+#[derive(Clone, Default)]
+pub struct SiblingPathCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    siblings: Vec<[Value<F>; I]>,
+    _marker: PhantomData<S>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for SiblingPathCircuit<F, S, M, W, I>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        SiblingPathCircuit {
+            siblings: vec![[Value::unknown(); I]; M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), I, "a node is one element of the spec");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        let mut current = (0..I)
+            .map(|j| arth.load_public(layouter.namespace(|| "load leaf"), j))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            let right = arth.load_public(layouter.namespace(|| "load index"), I + i)?;
+            let sibling = sibling
+                .iter()
+                .map(|s| arth.load_private(layouter.namespace(|| "load sibling"), *s))
+                .collect::<Result<Vec<_>, _>>()?;
+            current =
+                hash_layer::<F, S, W>(&poseidon, &arth, &mut layouter, current, sibling, right)?;
+        }
+
+        for (j, node) in current.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose root"), node, M + I + j)?;
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > SiblingPathCircuit<F, S, M, W, I>
+{
+    /// `siblings` holds the `M` nodes next to the path from the leaf up to
+    /// the root, the leaf itself is read from the instance column
+    pub fn new(siblings: Vec<Vec<F>>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        SiblingPathCircuit {
+            siblings: siblings
+                .into_iter()
+                .map(|s| {
+                    s.into_iter()
+                        .map(Value::known)
+                        .collect::<Vec<_>>()
+                        .try_into()
+                        .expect("a sibling is one node")
+                })
+                .collect(),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, CombinedConfig};
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// sorted(x) = y where poseidon(x) = c
+// The private values are sorted in circuit by a Batcher sorting network of
+// conditional swaps, so y is a permutation of x, and must all fit in `bits`
+// bits. The instance column is [c, y], the commitment taking one element.
+#[derive(Clone)]
+pub struct SortCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    x: Vec<Value<F>>,
+    bits: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for SortCircuit<F, S, W>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        SortCircuit {
+            x: vec![Value::unknown(); self.x.len()],
+            bits: self.bits,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+        let size = S::element_size();
+
+        // commit to the values
+        let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &self.x)?;
+        poseidon.expose_public(&mut layouter, digest, size)?;
+
+        let x = self
+            .x
+            .iter()
+            .map(|v| arth.load_private(layouter.namespace(|| "load x"), *v))
+            .collect::<Result<Vec<_>, _>>()?;
+        layouter.assign_region(
+            || "link commitment",
+            |mut region| {
+                for (l, v) in loaded.iter().zip(x.iter()) {
+                    region.constrain_equal(l.0.cell(), v.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        let sorted = arth.sort(layouter.namespace(|| "sort"), &x, self.bits)?;
+        for (i, y) in sorted.into_iter().enumerate() {
+            arth.expose_public(layouter.namespace(|| "expose y"), y, size + i)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> SortCircuit<F, S, W> {
+    /// the count of `x` must be a multiple of `S::element_size()`
+    pub fn new(x: Vec<F>, bits: usize) -> SortCircuit<F, S, W> {
+        assert!(!x.is_empty());
+        assert_eq!(x.len() % S::element_size(), 0, "x is whole elements");
+        SortCircuit {
+            x: to_values(&x),
+            bits,
+            _marker: PhantomData,
+        }
+    }
+}
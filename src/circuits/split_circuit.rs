@@ -0,0 +1,93 @@
+use std::marker::PhantomData;
+
+use super::super::chips::arth_chips::*;
+use super::super::chips::poseidon_chip::*;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::{sponge, PoseidonConfig};
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+#[derive(Clone)]
+pub struct SplitHashConfig<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    poseidon_config: PoseidonConfig<F, S, W>,
+    arth_config: ArthConfig,
+}
+
+// x = poseidon(hi, lo) where v = hi * 2^bits + lo
+// the private value v is split into two halves of `bits` bits, the digest x
+// goes to the first instance column. S must absorb both halves as one element.
+#[derive(Clone, Default)]
+pub struct SplitHashCircuit<F: PrimeField, S: Spec<F, W>, const W: usize> {
+    v: Value<F>,
+    bits: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<F: PrimeField, S: Spec<F, W> + Clone + Default, const W: usize> Circuit<F>
+    for SplitHashCircuit<F, S, W>
+{
+    type Config = SplitHashConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        SplitHashCircuit {
+            v: Value::unknown(),
+            bits: self.bits,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let poseidon_config = PoseidonConfig::configure(meta);
+
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let instance = meta.instance_column();
+        let coeff = [meta.fixed_column(), meta.fixed_column()];
+
+        SplitHashConfig {
+            poseidon_config,
+            arth_config: ArthChip::configure(meta, advice, instance, coeff),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: SplitHashConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        assert_eq!(S::element_size(), 2, "both halves form a single element");
+        let poseidon = PoseidonChip::new(config.poseidon_config.arth_config);
+        let arth = ArthChip::new(config.arth_config);
+
+        let v = arth.load_private(layouter.namespace(|| "load v"), self.v)?;
+        let (hi, lo) = arth.split(layouter.namespace(|| "split v"), v, self.bits)?;
+
+        let halves = [&hi, &lo].map(|h| h.cell().value().copied());
+        let (digest, loaded) = sponge::<F, S, W>(&poseidon, &mut layouter, &halves)?;
+
+        layouter.assign_region(
+            || "link halves",
+            |mut region| {
+                for (l, h) in loaded.iter().zip([&hi, &lo]) {
+                    region.constrain_equal(l.0.cell(), h.cell().cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        poseidon.expose_public(&mut layouter, digest, S::element_size())
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> SplitHashCircuit<F, S, W> {
+    pub fn new(v: F, bits: usize) -> SplitHashCircuit<F, S, W> {
+        SplitHashCircuit {
+            v: Value::known(v),
+            bits,
+            _marker: PhantomData,
+        }
+    }
+}
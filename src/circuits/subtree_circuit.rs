@@ -0,0 +1,123 @@
+use crate::chips::merkle_chip::{MerklePathChip, MerklePathInstruction};
+
+use super::super::chips::poseidon_chip::*;
+use super::dual_merkle_circuit::{DualMerkleCircuit, DualMerkleConfig};
+use super::merkle_circuit::MerklePathCircuit;
+use super::poseidon_circuit::utils::Spec;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// Two leaves of trees of max depth M share their ancestor `depth` layers
+// above them: both paths have the same nodes and index bits from layer
+// `depth` up, and so the same root. Each path has its own instance column
+// laid out as in MerklePathCircuit, [leaf, index, root].
+#[derive(Clone, Default)]
+pub struct SubtreeCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const I: usize,
+> {
+    first: MerklePathCircuit<F, S, M, W, I>,
+    second: MerklePathCircuit<F, S, M, W, I>,
+    depth: usize,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > Circuit<F> for SubtreeCircuit<F, S, M, W, I>
+{
+    type Config = DualMerkleConfig<F, S, M, M, W, I>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        SubtreeCircuit {
+            first: self.first.without_witnesses(),
+            second: self.second.without_witnesses(),
+            depth: self.depth,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        DualMerkleCircuit::<F, S, M, M, W, I>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: DualMerkleConfig<F, S, M, M, W, I>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let poseidon_chip = PoseidonChip::new(config.poseidon_config);
+        let first_chip = MerklePathChip::new(config.first);
+        let second_chip = MerklePathChip::new(config.second);
+
+        let first = self
+            .first
+            .load_layers(&poseidon_chip, &first_chip, &mut layouter)?;
+        let second = self
+            .second
+            .load_layers(&poseidon_chip, &second_chip, &mut layouter)?;
+
+        // the index bits are read from the public columns, there is none for
+        // the duplicated root layer
+        let mut bits = vec![];
+        for layer in self.depth..M {
+            bits.push((
+                first_chip.load_index(&mut layouter, layer)?,
+                second_chip.load_index(&mut layouter, layer)?,
+            ));
+        }
+
+        layouter.assign_region(
+            || "shared subtree",
+            |mut region| {
+                for layer in self.depth..=M {
+                    let nodes = first.left[layer].iter().zip(&second.left[layer]);
+                    let nodes = nodes.chain(first.right[layer].iter().zip(&second.right[layer]));
+                    for (a, b) in nodes {
+                        region.constrain_equal(a.cell(), b.cell())?;
+                    }
+                }
+                for (a, b) in bits.iter() {
+                    region.constrain_equal(a.cell(), b.cell())?;
+                }
+                Ok(())
+            },
+        )?;
+
+        first_chip.expose_public(&mut layouter, first.root, M + I)?;
+        second_chip.expose_public(&mut layouter, second.root, M + I)
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const I: usize,
+    > SubtreeCircuit<F, S, M, W, I>
+{
+    /// both paths are laid out as in `MerklePathCircuit::new`, their leaves
+    /// share the ancestor `depth` layers above them
+    pub fn new(
+        first: MerklePathCircuit<F, S, M, W, I>,
+        second: MerklePathCircuit<F, S, M, W, I>,
+        depth: usize,
+    ) -> Self {
+        assert!(depth <= M);
+        SubtreeCircuit {
+            first,
+            second,
+            depth,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::marker::PhantomData;
+
+use crate::chips::arth_chips::NumericInstructions;
+
+use super::merkle_common::hash_layer;
+use super::poseidon_circuit::utils::Spec;
+use super::poseidon_circuit::CombinedConfig;
+use super::to_values;
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+// A private leaf of a tree of depth M is one of N public candidates. The
+// nodes are single values, so S must absorb one value per element. The
+// instance column is [candidates, index, root], the leaf and its siblings
+// stay private.
+#[derive(Clone)]
+pub struct WhitelistCircuit<
+    F: PrimeField,
+    S: Spec<F, W>,
+    const M: usize,
+    const W: usize,
+    const N: usize,
+> {
+    leaf: Value<F>,
+    siblings: Vec<Value<F>>,
+    _marker: PhantomData<S>,
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const N: usize,
+    > Circuit<F> for WhitelistCircuit<F, S, M, W, N>
+{
+    type Config = CombinedConfig<F, S, W>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        WhitelistCircuit {
+            leaf: Value::unknown(),
+            siblings: vec![Value::unknown(); M],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert_eq!(S::element_size(), 1, "a node is a single value");
+        CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: CombinedConfig<F, S, W>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+
+        let candidates = (0..N)
+            .map(|i| arth.load_public(layouter.namespace(|| "load candidate"), i))
+            .collect::<Result<Vec<_>, _>>()?;
+        let leaf = arth.load_private(layouter.namespace(|| "load leaf"), self.leaf)?;
+        arth.assert_in_set(
+            layouter.namespace(|| "leaf in candidates"),
+            leaf.clone(),
+            &candidates,
+        )?;
+
+        let mut current = vec![leaf];
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            let right = arth.load_public(layouter.namespace(|| "load index"), N + i)?;
+            let sibling = arth.load_private(layouter.namespace(|| "load sibling"), *sibling)?;
+            current = hash_layer::<F, S, W>(
+                &poseidon,
+                &arth,
+                &mut layouter,
+                current,
+                vec![sibling],
+                right,
+            )?;
+        }
+
+        arth.expose_public(
+            layouter.namespace(|| "expose root"),
+            current[0].clone(),
+            N + M,
+        )
+    }
+}
+
+impl<
+        F: PrimeField,
+        S: Spec<F, W> + Clone + Default,
+        const M: usize,
+        const W: usize,
+        const N: usize,
+    > WhitelistCircuit<F, S, M, W, N>
+{
+    /// `siblings` holds the `M` nodes next to the path from the leaf up to
+    /// the root
+    pub fn new(leaf: F, siblings: Vec<F>) -> Self {
+        assert_eq!(
+            siblings.len(),
+            M,
+            "the path must have the full depth M = {M}"
+        );
+        WhitelistCircuit {
+            leaf: Value::known(leaf),
+            siblings: to_values(&siblings),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,498 @@
+//! Helpers for debugging circuits whose proofs fail to verify and for
+//! sizing their parameters.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use ff::Field;
+use halo2_proofs::{
+    arithmetic::CurveAffine,
+    circuit::Value,
+    dev::MockProver,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+        FloorPlanner, Instance, Selector,
+    },
+    poly::commitment::Params,
+};
+
+/// A public input that differs from the value the circuit binds to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceMismatch<F: Field> {
+    /// index of the instance column
+    pub column: usize,
+    /// row inside the instance column
+    pub row: usize,
+    /// value the witness binds to this instance cell
+    pub expected: F,
+    /// value that was supplied, `None` if the public vector is too short
+    pub supplied: Option<F>,
+}
+
+/// Why a public input vector does not fit the instance layout of a circuit.
+#[derive(Debug)]
+pub enum CircuitError {
+    /// the number of public columns differs from the instance columns
+    InstanceColumns { expected: usize, supplied: usize },
+    /// a public column is shorter than the rows the circuit binds
+    InstanceTooShort {
+        column: usize,
+        expected: usize,
+        supplied: usize,
+    },
+    /// a public column has trailing rows the circuit never binds
+    InstanceTooLong {
+        column: usize,
+        expected: usize,
+        supplied: usize,
+    },
+    /// a raw witness is empty or does not split into chunks of `chunk` values
+    WitnessNotChunked { chunk: usize, supplied: usize },
+    /// a raw witness has more values than the circuit takes
+    WitnessTooLong { expected: usize, supplied: usize },
+    /// the params for `k` would take more memory than the budget
+    ParamsTooLarge { k: u32, bytes: usize, budget: usize },
+    /// the circuit failed to synthesize
+    Synthesis(Error),
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::InstanceColumns { expected, supplied } => write!(
+                f,
+                "the circuit has {expected} instance columns, got {supplied} public vectors"
+            ),
+            CircuitError::InstanceTooShort {
+                column,
+                expected,
+                supplied,
+            } => write!(
+                f,
+                "instance column {column} binds {expected} rows, got only {supplied} values"
+            ),
+            CircuitError::InstanceTooLong {
+                column,
+                expected,
+                supplied,
+            } => write!(
+                f,
+                "instance column {column} binds {expected} rows, the other {} of {supplied} values are never checked",
+                supplied - expected
+            ),
+            CircuitError::WitnessNotChunked { chunk, supplied } => write!(
+                f,
+                "a witness of {supplied} values is not a non-empty multiple of {chunk} values"
+            ),
+            CircuitError::WitnessTooLong { expected, supplied } => write!(
+                f,
+                "the circuit takes at most {expected} witness values, got {supplied}"
+            ),
+            CircuitError::ParamsTooLarge { k, bytes, budget } => write!(
+                f,
+                "params for k = {k} take about {bytes} bytes, over the budget of {budget} bytes"
+            ),
+            CircuitError::Synthesis(e) => write!(f, "synthesis failed: {e}"),
+        }
+    }
+}
+
+impl From<Error> for CircuitError {
+    fn from(e: Error) -> Self {
+        CircuitError::Synthesis(e)
+    }
+}
+
+/// Check that `public` has one vector per instance column, each as long as
+/// the rows the circuit binds in that column, before running the prover.
+pub fn validate_public<F: Field, C: Circuit<F>>(
+    circuit: &C,
+    public: &[Vec<F>],
+) -> Result<(), CircuitError> {
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+
+    // the next instance column would take the index of their count
+    let expected = column_index::<F>(meta.clone().instance_column().into());
+    if public.len() != expected {
+        return Err(CircuitError::InstanceColumns {
+            expected,
+            supplied: public.len(),
+        });
+    }
+
+    let mut recorder = InstanceRecorder::new(public);
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, vec![])?;
+
+    // every instance row is bound through a copy
+    let mut bound = vec![0; expected];
+    for (column, row) in recorder.copies.iter().flat_map(|(l, r)| [l, r]) {
+        if *column.column_type() == Any::Instance {
+            let c = column_index::<F>(*column);
+            bound[c] = bound[c].max(row + 1);
+        }
+    }
+
+    for (column, (&expected, values)) in bound.iter().zip(public).enumerate() {
+        let supplied = values.len();
+        if supplied < expected {
+            return Err(CircuitError::InstanceTooShort {
+                column,
+                expected,
+                supplied,
+            });
+        }
+        if supplied > expected {
+            return Err(CircuitError::InstanceTooLong {
+                column,
+                expected,
+                supplied,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Recompute the instance values the verifier expects by synthesizing the
+/// circuit and following every copy between a witnessed cell and an instance
+/// cell, then report each supplied value that disagrees.
+///
+/// Instance cells that are only read by the circuit (e.g. via
+/// `assign_advice_from_instance`) always agree, since the witness is built
+/// from the supplied value.
+///
+/// Circuits relying on `enable_constant` are not supported.
+pub fn diagnose_instances<F: Field, C: Circuit<F>>(
+    circuit: &C,
+    public: &[Vec<F>],
+) -> Result<Vec<InstanceMismatch<F>>, Error> {
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+
+    let mut recorder = InstanceRecorder::new(public);
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, vec![])?;
+
+    let mut mismatches = vec![];
+    for (left, right) in recorder.copies.iter() {
+        // only copies linking a witnessed cell to an instance cell matter
+        let (instance, cell) = match (left.0.column_type(), right.0.column_type()) {
+            (Any::Instance, Any::Instance) => continue,
+            (Any::Instance, _) => (left, right),
+            (_, Any::Instance) => (right, left),
+            _ => continue,
+        };
+
+        let expected = match recorder.cells.get(cell) {
+            Some(v) => *v,
+            None => continue,
+        };
+        let (column, row) = (column_index::<F>(instance.0), instance.1);
+        let supplied = public.get(column).and_then(|c| c.get(row)).cloned();
+
+        if supplied != Some(expected) {
+            mismatches.push(InstanceMismatch {
+                column,
+                row,
+                expected,
+                supplied,
+            });
+        }
+    }
+
+    mismatches.sort_by_key(|m| (m.column, m.row));
+    mismatches.dedup();
+    Ok(mismatches)
+}
+
+/// Smallest `k` such that `circuit` fits in `2^k` rows with the given public
+/// inputs, counting the rows the floor planner uses and the blinding rows.
+pub fn min_k<F: Field, C: Circuit<F>>(circuit: &C, public: &[Vec<F>]) -> Result<u32, Error> {
+    let mut meta = ConstraintSystem::default();
+    C::configure(&mut meta);
+
+    // rows past the usable ones are reserved for blinding factors
+    let reserved = meta.blinding_factors() + 1;
+    let instance_rows = public.iter().map(|c| c.len()).max().unwrap_or(0);
+    let rows = used_rows(circuit, public)?.max(instance_rows) + reserved;
+    let rows = rows.max(meta.minimum_rows());
+
+    Ok(rows.next_power_of_two().trailing_zeros())
+}
+
+/// Number of rows the floor planner assigns or enables selectors on,
+/// without the blinding rows.
+pub fn used_rows<F: Field, C: Circuit<F>>(circuit: &C, public: &[Vec<F>]) -> Result<usize, Error> {
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+
+    let mut recorder = InstanceRecorder::new(public);
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, vec![])?;
+    Ok(recorder.rows)
+}
+
+/// Returns the smallest `k` for `circuit`. With the `trace` feature, a
+/// warning event is emitted when the supplied `k` is more than one above
+/// it, since every extra degree doubles the memory used. Without `trace`
+/// nothing is reported, callers compare `k` to the returned minimum.
+pub fn check_k<F: Field, C: Circuit<F>>(
+    circuit: &C,
+    public: &[Vec<F>],
+    k: u32,
+) -> Result<u32, Error> {
+    let min = min_k(circuit, public)?;
+    if k > min + 1 {
+        #[cfg(feature = "trace")]
+        tracing::warn!(k, min, "oversized k, the circuit fits in a smaller one");
+    }
+    Ok(min)
+}
+
+/// Bytes held by `Params::new(k)` on curve `C`: the `2^k` generators and
+/// as many lagrange basis points, on top of the struct itself. `None` if
+/// that does not fit in a `usize`.
+pub fn params_bytes<C: CurveAffine>(k: u32) -> Option<usize> {
+    let points = 1usize.checked_shl(k).and_then(|n| n.checked_mul(2))?;
+    points
+        .checked_mul(size_of::<C>())
+        .and_then(|bytes| bytes.checked_add(size_of::<Params<C>>()))
+}
+
+/// Estimate the memory `Params::new(k)` allocates before calling it, and
+/// return `ParamsTooLarge` instead of running out of memory if it exceeds
+/// `budget` bytes. An estimate past `usize::MAX` is reported as
+/// `usize::MAX` bytes.
+pub fn check_params_memory<C: CurveAffine>(k: u32, budget: usize) -> Result<usize, CircuitError> {
+    let bytes = params_bytes::<C>(k).unwrap_or(usize::MAX);
+    if bytes > budget {
+        return Err(CircuitError::ParamsTooLarge { k, bytes, budget });
+    }
+    Ok(bytes)
+}
+
+/// The advice and fixed values assigned by a circuit, indexed by
+/// `[column][row]` with columns in allocation order. Cells that are never
+/// assigned or hold an unknown value are `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignments<F: Field> {
+    pub advice: Vec<Vec<Option<F>>>,
+    pub fixed: Vec<Vec<Option<F>>>,
+}
+
+/// Synthesize `circuit` with the given public inputs and return every advice
+/// and fixed assignment, for diffing against an independent computation.
+/// The circuit is first run through `MockProver` so that it must fit in
+/// `2^k` rows. Columns after the last one with an assignment are left out.
+pub fn dump_assignments<F: Field + Ord, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    public: &[Vec<F>],
+) -> Result<Assignments<F>, Error> {
+    MockProver::run(k, circuit, public.to_vec())?;
+
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+
+    let mut recorder = InstanceRecorder::new(public);
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, vec![])?;
+
+    let mut table = Assignments {
+        advice: vec![],
+        fixed: vec![],
+    };
+    for (&(column, row), &value) in &recorder.cells {
+        let columns = match column.column_type() {
+            Any::Advice => &mut table.advice,
+            Any::Fixed => &mut table.fixed,
+            Any::Instance => continue,
+        };
+        let index = column_index::<F>(column);
+        if columns.len() <= index {
+            columns.resize(index + 1, vec![]);
+        }
+        columns[index].resize(recorder.rows, None);
+        columns[index][row] = Some(value);
+    }
+    for column in table.advice.iter_mut().chain(table.fixed.iter_mut()) {
+        column.resize(recorder.rows, None);
+    }
+    Ok(table)
+}
+
+/// How much of the fixed columns of a circuit repeats itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedUsage {
+    /// fixed cells holding a value
+    pub assigned: usize,
+    /// distinct values summed over the fixed columns, the cells left if every
+    /// column stored each of its values once
+    pub distinct: usize,
+}
+
+/// Count the fixed cells `circuit` assigns and how many of them are distinct
+/// within their column, see `dump_assignments`. Every poseidon permutation
+/// assigns the round constants of the spec again, so `assigned` grows with
+/// the permutations while `distinct` stays below `rounds * WIDTH` of one
+/// permutation, plus the constants of other gates.
+pub fn fixed_usage<F: Field + Ord, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    public: &[Vec<F>],
+) -> Result<FixedUsage, Error> {
+    let table = dump_assignments(k, circuit, public)?;
+    let mut usage = FixedUsage {
+        assigned: 0,
+        distinct: 0,
+    };
+    for column in &table.fixed {
+        let values = column.iter().flatten().collect::<BTreeSet<_>>();
+        usage.assigned += column.iter().flatten().count();
+        usage.distinct += values.len();
+    }
+    Ok(usage)
+}
+
+/// Position of a column among the columns of its type, in allocation order.
+///
+/// Column indices are not exposed by halo2, but columns compare equal by
+/// type and index, so allocate columns of the same type in a scratch
+/// constraint system until one matches.
+fn column_index<F: Field>(column: Column<Any>) -> usize {
+    let mut meta = ConstraintSystem::<F>::default();
+    let mut allocate = || -> Column<Any> {
+        match column.column_type() {
+            Any::Advice => meta.advice_column().into(),
+            Any::Fixed => meta.fixed_column().into(),
+            Any::Instance => meta.instance_column().into(),
+        }
+    };
+    (0..)
+        .find(|_| allocate() == column)
+        .expect("column is not allocated")
+}
+
+/// A cell addressed by its column and absolute row
+type Cell = (Column<Any>, usize);
+
+/// Records the known assignments, copies and used rows during synthesis
+struct InstanceRecorder<'a, F: Field> {
+    instances: &'a [Vec<F>],
+    cells: HashMap<Cell, F>,
+    copies: Vec<(Cell, Cell)>,
+    /// number of rows touched by any assignment or selector
+    rows: usize,
+}
+
+impl<'a, F: Field> InstanceRecorder<'a, F> {
+    fn new(instances: &'a [Vec<F>]) -> Self {
+        InstanceRecorder {
+            instances,
+            cells: HashMap::new(),
+            copies: vec![],
+            rows: 0,
+        }
+    }
+
+    fn record(&mut self, column: Column<Any>, row: usize, value: Value<F>) {
+        self.touch(row);
+        value.map(|v| self.cells.insert((column, row), v));
+    }
+
+    fn touch(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+    }
+}
+
+impl<'a, F: Field> Assignment<F> for InstanceRecorder<'a, F> {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+        // missing rows stay unknown so that they can be reported
+        Ok(self
+            .instances
+            .get(column_index::<F>(column.into()))
+            .and_then(|c| c.get(row))
+            .map_or(Value::unknown(), |v| Value::known(*v)))
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.record(column.into(), row, to().into_field().evaluate());
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.record(column.into(), row, to().into_field().evaluate());
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.copies
+            .push(((left_column, left_row), (right_column, right_row)));
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
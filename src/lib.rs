@@ -1,2 +1,15 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod chips;
+#[cfg(feature = "std")]
 pub mod circuits;
+#[cfg(feature = "std")]
+pub mod debug;
+pub mod native;
+#[cfg(feature = "std")]
+pub mod prover;
+#[cfg(feature = "std")]
+pub mod verifier;
@@ -0,0 +1,9 @@
+//! Native (off-circuit) counterparts of the chips.
+//!
+//! Everything in here only relies on `core` and `alloc`, so it stays
+//! available when the crate is built without the default `std` feature.
+pub mod encoding;
+pub mod merkle;
+pub mod poseidon;
+pub mod registry;
+pub mod spec;
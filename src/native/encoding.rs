@@ -0,0 +1,61 @@
+//! Byte encodings of field elements for interoperating with other tools.
+//!
+//! `PrimeField::to_repr` is little-endian for the fields used here (e.g.
+//! `Fp`), while many external tree libraries use big-endian.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+use ff::PrimeField;
+
+/// Little-endian bytes of `v`, as returned by `to_repr`
+pub fn field_to_le_bytes<F: PrimeField>(v: &F) -> Vec<u8> {
+    v.to_repr().as_ref().to_vec()
+}
+
+/// Big-endian bytes of `v`
+pub fn field_to_be_bytes<F: PrimeField>(v: &F) -> Vec<u8> {
+    let mut bytes = field_to_le_bytes(v);
+    bytes.reverse();
+    bytes
+}
+
+/// Reads little-endian bytes, `None` if their length differs from the
+/// field encoding or they are not a canonical element.
+pub fn field_from_le_bytes<F: PrimeField>(bytes: &[u8]) -> Option<F> {
+    let mut repr = F::Repr::default();
+    if repr.as_ref().len() != bytes.len() {
+        return None;
+    }
+    repr.as_mut().copy_from_slice(bytes);
+    F::from_repr(repr).into()
+}
+
+/// Reads big-endian bytes, see `field_from_le_bytes`
+pub fn field_from_be_bytes<F: PrimeField>(bytes: &[u8]) -> Option<F> {
+    let mut le = bytes.to_vec();
+    le.reverse();
+    field_from_le_bytes(&le)
+}
+
+/// `0x` followed by the big-endian bytes of `v` in lower case hex
+pub fn field_to_hex<F: PrimeField>(v: &F) -> String {
+    let mut hex = String::from("0x");
+    for byte in field_to_be_bytes(v) {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Reads the output of `field_to_hex`, with or without the `0x` prefix,
+/// `None` if it is not the hex of a canonical element.
+pub fn field_from_hex<F: PrimeField>(hex: &str) -> Option<F> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    field_from_be_bytes(&bytes)
+}
@@ -0,0 +1,295 @@
+//! Random merkle paths laid out as the witness of `MerklePathCircuit`, for
+//! tests and benchmarks, and a small tree to take real paths from.
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::fmt;
+
+use ff::PrimeField;
+use rand_core::RngCore;
+
+use super::poseidon::compress;
+use super::spec::Spec;
+
+#[cfg(feature = "std")]
+mod fixture;
+
+/// The left and right node of every layer of a path from a leaf, with the
+/// index bits choosing the node on the path.
+pub struct MerklePath<F: PrimeField> {
+    left: Vec<Vec<F>>,
+    right: Vec<Vec<F>>,
+    index: Vec<F>,
+}
+
+/// Generate a random merkle path in a tree of real depth `n`, padded to the
+/// max depth `m` of the circuit.
+///
+/// The path has `n + 1` layers of left and right nodes, from the leaves up to
+/// the root, which is written as both nodes of the top layer. The index has
+/// one bit per layer of the max depth, `m` in total, the bits past `n` being
+/// random. Panics unless `1 <= n <= m`.
+pub fn gen_merkle_path<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    rng: &mut impl RngCore,
+    n: usize,
+    m: usize,
+) -> MerklePath<F> {
+    let leaf = (0..S::element_size())
+        .map(|_| F::random(&mut *rng))
+        .collect();
+    gen_merkle_path_with_leaf::<F, S, W>(rng, leaf, n, m)
+}
+
+/// Same as `gen_merkle_path`, but the selected leaf is the given one
+pub fn gen_merkle_path_with_leaf<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    rng: &mut impl RngCore,
+    leaf: Vec<F>,
+    n: usize,
+    m: usize,
+) -> MerklePath<F> {
+    assert!(m >= 1, "the max depth m must be at least 1, got m = {m}");
+    assert!(n >= 1, "the real depth n must be at least 1, got n = {n}");
+    assert!(
+        n <= m,
+        "the real depth n = {n} is larger than the max depth m = {m}"
+    );
+
+    let element_size = S::element_size();
+    assert_eq!(
+        leaf.len(),
+        element_size,
+        "a leaf is one element of {element_size} values"
+    );
+    let mut inputs: Vec<Vec<F>> = (0..n + 1)
+        .map(|_| (0..element_size).map(|_| F::random(&mut *rng)).collect())
+        .collect();
+
+    let mut bit = || rng.next_u32() & 1 == 1;
+    let mut index = vec![];
+
+    match bit() {
+        true => {
+            index.push(F::ONE);
+            inputs[1] = leaf;
+        }
+        false => {
+            index.push(F::ZERO);
+            inputs[0] = leaf;
+        }
+    };
+
+    let mut left = vec![inputs[0].clone()];
+    let mut right = vec![inputs[1].clone()];
+
+    // put element size
+    for i in 1..=m {
+        let bit = bit();
+        // add path
+        if i < m {
+            index.push(match bit {
+                true => F::ONE,
+                false => F::ZERO,
+            });
+        }
+
+        if i <= n {
+            let hash = compress::<F, S, W>(&left[i - 1], &right[i - 1]);
+            let element = match i < n {
+                true => inputs[i + 1].clone(),
+
+                // last line is duplicated
+                false => hash.clone(),
+            };
+
+            match bit {
+                true => {
+                    right.push(hash);
+                    left.push(element);
+                }
+                false => {
+                    left.push(hash);
+                    right.push(element);
+                }
+            };
+        }
+    }
+
+    assert!(left.iter().all(|v| v.len() == element_size));
+    assert!(right.iter().all(|v| v.len() == element_size));
+
+    MerklePath { left, right, index }
+}
+
+impl<F: PrimeField> MerklePath<F> {
+    pub fn get_leaf(&self) -> Vec<F> {
+        let inital_bit = *self.index.first().expect("leaf index is missed");
+        if inital_bit == F::ONE {
+            self.right.first().expect("missing right leaf ").clone()
+        } else if inital_bit == F::ZERO {
+            self.left.first().expect("missing left leaf ").clone()
+        } else {
+            panic!("leaf index is not binary");
+        }
+    }
+
+    pub fn get_root(&self) -> Vec<F> {
+        self.right.last().expect("missing right leaf ").clone()
+    }
+
+    /// nodes next to the path from the leaf up to the root
+    pub fn get_siblings(&self) -> Vec<Vec<F>> {
+        let n = self.left.len() - 1;
+        (0..n)
+            .map(|i| match self.index[i] == F::ONE {
+                true => self.left[i].clone(),
+                false => self.right[i].clone(),
+            })
+            .collect()
+    }
+
+    /// the node on the path at `depth`, the leaf at depth 0 and the root
+    /// past the real depth
+    pub fn get_node(&self, depth: usize) -> Vec<F> {
+        let n = self.left.len() - 1;
+        if depth >= n {
+            return self.get_root();
+        }
+        match self.index[depth] == F::ONE {
+            true => self.right[depth].clone(),
+            false => self.left[depth].clone(),
+        }
+    }
+
+    pub fn get_index(&self) -> Vec<F> {
+        self.index.clone()
+    }
+
+    /// left nodes from the leaves up to the root
+    pub fn get_left(&self) -> &[Vec<F>] {
+        &self.left
+    }
+
+    /// right nodes from the leaves up to the root
+    pub fn get_right(&self) -> &[Vec<F>] {
+        &self.right
+    }
+
+    /// left then right node of every layer, flattened
+    pub fn get_layers(&self) -> Vec<F> {
+        self.left
+            .iter()
+            .zip(self.right.iter())
+            .flat_map(|(l, r)| l.iter().chain(r.iter()).copied())
+            .collect()
+    }
+
+    /// `m + 1` flags, zero for the hashed layers and one for the copied ones
+    pub fn get_copy_flags(&self, m: usize) -> Vec<F> {
+        let n = self.left.len() - 1;
+        (0..=m)
+            .map(|i| match i < n {
+                true => F::ZERO,
+                false => F::ONE,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// the leaf at `second` repeats the one at `first`
+    DuplicateLeaf { first: usize, second: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DuplicateLeaf { first, second } => {
+                write!(f, "the leaf at {second} repeats the leaf at {first}")
+            }
+        }
+    }
+}
+
+/// A full binary tree over leaves of `S::element_size()` values, padded with
+/// zero leaves up to a power of two, and every layer of inner nodes up to
+/// the root hashed as the merkle circuits do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree<F: PrimeField> {
+    /// the padded leaves first and the root alone in the last layer
+    layers: Vec<Vec<Vec<F>>>,
+}
+
+impl<F: PrimeField> MerkleTree<F> {
+    /// Build the tree of depth `ceil(log2(leaves.len()))`, at least one.
+    /// Panics if there is no leaf or a leaf is not one element.
+    pub fn new<S: Spec<F, W>, const W: usize>(leaves: Vec<Vec<F>>) -> Self {
+        let size = S::element_size();
+        assert!(!leaves.is_empty(), "a tree has at least one leaf");
+        assert!(
+            leaves.iter().all(|l| l.len() == size),
+            "a leaf is one element of {size} values"
+        );
+
+        let width = leaves.len().next_power_of_two().max(2);
+        let mut layer = leaves;
+        layer.resize(width, vec![F::ZERO; size]);
+
+        let mut layers = vec![layer];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| compress::<F, S, W>(&pair[0], &pair[1]))
+                .collect();
+            layers.push(next);
+        }
+        MerkleTree { layers }
+    }
+
+    /// Same as `new`, but fails on the first leaf repeating an earlier one.
+    /// The zero leaves padding the tree are not compared.
+    pub fn new_unique<S: Spec<F, W>, const W: usize>(leaves: Vec<Vec<F>>) -> Result<Self, Error> {
+        let mut seen = BTreeMap::new();
+        for (second, leaf) in leaves.iter().enumerate() {
+            let bytes: Vec<u8> = leaf
+                .iter()
+                .flat_map(|x| x.to_repr().as_ref().to_vec())
+                .collect();
+            if let Some(&first) = seen.get(&bytes) {
+                return Err(Error::DuplicateLeaf { first, second });
+            }
+            seen.insert(bytes, second);
+        }
+        Ok(Self::new::<S, W>(leaves))
+    }
+
+    /// Number of hashed layers of every path
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn root(&self) -> Vec<F> {
+        self.layers[self.depth()][0].clone()
+    }
+
+    /// The leaf at `index`, a zero leaf in the padding
+    pub fn leaf(&self, index: usize) -> Vec<F> {
+        self.layers[0][index].clone()
+    }
+
+    /// Nodes next to the path from the leaf at `index` up to the root
+    pub fn siblings(&self, index: usize) -> Vec<Vec<F>> {
+        assert!(index < self.layers[0].len(), "no leaf at {index}");
+        self.layers[..self.depth()]
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| layer[(index >> i) ^ 1].clone())
+            .collect()
+    }
+
+    /// Position of the node on the path at each layer, `true` for right
+    pub fn index_bits(&self, index: usize) -> Vec<bool> {
+        (0..self.depth()).map(|i| (index >> i) & 1 == 1).collect()
+    }
+}
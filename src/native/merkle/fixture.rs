@@ -0,0 +1,204 @@
+//! A JSON fixture format for merkle paths, so that a generated path can be
+//! stored as a golden test vector or handed to another tool.
+//!
+//! Every field element is written with `field_to_hex`:
+//!
+//! ```json
+//! {
+//!   "leaf": ["0x..", ..],
+//!   "root": ["0x..", ..],
+//!   "index": ["0x..", ..],
+//!   "left": [["0x..", ..], ..],
+//!   "right": [["0x..", ..], ..]
+//! }
+//! ```
+//!
+//! The leaf and the root are written for readers of the file only, the
+//! loader checks they agree with the layers.
+
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use ff::PrimeField;
+
+use super::MerklePath;
+use crate::native::encoding::{field_from_hex, field_to_hex};
+
+/// The value types of the fixture, strings of hex and nested lists of them
+enum Json {
+    Str(String),
+    List(Vec<Json>),
+}
+
+fn invalid(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn write_list<F: PrimeField>(values: &[F]) -> String {
+    let values = values
+        .iter()
+        .map(|v| format!("\"{}\"", field_to_hex(v)))
+        .collect::<Vec<_>>();
+    format!("[{}]", values.join(", "))
+}
+
+fn write_layers<F: PrimeField>(layers: &[Vec<F>]) -> String {
+    let layers = layers
+        .iter()
+        .map(|l| format!("\n    {}", write_list(l)))
+        .collect::<Vec<_>>();
+    format!("[{}\n  ]", layers.join(","))
+}
+
+/// A parser for the subset of JSON written by `to_fixture`: one object whose
+/// values are lists of strings or lists of such lists.
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn skip(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn eat(&mut self, c: char) -> Result<()> {
+        self.skip();
+        self.rest = self
+            .rest
+            .strip_prefix(c)
+            .ok_or_else(|| invalid(format!("expected '{c}' in the fixture")))?;
+        Ok(())
+    }
+
+    fn peek(&mut self, c: char) -> bool {
+        self.skip();
+        self.rest.starts_with(c)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.eat('"')?;
+        let end = self
+            .rest
+            .find('"')
+            .ok_or_else(|| invalid("unterminated string in the fixture".into()))?;
+        let s = self.rest[..end].to_string();
+        self.rest = &self.rest[end + 1..];
+        Ok(s)
+    }
+
+    fn value(&mut self) -> Result<Json> {
+        if !self.peek('[') {
+            return Ok(Json::Str(self.string()?));
+        }
+        self.eat('[')?;
+        let mut items = vec![];
+        if !self.peek(']') {
+            loop {
+                items.push(self.value()?);
+                if !self.peek(',') {
+                    break;
+                }
+                self.eat(',')?;
+            }
+        }
+        self.eat(']')?;
+        Ok(Json::List(items))
+    }
+
+    fn object(&mut self) -> Result<Vec<(String, Json)>> {
+        self.eat('{')?;
+        let mut fields = vec![];
+        if !self.peek('}') {
+            loop {
+                let key = self.string()?;
+                self.eat(':')?;
+                fields.push((key, self.value()?));
+                if !self.peek(',') {
+                    break;
+                }
+                self.eat(',')?;
+            }
+        }
+        self.eat('}')?;
+        self.skip();
+        if !self.rest.is_empty() {
+            return Err(invalid("trailing data after the fixture".into()));
+        }
+        Ok(fields)
+    }
+}
+
+fn read_list<F: PrimeField>(value: &Json, key: &str) -> Result<Vec<F>> {
+    let Json::List(items) = value else {
+        return Err(invalid(format!("\"{key}\" is not a list")));
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            Json::Str(hex) => field_from_hex(hex)
+                .ok_or_else(|| invalid(format!("\"{key}\" holds {hex}, not a field element"))),
+            Json::List(_) => Err(invalid(format!("\"{key}\" is not a list of elements"))),
+        })
+        .collect()
+}
+
+fn read_layers<F: PrimeField>(value: &Json, key: &str) -> Result<Vec<Vec<F>>> {
+    let Json::List(layers) = value else {
+        return Err(invalid(format!("\"{key}\" is not a list")));
+    };
+    layers.iter().map(|l| read_list(l, key)).collect()
+}
+
+impl<F: PrimeField> MerklePath<F> {
+    /// Write the path to `path` in the fixture format of this module
+    pub fn to_fixture(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = format!(
+            "{{\n  \"leaf\": {},\n  \"root\": {},\n  \"index\": {},\n  \"left\": {},\n  \"right\": {}\n}}\n",
+            write_list(&self.get_leaf()),
+            write_list(&self.get_root()),
+            write_list(&self.index),
+            write_layers(&self.left),
+            write_layers(&self.right),
+        );
+        fs::write(path, json)
+    }
+
+    /// Load a path written by `to_fixture`. Fails with `InvalidData` on a
+    /// malformed file, or when its leaf and root disagree with its layers.
+    pub fn from_fixture(path: impl AsRef<Path>) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let fields = Parser { rest: &json }.object()?;
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v)
+                .ok_or_else(|| invalid(format!("missing \"{key}\" in the fixture")))
+        };
+
+        let left = read_layers(field("left")?, "left")?;
+        let right = read_layers(field("right")?, "right")?;
+        let index = read_list(field("index")?, "index")?;
+        if left.is_empty() || left.len() != right.len() || left.len() > index.len() + 1 {
+            return Err(invalid(format!(
+                "{} left and {} right layers do not fit {} index bits",
+                left.len(),
+                right.len(),
+                index.len()
+            )));
+        }
+        if index.iter().any(|b| *b != F::ZERO && *b != F::ONE) {
+            return Err(invalid("the index is not binary".into()));
+        }
+
+        let merkle_path = MerklePath { left, right, index };
+        if merkle_path.get_leaf() != read_list::<F>(field("leaf")?, "leaf")? {
+            return Err(invalid("the leaf differs from the layers".into()));
+        }
+        if merkle_path.get_root() != read_list::<F>(field("root")?, "root")? {
+            return Err(invalid("the root differs from the layers".into()));
+        }
+        Ok(merkle_path)
+    }
+}
@@ -0,0 +1,227 @@
+use alloc::vec::Vec;
+
+use ff::PrimeField;
+
+use super::spec::Spec;
+
+/// A mirrored implementation for poseidon hash
+pub fn hash<F: PrimeField, S: Spec<F, W>, const W: usize>(inputs: Vec<F>) -> Vec<F> {
+    hash_squeeze::<F, S, W>(inputs, 1)
+}
+
+/// Same as `hash`, squeezing `outputs` elements of `S::element_size()`
+/// values: the first one is the digest, every next one is read after one
+/// more permutation of the state.
+pub fn hash_squeeze<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    inputs: Vec<F>,
+    outputs: usize,
+) -> Vec<F> {
+    S::check_rate();
+    assert!(outputs > 0, "squeeze at least one element");
+
+    // initate states [0,0,...., capacity]
+    let mut states = [F::ZERO; W];
+    states[W - 1] = F::from_u128(S::capacity());
+    let size = S::element_size();
+
+    let elements = inputs
+        .chunks(size)
+        .map(|c| c.iter().copied().chain(S::pad()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    // absorb add inputs to state and then do permutation
+    for x in elements {
+        for i in 0..W - 1 {
+            states[i] += x[i];
+        }
+        states = permutation::<F, S, W>(states);
+    }
+
+    //squezze
+    let mut squeezed = states[0..size].to_vec();
+    for _ in 1..outputs {
+        states = permutation::<F, S, W>(states);
+        squeezed.extend_from_slice(&states[0..size]);
+    }
+    squeezed
+}
+
+/// Hash two nodes of one element each into their parent, absorbing the
+/// padded left node and then the padded right node as the merkle circuit
+/// does.
+pub fn compress<F: PrimeField, S: Spec<F, W>, const W: usize>(left: &[F], right: &[F]) -> Vec<F> {
+    let size = S::element_size();
+    assert_eq!(left.len(), size, "the left node is one element");
+    assert_eq!(right.len(), size, "the right node is one element");
+
+    let mut states = [F::ZERO; W];
+    states[W - 1] = F::from_u128(S::capacity());
+    for node in [left, right] {
+        for (state, x) in states.iter_mut().zip(node.iter().copied().chain(S::pad())) {
+            *state += x;
+        }
+        states = permutation::<F, S, W>(states);
+    }
+    states[0..size].to_vec()
+}
+
+/// The element absorbed before the inputs by `hash_with_length`: the
+/// number of inputs followed by zeros up to `S::element_size()`.
+pub fn length_prefix<F: PrimeField, S: Spec<F, W>, const W: usize>(len: usize) -> Vec<F> {
+    let mut prefix = alloc::vec![F::ZERO; S::element_size()];
+    prefix[0] = F::from(len as u64);
+    prefix
+}
+
+/// Same as `hash`, with the number of inputs absorbed first so that inputs
+/// of different lengths never share a digest.
+pub fn hash_with_length<F: PrimeField, S: Spec<F, W>, const W: usize>(inputs: Vec<F>) -> Vec<F> {
+    let mut prefixed = length_prefix::<F, S, W>(inputs.len());
+    prefixed.extend(inputs);
+    hash::<F, S, W>(prefixed)
+}
+
+/// Domain tag of a struct with the given field names, in order: each name
+/// is encoded as its length in 4 little-endian bytes followed by its bytes,
+/// the encodings are packed 15 bytes per value and zero padded to whole
+/// elements, then hashed by `hash_with_length`.
+pub fn struct_tag<F: PrimeField, S: Spec<F, W>, const W: usize>(names: &[&str]) -> Vec<F> {
+    let bytes = names
+        .iter()
+        .flat_map(|name| {
+            (name.len() as u32)
+                .to_le_bytes()
+                .into_iter()
+                .chain(name.bytes())
+        })
+        .collect::<Vec<_>>();
+    let mut packed = bytes
+        .chunks(15)
+        .map(|chunk| {
+            let mut le = [0u8; 16];
+            le[..chunk.len()].copy_from_slice(chunk);
+            F::from_u128(u128::from_le_bytes(le))
+        })
+        .collect::<Vec<_>>();
+    pad_to_element::<F, S, W>(&mut packed);
+    hash_with_length::<F, S, W>(packed)
+}
+
+/// Hash the values of a struct given as `(field_name, value)` pairs in their
+/// canonical order. The tag of the names is absorbed first, so structs with
+/// other names or another order of the fields never share a digest, then
+/// the values zero padded to whole elements.
+pub fn hash_struct<F: PrimeField, S: Spec<F, W>, const W: usize>(fields: &[(&str, F)]) -> Vec<F> {
+    let names = fields.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    let mut inputs = struct_tag::<F, S, W>(&names);
+    inputs.extend(fields.iter().map(|(_, v)| *v));
+    pad_to_element::<F, S, W>(&mut inputs);
+    hash::<F, S, W>(inputs)
+}
+
+fn pad_to_element<F: PrimeField, S: Spec<F, W>, const W: usize>(values: &mut Vec<F>) {
+    let size = S::element_size();
+    values.resize(values.len().div_ceil(size) * size, F::ZERO);
+}
+
+/// Compute the root from a leaf, its siblings from bottom to top and the
+/// position of the node at each layer (`true` for right).
+pub fn merkle_root<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    leaf: Vec<F>,
+    siblings: &[Vec<F>],
+    index: &[bool],
+) -> Vec<F> {
+    assert_eq!(siblings.len(), index.len());
+    siblings
+        .iter()
+        .zip(index)
+        .fold(leaf, |node, (sibling, &is_right)| {
+            let (left, right) = match is_right {
+                true => (sibling.clone(), node),
+                false => (node, sibling.clone()),
+            };
+            compress::<F, S, W>(&left, &right)
+        })
+}
+
+/// Apply the full poseidon permutation over the states
+pub fn permutation<F: PrimeField, S: Spec<F, W>, const W: usize>(input: [F; W]) -> [F; W] {
+    let fr = S::full_rounds();
+    let pr = S::partial_rounds();
+    let all_rounds = fr + pr;
+    let half_rounds = fr / 2;
+    let mid = half_rounds + pr;
+    let mut result = input;
+    for i in 0..half_rounds {
+        result = full_round::<F, S, W>(result, i);
+    }
+    for i in half_rounds..mid {
+        result = partial_round::<F, S, W>(result, i);
+    }
+
+    for i in mid..all_rounds {
+        result = full_round::<F, S, W>(result, i);
+    }
+
+    result
+}
+
+/// Apply round `round` of the permutation, full or partial by its position
+pub fn round<F: PrimeField, S: Spec<F, W>, const W: usize>(input: [F; W], round: usize) -> [F; W] {
+    let half_rounds = S::full_rounds() / 2;
+    if round < half_rounds || round >= half_rounds + S::partial_rounds() {
+        full_round::<F, S, W>(input, round)
+    } else {
+        partial_round::<F, S, W>(input, round)
+    }
+}
+
+/// Add the round constants, apply the sbox to every state and mix
+pub fn full_round<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    input: [F; W],
+    round: usize,
+) -> [F; W] {
+    let ark = S::arks()[round];
+    let mds = S::mds();
+    // add round constant and apply full box
+    let mid: Vec<F> = (0..W)
+        .map(|i| {
+            let x = input[i] + ark[i];
+            x.cube() * x.square()
+        })
+        .collect();
+
+    (0..W)
+        .map(|i| {
+            (0..W)
+                .map(|j| mid[j] * mds[i][j])
+                .reduce(|acc, x| acc + x)
+                .unwrap()
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap()
+}
+
+/// Add the round constants, apply the sbox to the first state and mix
+pub fn partial_round<F: PrimeField, S: Spec<F, W>, const W: usize>(
+    input: [F; W],
+    round: usize,
+) -> [F; W] {
+    let ark = S::arks()[round];
+    let mds = S::mds();
+    // add round constant and apply full box
+    let mut mid: Vec<F> = (0..W).map(|i| input[i] + ark[i]).collect();
+    mid[0] = mid[0].cube() * mid[0].square();
+
+    (0..W)
+        .map(|i| {
+            (0..W)
+                .map(|j| mid[j] * mds[i][j])
+                .reduce(|acc, x| acc + x)
+                .unwrap()
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap()
+}
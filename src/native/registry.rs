@@ -0,0 +1,79 @@
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+use core::{fmt, marker::PhantomData};
+
+use ff::PrimeField;
+
+use super::{poseidon::hash, spec::Spec};
+
+/// Object safe view of the native poseidon hash, so that tooling can pick
+/// a spec at runtime.
+pub trait PoseidonHasher<F: PrimeField> {
+    fn hash(&self, inputs: Vec<F>) -> Vec<F>;
+}
+
+/// Native hasher for a fixed spec `S` of width `W`
+pub struct SpecHasher<S, const W: usize>(PhantomData<S>);
+
+impl<S, const W: usize> Default for SpecHasher<S, W> {
+    fn default() -> Self {
+        SpecHasher(PhantomData)
+    }
+}
+
+impl<F: PrimeField, S: Spec<F, W>, const W: usize> PoseidonHasher<F> for SpecHasher<S, W> {
+    fn hash(&self, inputs: Vec<F>) -> Vec<F> {
+        hash::<F, S, W>(inputs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// no spec was registered under this name
+    UnknownSpec(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownSpec(name) => write!(f, "unknown poseidon spec \"{name}\""),
+        }
+    }
+}
+
+/// Specs known to the tooling, keyed by an identifier such as "p128pow5t3"
+pub struct Registry<F: PrimeField> {
+    hashers: BTreeMap<String, Box<dyn PoseidonHasher<F>>>,
+}
+
+impl<F: PrimeField> Default for Registry<F> {
+    fn default() -> Self {
+        Registry {
+            hashers: BTreeMap::new(),
+        }
+    }
+}
+
+impl<F: PrimeField> Registry<F> {
+    /// Register spec `S` under `name`, replacing any previous entry
+    pub fn register<S: Spec<F, W> + 'static, const W: usize>(&mut self, name: &str) {
+        self.hashers
+            .insert(name.into(), Box::new(SpecHasher::<S, W>::default()));
+    }
+
+    /// Look up the hasher registered under `name`
+    pub fn get(&self, name: &str) -> Result<&dyn PoseidonHasher<F>, Error> {
+        self.hashers
+            .get(name)
+            .map(|h| h.as_ref())
+            .ok_or_else(|| Error::UnknownSpec(name.into()))
+    }
+
+    pub fn hash_by_name(&self, name: &str, inputs: Vec<F>) -> Result<Vec<F>, Error> {
+        Ok(self.get(name)?.hash(inputs))
+    }
+
+    /// Registered names in sorted order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.hashers.keys().map(|k| k.as_str())
+    }
+}
@@ -0,0 +1,54 @@
+use alloc::vec::Vec;
+use core::fmt;
+use ff::PrimeField;
+
+/// The type used to hold the MDS matrix and its inverse.
+pub type Mds<F, const WIDTH: usize> = [[F; WIDTH]; WIDTH];
+
+/// A specification for a Poseidon permutation.
+/// The input should be a field F
+/// the sponge width is WIDTH
+/// Number of full round and partial rounds are fixed
+///
+/// Inputs are absorbed one element of `element_size()` values at a time,
+/// followed by `pad()`, so the two must fill the rate `WIDTH - 1` exactly.
+/// Merkle circuits additionally use one element per node (`I`).
+pub trait Spec<F: PrimeField, const WIDTH: usize>: fmt::Debug + Clone + Default {
+    /// The number of full rounds for this specification.
+    ///
+    /// This must be an even number.
+    fn full_rounds() -> usize;
+
+    /// The number of partial rounds for this specification.
+    fn partial_rounds() -> usize;
+
+    /// Generates `mds` corresponding to this specification.
+    fn mds() -> Mds<F, WIDTH>;
+
+    /// Generates `ARKS` corresponding to this specification.
+    fn arks() -> Vec<[F; WIDTH]>;
+
+    // Generate the capacity
+    fn capacity() -> u128;
+
+    // Return the Pad Element;
+    fn pad() -> Vec<F>;
+
+    // element size
+    fn element_size() -> usize;
+
+    /// Panics unless a padded element fills the rate, see the trait docs.
+    fn check_rate() {
+        assert!(
+            WIDTH >= 2,
+            "a poseidon state of width {WIDTH} has no rate, the width must be at least 2"
+        );
+        let (size, pad) = (Self::element_size(), Self::pad().len());
+        assert_eq!(
+            size + pad,
+            WIDTH - 1,
+            "an element of {size} inputs padded with {pad} values must fill the rate {}",
+            WIDTH - 1
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Creating proofs of the circuits outside of tests, for services that prove
+//! many statements of the same circuit.
+
+use ff::FromUniformBytes;
+use halo2_proofs::{
+    arithmetic::CurveAffine,
+    plonk::{create_proof, Circuit, ProvingKey},
+    poly::commitment::Params,
+    transcript::{Blake2bWrite, Challenge255},
+};
+use rand_core::RngCore;
+
+use crate::debug::{validate_public, CircuitError};
+
+/// Prove `circuit` for `public`, one vector per instance column, blinding
+/// with `rng`, and return the proof bytes. `public` is checked against the
+/// instance layout of the circuit first, see `validate_public`.
+pub fn prove<C: CurveAffine, Circ: Circuit<C::Scalar>>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    circuit: Circ,
+    public: &[Vec<C::Scalar>],
+    rng: impl RngCore,
+) -> Result<Vec<u8>, CircuitError>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    validate_public(&circuit, public)?;
+    let public = public.iter().map(|c| &c[..]).collect::<Vec<_>>();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(params, pk, &[circuit], &[&public], rng, &mut transcript)?;
+    Ok(transcript.finalize())
+}
+
+/// Prove every `(circuit, public)` pair on the rayon thread pool, sharing the
+/// params and proving key, and return the proofs in the order of `statements`.
+///
+/// Proof `i` is blinded with `rng(i)`, so seeded rngs give the same bytes as
+/// proving the statements one by one with `prove`.
+#[cfg(feature = "parallel")]
+pub fn prove_parallel<C, Circ, R, G>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    statements: Vec<(Circ, Vec<Vec<C::Scalar>>)>,
+    rng: G,
+) -> Result<Vec<Vec<u8>>, CircuitError>
+where
+    C: CurveAffine,
+    C::Scalar: FromUniformBytes<64>,
+    Circ: Circuit<C::Scalar> + Send,
+    R: RngCore,
+    G: Fn(usize) -> R + Sync,
+{
+    use rayon::prelude::*;
+
+    statements
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, (circuit, public))| prove(params, pk, circuit, &public, rng(i)))
+        .collect()
+}
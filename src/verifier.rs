@@ -0,0 +1,49 @@
+//! Verifying proofs of the circuits outside of tests, for services that check
+//! many proofs of the same circuit.
+
+use ff::FromUniformBytes;
+use halo2_proofs::{
+    arithmetic::CurveAffine,
+    plonk::{verify_proof, BatchVerifier, SingleVerifier, VerifyingKey},
+    poly::commitment::Params,
+    transcript::{Blake2bRead, Challenge255},
+};
+
+/// A proof with its public inputs, one vector per instance column
+pub type Statement<F> = (Vec<u8>, Vec<Vec<F>>);
+
+/// Verify a batch of `(proof, public)` pairs created with the same params and
+/// verifying key.
+///
+/// The proofs are checked together, which is cheaper than one at a time. If
+/// the batch fails, each proof is verified on its own and the indices of the
+/// rejected ones are returned.
+pub fn verify_batch<C: CurveAffine>(
+    params: &Params<C>,
+    vk: &VerifyingKey<C>,
+    proofs: &[Statement<C::Scalar>],
+) -> Result<(), Vec<usize>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    let mut batch = BatchVerifier::new();
+    for (proof, public) in proofs {
+        batch.add_proof(vec![public.clone()], proof.clone());
+    }
+    if batch.finalize(params, vk) {
+        return Ok(());
+    }
+
+    let rejected = proofs
+        .iter()
+        .enumerate()
+        .filter(|(_, (proof, public))| {
+            let public = public.iter().map(|c| &c[..]).collect::<Vec<_>>();
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            let strategy = SingleVerifier::new(params);
+            verify_proof(params, vk, strategy, &[&public], &mut transcript).is_err()
+        })
+        .map(|(i, _)| i)
+        .collect();
+    Err(rejected)
+}
@@ -1,16 +1,26 @@
 mod utils;
 use crate::utils::p128_pow5_t3::P128Pow5T3;
+use circuit_samples::chips::arth_chips::{ArthChip, Number, NumericInstructions};
+use circuit_samples::chips::poseidon_chip::{
+    PoseidonArthConfig, PoseidonChip, PoseidonInstructions,
+};
 use circuit_samples::circuits::merkle_circuit::MerklePathCircuit;
 use circuit_samples::circuits::poseidon_circuit::utils::Spec;
 use circuit_samples::circuits::*;
 use ff::PrimeField;
-use halo2_proofs::circuit::Value;
+use halo2_proofs::circuit::{Layouter, Value};
 use halo2_proofs::dev::MockProver;
-use halo2_proofs::pasta::{EqAffine, Fp};
-use halo2_proofs::plonk::{keygen_pk, keygen_vk};
-use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::pasta::Fp;
+use halo2_proofs::plonk::Error;
 use rstest::rstest;
+use utils::gadget::{Gadget, GadgetCircuit};
 use utils::poseidon_hash::gen_merkle_path;
+use utils::prover::prove_and_verify;
+
+/// smallest degree holding `rows` and the blinding rows
+fn degree_for(rows: usize) -> u32 {
+    ((rows + 10) as f64).log2().ceil() as u32
+}
 
 #[cfg(test)]
 #[rstest]
@@ -36,6 +46,69 @@ fn fuction_demo1(#[case] x: u64, #[case] y: u64, #[case] z: u64) {
     }
 }
 
+#[cfg(test)]
+#[rstest]
+#[case(7)]
+#[case(0)]
+// demo1 accepts z = (x^3+x)y for every small x and rejects any other witness
+// for the same public y and z
+fn function_demo1_witness(#[case] y: u64) {
+    for x in 0..6u64 {
+        let z = Fp::from((x.pow(3) + x) * y);
+        let public = vec![Fp::from(y), z];
+        let circuit = arth_circuit::DemoCircuit1::new(Fp::from(x));
+        let prover = MockProver::run(4, &circuit, vec![public.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // x^3+x is injective on the integers, only y = 0 hides the witness
+        let wrong = arth_circuit::DemoCircuit1::new(Fp::from(x + 1));
+        let prover = MockProver::run(4, &wrong, vec![public]).unwrap();
+        assert_eq!(prover.verify().is_ok(), y == 0);
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![3, 5, 7], 22)]
+#[case(vec![3, 5, 7], 21)]
+#[case(vec![3, 5], 15)]
+#[case(vec![2, 4, 1, 1, 6], 16)]
+#[case(vec![2, 4, 1, 1, 6], 14)]
+// demo2 proves x0 * x1 + x2 + ... = y for case(xs, y)
+fn function_demo2(#[case] xs: Vec<u64>, #[case] y: u64) {
+    let circuit = arth_circuit::DemoCircuit2::new(xs.iter().map(|&x| Fp::from(x)).collect());
+    let prover = MockProver::run(5, &circuit, vec![vec![Fp::from(y)]]).unwrap();
+
+    if xs[0] * xs[1] + xs[2..].iter().sum::<u64>() == y {
+        assert_eq!(prover.verify(), Ok(()));
+    } else {
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![(3, 2), (2, 1), (1, 0)], 4, 57)]
+#[case(vec![(3, 2), (2, 1), (1, 0)], 4, 58)]
+#[case(vec![(1, 4), (2, 3), (7, 0), (5, 0)], 3, 147)]
+#[case(vec![(1, 4), (2, 3), (7, 0), (5, 0)], 2, 147)]
+#[case(vec![(9, 0)], 5, 9)]
+// the poly circuit proves y = sum c * x^e for case(terms, x, y)
+fn function_poly(#[case] terms: Vec<(u64, u64)>, #[case] x: u64, #[case] y: u64) {
+    let circuit = arth_circuit::PolyCircuit::new(
+        terms.iter().map(|&(c, e)| (Fp::from(c), e)).collect(),
+        Fp::from(x),
+    );
+    let prover = MockProver::run(6, &circuit, vec![vec![Fp::from(y)]]).unwrap();
+
+    let expected: u64 = terms.iter().map(|&(c, e)| c * x.pow(e as u32)).sum();
+    if expected == y {
+        assert_eq!(prover.verify(), Ok(()));
+    } else {
+        assert!(prover.verify().is_err());
+    }
+}
+
 #[cfg(test)]
 #[rstest]
 #[case(2)]
@@ -51,14 +124,10 @@ fn function_poseidon(#[case] n: usize) {
 
     use ff::Field;
 
-    let row_n = (<P128Pow5T3 as Spec<Fp, 3>>::full_rounds()
-        + <P128Pow5T3 as Spec<Fp, 3>>::partial_rounds())
-        * (<P128Pow5T3 as Spec<Fp, 3>>::element_size() + n)
-        + 3 * n;
-    let degree = (row_n as f32).log2().ceil() as u32;
+    let degree = degree_for(poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(n));
     let mut rng = rand::thread_rng();
     let inputs: Vec<Fp> = (0..n).map(|_| <Fp as Field>::random(&mut rng)).collect();
-    let mut outputs = utils::poseidon_hash::hash::<Fp, P128Pow5T3, 3>(inputs.clone()).unwrap();
+    let mut outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.clone());
 
     let circuit = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs);
 
@@ -67,7 +136,7 @@ fn function_poseidon(#[case] n: usize) {
     prover.assert_satisfied();
     assert_eq!(prover.verify(), Ok(()));
 
-    outputs[0] = outputs[0] + Fp::from_u128(1);
+    outputs[0] += Fp::from_u128(1);
     let f_prover = MockProver::run(degree, &circuit, vec![outputs.clone()]).unwrap();
 
     assert!(f_prover.verify().is_err());
@@ -75,100 +144,4362 @@ fn function_poseidon(#[case] n: usize) {
 
 #[cfg(test)]
 #[rstest]
-#[case(16, 32)]
-#[case(32, 32)]
-fn function_merkle_32(#[case] n: usize, #[case] m: usize) {
-    use circuit_samples::circuits::merkle_circuit::MerklePathCircuit;
+#[case(2)]
+#[case(6)]
+// a real proof for the public input laid out by expected_public verifies
+fn full_poseidon_expected_public(#[case] n: usize) {
+    use poseidon_circuit::PoseidonCircuit;
 
-    let row_n = (<P128Pow5T3 as Spec<Fp, 3>>::full_rounds()
-        + <P128Pow5T3 as Spec<Fp, 3>>::partial_rounds())
-        * (<P128Pow5T3 as Spec<Fp, 3>>::element_size() + 2)
-        + 6;
-    let degree = ((row_n * m) as f64).log2().ceil() as u32;
+    let inputs = (0..n as u64).map(Fp::from).collect::<Vec<_>>();
+    let public = PoseidonCircuit::<Fp, P128Pow5T3, 3>::expected_public(&inputs);
+    assert_eq!(
+        public,
+        circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.clone())
+    );
 
-    let path = utils::poseidon_hash::gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+    let degree = degree_for(PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(n));
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs);
+    let empty = halo2_proofs::plonk::Circuit::without_witnesses(&circuit);
+    assert!(prove_and_verify(degree, circuit, &empty, &[public]));
+}
 
-    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::new(
-        path.get_left_value(),
-        path.get_right_value(),
-        path.get_copy_value(m),
+#[cfg(test)]
+#[rstest]
+#[case(2, 3)]
+#[case(6, 3)]
+#[case(4, 1)]
+// squeezes several elements, the first one being the plain digest
+fn function_poseidon_squeeze(#[case] n: usize, #[case] outputs: usize) {
+    use circuit_samples::native::poseidon::{hash, hash_squeeze};
+    use poseidon_circuit::PoseidonCircuit;
+
+    let inputs = (0..n as u64).map(Fp::from).collect::<Vec<_>>();
+    let public = hash_squeeze::<Fp, P128Pow5T3, 3>(inputs.clone(), outputs);
+    assert_eq!(public.len(), 2 * outputs);
+    assert_eq!(public[..2], hash::<Fp, P128Pow5T3, 3>(inputs.clone())[..]);
+    assert_eq!(
+        PoseidonCircuit::<Fp, P128Pow5T3, 3>::expected_public_with_outputs(&inputs, outputs),
+        public
     );
-    let public = path
-        .get_leaf()
-        .into_iter()
-        .chain(path.get_index())
-        .chain(path.get_root())
-        .collect::<Vec<_>>();
-    let prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
 
-    prover.assert_satisfied();
+    let degree = degree_for(PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for_outputs(
+        n, outputs,
+    ));
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T3, 3>::new_with_outputs(inputs, outputs);
+    assert_eq!(circuit.permutations(), n / 2 + outputs - 1);
+    let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
     assert_eq!(prover.verify(), Ok(()));
+
+    // every squeezed value is constrained
+    for i in 0..public.len() {
+        let mut wrong = public.clone();
+        wrong[i] += Fp::one();
+        let prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
 
 #[cfg(test)]
 #[rstest]
-#[case(16, 32)]
-#[case(32, 32)]
-fn full_merkle_circuit(#[case] n: usize, #[case] m: usize) {
-    use halo2_proofs::{
-        plonk::{create_proof, verify_proof, SingleVerifier},
-        transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+// proves y=poseidon(len(x), x), a trailing zero changes the digest
+fn function_poseidon_with_length() {
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash_with_length;
+    use utils::p128_pow5_t2::P128Pow5T2;
+
+    let (a, b) = (Fp::from(3), Fp::from(5));
+    let short = hash_with_length::<Fp, P128Pow5T2, 3>(vec![a, b]);
+    let long = hash_with_length::<Fp, P128Pow5T2, 3>(vec![a, b, Fp::zero()]);
+    assert_ne!(short, long);
+
+    let circuit = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T2, 3>::hash_with_length(vec![
+        a,
+        b,
+        Fp::zero(),
+    ]);
+    let degree = min_k(&circuit, std::slice::from_ref(&long)).unwrap();
+
+    let prover = MockProver::run(degree, &circuit, vec![long]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let f_prover = MockProver::run(degree, &circuit, vec![short]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+// a single poseidon chip exposing its digest
+fn configure_p128_pow5_t3(
+    meta: &mut halo2_proofs::plonk::ConstraintSystem<Fp>,
+) -> PoseidonArthConfig<Fp, 3> {
+    let state = [(); 3].map(|_| meta.advice_column());
+    let arc = [(); 3].map(|_| meta.fixed_column());
+    let output = meta.instance_column();
+
+    PoseidonChip::configure::<P128Pow5T3>(
+        meta,
+        state,
+        output,
+        arc,
+        P128Pow5T3::mds(),
+        P128Pow5T3::arks(),
+        P128Pow5T3::capacity(),
+    )
+    .unwrap()
+}
+
+// hashes a fixed size array with `hash_array`
+#[derive(Clone)]
+struct ArrayHashCircuit<const N: usize>([Value<Fp>; N]);
+
+impl<const N: usize> halo2_proofs::plonk::Circuit<Fp> for ArrayHashCircuit<N> {
+    type Config = PoseidonArthConfig<Fp, 3>;
+
+    type FloorPlanner = halo2_proofs::circuit::SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ArrayHashCircuit([Value::unknown(); N])
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<Fp>) -> Self::Config {
+        configure_p128_pow5_t3(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = PoseidonChip::new(config);
+        let state = chip.hash_array::<P128Pow5T3, N>(&mut layouter, self.0)?;
+        chip.expose_public(&mut layouter, state, P128Pow5T3::element_size())
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 5)]
+#[case(0, 1)]
+// every state of the dumped permutation follows from the previous one by a
+// native round
+fn debug_dump_assignments(#[case] a: u64, #[case] b: u64) {
+    use circuit_samples::debug::dump_assignments;
+    use circuit_samples::native::poseidon::round;
+
+    let (a, b) = (Fp::from(a), Fp::from(b));
+    let circuit = ArrayHashCircuit([a, b].map(Value::known));
+    let digest = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(vec![a, b]);
+    let table = dump_assignments(8, &circuit, &[digest]).unwrap();
+
+    let state = |row: usize| -> Option<[Fp; 3]> {
+        let cells = (0..3)
+            .map(|i| table.advice[i][row])
+            .collect::<Option<Vec<_>>>()?;
+        cells.try_into().ok()
     };
-    use rand_core::OsRng;
+    let rows = table.advice[0].len();
+    let capacity = Fp::from_u128(P128Pow5T3::capacity());
 
-    let leaf_size = P128Pow5T3::element_size();
+    // the loaded state is copied to the first row of the permutation
+    let start = (0..rows)
+        .rposition(|row| state(row) == Some([a, b, capacity]))
+        .unwrap();
+    let rounds = P128Pow5T3::full_rounds() + P128Pow5T3::partial_rounds();
+    let mut expected = [a, b, capacity];
+    for r in 0..rounds {
+        expected = round::<Fp, P128Pow5T3, 3>(expected, r);
+        assert_eq!(state(start + 1 + r), Some(expected), "round {r}");
+    }
 
-    let row_n = (<P128Pow5T3 as Spec<Fp, 3>>::full_rounds()
-        + <P128Pow5T3 as Spec<Fp, 3>>::partial_rounds())
-        * (leaf_size + 2)
-        + 6;
-    let degree = ((row_n * m) as f64).log2().ceil() as u32;
+    // too few rows for the permutation
+    assert!(dump_assignments(4, &circuit, &[vec![]]).is_err());
+}
 
-    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+// exposes the parent of two nodes hashed by the chip with `hash_two`
+#[derive(Clone, Default)]
+struct TwoToOneCircuit<S> {
+    left: Vec<Value<Fp>>,
+    right: Vec<Value<Fp>>,
+    _marker: std::marker::PhantomData<S>,
+}
 
-    let prover_circuit = MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::new(
-        path.get_left_value(),
-        path.get_right_value(),
-        path.get_copy_value(m),
+impl<S: Spec<Fp, 3> + Clone + Default> halo2_proofs::plonk::Circuit<Fp> for TwoToOneCircuit<S> {
+    type Config = poseidon_circuit::CombinedConfig<Fp, S, 3>;
+
+    type FloorPlanner = halo2_proofs::circuit::SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        TwoToOneCircuit {
+            left: vec![Value::unknown(); self.left.len()],
+            right: vec![Value::unknown(); self.right.len()],
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<Fp>) -> Self::Config {
+        poseidon_circuit::CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (poseidon, _) = config.chips();
+        let state = poseidon.hash_two::<S>(&mut layouter, &self.left, &self.right)?;
+        poseidon.expose_public(&mut layouter, state, S::element_size())
+    }
+}
+
+fn verify_two_to_one<S: Spec<Fp, 3> + Clone + Default>(left: Vec<Fp>, right: Vec<Fp>) {
+    use circuit_samples::native::poseidon::{compress, merkle_root};
+
+    let parent = compress::<Fp, S, 3>(&left, &right);
+    assert_eq!(
+        merkle_root::<Fp, S, 3>(left.clone(), std::slice::from_ref(&right), &[false]),
+        parent
     );
-    let empty: Vec<Vec<Value<Fp>>> = vec![vec![Value::unknown(); leaf_size]; m];
-    let empty_copy: Vec<Value<Fp>> = vec![Value::unknown(); m + 1];
-    let empty_circuit = MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::new(
-        empty.clone(),
-        empty.clone(),
-        empty_copy.clone(),
+
+    let circuit = TwoToOneCircuit::<S> {
+        left: to_values(&left),
+        right: to_values(&right),
+        _marker: std::marker::PhantomData,
+    };
+    let prover = MockProver::run(8, &circuit, vec![parent.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let mut wrong = parent;
+    wrong[0] += Fp::one();
+    let prover = MockProver::run(8, &circuit, vec![wrong]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 2)]
+#[case(0, 0)]
+// the native compress of two nodes is the parent hashed in circuit
+fn function_two_to_one(#[case] a: u64, #[case] b: u64) {
+    use utils::p128_pow5_t2::P128Pow5T2;
+
+    let (a, b) = (Fp::from(a), Fp::from(b));
+    verify_two_to_one::<P128Pow5T3>(vec![a, b], vec![b, a]);
+    verify_two_to_one::<P128Pow5T2>(vec![a], vec![b]);
+}
+
+// y = poseidon(a, b) and z = a^3 on a single instance column [y, z]
+#[derive(Clone, Default)]
+struct CombinedCircuit([Value<Fp>; 2]);
+
+impl halo2_proofs::plonk::Circuit<Fp> for CombinedCircuit {
+    type Config = poseidon_circuit::CombinedConfig<Fp, P128Pow5T3, 3>;
+
+    type FloorPlanner = halo2_proofs::circuit::SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<Fp>) -> Self::Config {
+        poseidon_circuit::CombinedConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (poseidon, arth) = config.chips();
+        let state = poseidon.hash_array::<P128Pow5T3, 2>(&mut layouter, self.0)?;
+        poseidon.expose_public(&mut layouter, state, 2)?;
+
+        let a = arth.load_private(layouter.namespace(|| "load a"), self.0[0])?;
+        let z = arth.cube(layouter.namespace(|| "a^3"), a)?;
+        arth.expose_public(layouter.namespace(|| "expose z"), z, 2)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+// both chips of a combined config expose to one instance column
+fn function_combined_config() {
+    use circuit_samples::debug::{validate_public, CircuitError};
+
+    let (a, b) = (Fp::from(3), Fp::from(5));
+    let circuit = CombinedCircuit([a, b].map(Value::known));
+    let digest = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(vec![a, b]);
+    let public = [digest.clone(), vec![Fp::from(27)]].concat();
+
+    assert!(matches!(
+        validate_public(&circuit, &[public.clone(), vec![]]),
+        Err(CircuitError::InstanceColumns {
+            expected: 1,
+            supplied: 2
+        })
+    ));
+    assert!(validate_public(&circuit, std::slice::from_ref(&public)).is_ok());
+
+    let degree = circuit_samples::debug::min_k(&circuit, std::slice::from_ref(&public)).unwrap();
+    let prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let wrong = [digest, vec![Fp::from(28)]].concat();
+    let f_prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+// proves y=poseidon(x) for an array of 6 inputs
+fn function_poseidon_array() {
+    use ff::Field;
+
+    let degree = degree_for(poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(6));
+    let mut rng = rand::thread_rng();
+    let inputs: [Fp; 6] = [(); 6].map(|_| <Fp as Field>::random(&mut rng));
+    let mut outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.to_vec());
+
+    let circuit = ArrayHashCircuit(inputs.map(Value::known));
+
+    let prover = MockProver::run(degree, &circuit, vec![outputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    outputs[0] += Fp::from_u128(1);
+    let f_prover = MockProver::run(degree, &circuit, vec![outputs]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+// hashes the inputs with `hash_blocks`, handing over one block at a time
+#[derive(Clone, Default)]
+struct BlockHashCircuit(Vec<Value<Fp>>);
+
+impl halo2_proofs::plonk::Circuit<Fp> for BlockHashCircuit {
+    type Config = PoseidonArthConfig<Fp, 3>;
+
+    type FloorPlanner = halo2_proofs::circuit::SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        BlockHashCircuit(vec![Value::unknown(); self.0.len()])
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<Fp>) -> Self::Config {
+        configure_p128_pow5_t3(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = PoseidonChip::new(config);
+        let blocks = self.0.chunks(2).map(|c| [c[0], c[1]]);
+        let state = chip.hash_blocks::<P128Pow5T3, 2>(&mut layouter, blocks)?;
+        chip.expose_public(&mut layouter, state, P128Pow5T3::element_size())
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2)]
+#[case(10)]
+// streamed blocks give the same digest as the vector based circuit
+fn function_poseidon_blocks(#[case] n: usize) {
+    use ff::Field;
+
+    let degree = degree_for(poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(n));
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Fp> = (0..n).map(|_| <Fp as Field>::random(&mut rng)).collect();
+    let mut outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.clone());
+
+    let vector = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs.clone());
+    let streamed = BlockHashCircuit(to_values(&inputs));
+
+    let prover = MockProver::run(degree, &vector, vec![outputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(degree, &streamed, vec![outputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    outputs[0] += Fp::from_u128(1);
+    let f_prover = MockProver::run(degree, &streamed, vec![outputs]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+// hashes two messages one after the other, resetting the sponge in between
+#[derive(Clone, Default)]
+struct ResetHashCircuit([Vec<Value<Fp>>; 2]);
+
+impl halo2_proofs::plonk::Circuit<Fp> for ResetHashCircuit {
+    type Config = PoseidonArthConfig<Fp, 3>;
+
+    type FloorPlanner = halo2_proofs::circuit::SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ResetHashCircuit(self.0.clone().map(|m| vec![Value::unknown(); m.len()]))
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<Fp>) -> Self::Config {
+        configure_p128_pow5_t3(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = PoseidonChip::new(config);
+        let size = P128Pow5T3::element_size();
+
+        let mut state = chip.initiate(&mut layouter)?;
+        for (i, message) in self.0.iter().enumerate() {
+            if i > 0 {
+                state = chip.reset(&mut layouter, state)?;
+            }
+            for element in message.chunks(size) {
+                state = chip.absorb::<P128Pow5T3>(&mut layouter, state, element)?;
+            }
+            chip.expose_public_at(&mut layouter, state.clone(), size, i * size)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2, 4)]
+#[case(6, 2)]
+// a reset sponge hashes like a fresh one
+fn function_poseidon_reset(#[case] a_len: usize, #[case] b_len: usize) {
+    use circuit_samples::native::poseidon::hash;
+    use ff::Field;
+
+    let rows_for = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for;
+    let degree = degree_for(rows_for(a_len) + rows_for(b_len));
+    let mut rng = rand::thread_rng();
+    let mut random = |n| -> Vec<Fp> { (0..n).map(|_| <Fp as Field>::random(&mut rng)).collect() };
+    let (a, b) = (random(a_len), random(b_len));
+
+    let first = hash::<Fp, P128Pow5T3, 3>(a.clone());
+    let second = hash::<Fp, P128Pow5T3, 3>(b.clone());
+    let chained = hash::<Fp, P128Pow5T3, 3>([a.clone(), b.clone()].concat());
+    let known = |m: Vec<Fp>| m.into_iter().map(Value::known).collect::<Vec<_>>();
+    let circuit = ResetHashCircuit([known(a), known(b)]);
+
+    let public = [first.clone(), second].concat();
+    let prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // the second message does not continue the first one
+    let public = [first, chained].concat();
+    let f_prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0)]
+#[case(2)]
+// a reset row holding anything but zeros and the capacity breaks the reset
+// gate, even when the copies out of it are ignored
+fn function_poseidon_reset_tampered(#[case] lane: usize) {
+    use circuit_samples::native::poseidon::hash;
+    use halo2_proofs::dev::VerifyFailure;
+    use utils::tamper::tamper_advice;
+
+    let rows_for = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for;
+    let degree = degree_for(2 * rows_for(2));
+    let (a, b) = (
+        vec![Fp::from(1), Fp::from(2)],
+        vec![Fp::from(3), Fp::from(4)],
     );
-    let public = path
-        .get_leaf()
-        .into_iter()
-        .chain(path.get_index())
-        .chain(path.get_root())
-        .collect::<Vec<_>>();
+    let public = [
+        hash::<Fp, P128Pow5T3, 3>(a.clone()),
+        hash::<Fp, P128Pow5T3, 3>(b.clone()),
+    ]
+    .concat();
+    let known = |m: Vec<Fp>| m.into_iter().map(Value::known).collect::<Vec<_>>();
+    let circuit = ResetHashCircuit([known(a), known(b)]);
 
-    let params: Params<EqAffine> = Params::new(degree);
-    let vk = keygen_vk(&params, &empty_circuit).expect("failed to generate vk");
-    let pk = keygen_pk(&params, vk, &empty_circuit).expect("failed to generate pk");
-    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
-
-    // Create a proof
-    create_proof(
-        &params,
-        &pk,
-        &[prover_circuit],
-        &[&[&public]],
-        OsRng,
-        &mut transcript,
-    )
-    .expect("proof generation should not fail");
-    let proof: Vec<u8> = transcript.finalize();
-    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
-    let strategy = SingleVerifier::new(&params);
-    assert!(verify_proof(
-        &params,
-        pk.get_vk(),
-        strategy,
-        &[&[&public]],
-        &mut transcript,
+    let mut prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    tamper_advice(
+        &mut prover,
+        &circuit,
+        &format!("reset state {lane}"),
+        Fp::from(7),
     )
-    .is_ok());
+    .unwrap();
+
+    let failures = prover.verify().unwrap_err();
+    assert!(failures.iter().any(|f| matches!(
+        f,
+        VerifyFailure::ConstraintNotSatisfied { constraint, .. }
+            if constraint.to_string().contains("('reset')")
+    )));
+}
+
+// P128Pow5T3 absorbing a single input per element without padding the rest
+// of the rate
+#[derive(Debug, Clone, Default)]
+struct UnpaddedSpec;
+
+impl Spec<Fp, 3> for UnpaddedSpec {
+    fn full_rounds() -> usize {
+        P128Pow5T3::full_rounds()
+    }
+
+    fn partial_rounds() -> usize {
+        P128Pow5T3::partial_rounds()
+    }
+
+    fn mds() -> [[Fp; 3]; 3] {
+        P128Pow5T3::mds()
+    }
+
+    fn arks() -> Vec<[Fp; 3]> {
+        P128Pow5T3::arks()
+    }
+
+    fn capacity() -> u128 {
+        P128Pow5T3::capacity()
+    }
+
+    fn pad() -> Vec<Fp> {
+        vec![]
+    }
+
+    fn element_size() -> usize {
+        1
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "an element of 1 inputs padded with 0 values must fill the rate 2")]
+// a spec that does not fill the rate is rejected when configuring
+fn spec_rate_circuit() {
+    let circuit = poseidon_circuit::PoseidonCircuit::<Fp, UnpaddedSpec, 3>::new(vec![Fp::one()]);
+    let _ = MockProver::run(8, &circuit, vec![vec![Fp::one()]]);
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "an element of 1 inputs padded with 0 values must fill the rate 2")]
+// and by the native hash, instead of reading past the padded element
+fn spec_rate_native() {
+    circuit_samples::native::poseidon::hash::<Fp, UnpaddedSpec, 3>(vec![Fp::one()]);
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "a node is one element of the spec")]
+// merkle nodes must be exactly one element
+fn spec_merkle_node_size() {
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 1>::empty();
+    let _ = MockProver::run(8, &circuit, vec![vec![]]);
+}
+
+// a state of width one, all capacity and no rate
+#[derive(Debug, Clone, Default)]
+struct NarrowSpec;
+
+impl Spec<Fp, 1> for NarrowSpec {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        0
+    }
+
+    fn mds() -> [[Fp; 1]; 1] {
+        [[Fp::one()]]
+    }
+
+    fn arks() -> Vec<[Fp; 1]> {
+        vec![[Fp::zero()]; 8]
+    }
+
+    fn capacity() -> u128 {
+        0
+    }
+
+    fn pad() -> Vec<Fp> {
+        vec![]
+    }
+
+    fn element_size() -> usize {
+        1
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "a poseidon state of width 1 has no rate, the width must be at least 2")]
+// a poseidon chip without rate is rejected when configuring
+fn spec_poseidon_width() {
+    let mut meta = halo2_proofs::plonk::ConstraintSystem::<Fp>::default();
+    let state = [meta.advice_column()];
+    let output = meta.instance_column();
+    let arc = [meta.fixed_column()];
+    let _ = PoseidonChip::<Fp, 1>::configure::<NarrowSpec>(
+        &mut meta,
+        state,
+        output,
+        arc,
+        [[Fp::one()]],
+        vec![],
+        0,
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "a poseidon state of width 1 has no rate, the width must be at least 2")]
+// and by the native hash, instead of underflowing the rate
+fn spec_poseidon_width_native() {
+    circuit_samples::native::poseidon::hash::<Fp, NarrowSpec, 1>(vec![Fp::one()]);
+}
+
+#[cfg(test)]
+#[rstest]
+// a poseidon chip configured with another mds than its spec is rejected
+fn spec_poseidon_mds() {
+    use circuit_samples::chips::poseidon_chip::ConfigError;
+
+    let mut meta = halo2_proofs::plonk::ConstraintSystem::<Fp>::default();
+    let mut configure = |mds| {
+        let state = [(); 3].map(|_| meta.advice_column());
+        let output = meta.instance_column();
+        let arc = [(); 3].map(|_| meta.fixed_column());
+        PoseidonChip::configure::<P128Pow5T3>(
+            &mut meta,
+            state,
+            output,
+            arc,
+            mds,
+            P128Pow5T3::arks(),
+            P128Pow5T3::capacity(),
+        )
+    };
+    // the spec mds passes
+    assert!(configure(P128Pow5T3::mds()).is_ok());
+
+    let mut mds = P128Pow5T3::mds();
+    mds.swap(0, 1);
+    let error = configure(mds).unwrap_err();
+    assert_eq!(error, ConfigError::MdsMismatch { row: 0, column: 0 });
+    assert_eq!(
+        error.to_string(),
+        "the configured mds differs from the spec at row 0, column 0"
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "a merkle node holds at least one value, I must be at least 1")]
+// a merkle chip of empty nodes is rejected when configuring
+fn spec_merkle_empty_node() {
+    use circuit_samples::chips::merkle_chip::MerklePathChip;
+
+    let mut meta = halo2_proofs::plonk::ConstraintSystem::<Fp>::default();
+    let (copy, index) = (meta.advice_column(), meta.advice_column());
+    let public = meta.instance_column();
+    MerklePathChip::<Fp, 0>::configure(&mut meta, [], copy, index, public);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(16, 32)]
+#[case(32, 32)]
+fn function_merkle_32(#[case] n: usize, #[case] m: usize) {
+    use circuit_samples::circuits::merkle_circuit::MerklePathCircuit;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = utils::poseidon_hash::gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(m),
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    let prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+
+    prover.assert_satisfied();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+#[cfg(test)]
+#[rstest]
+// the grain generator reproduces the published parameters of P128Pow5T3
+fn spec_grain_p128_pow5_t3() {
+    let (arks, mds) = utils::grain::generate::<Fp, 3>(8, 56);
+    assert_eq!(arks, <P128Pow5T3 as Spec<Fp, 3>>::arks());
+    assert_eq!(mds, <P128Pow5T3 as Spec<Fp, 3>>::mds());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(4)]
+#[case(8)]
+// proves y=poseidon(x) with width 5, absorbing 4 values at a time
+fn function_poseidon_t5(#[case] n: usize) {
+    use poseidon_circuit::PoseidonCircuit;
+    use utils::p128_pow5_t5::P128Pow5T5;
+
+    let inputs = (0..n as u64).map(Fp::from).collect::<Vec<_>>();
+    let mut outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T5, 5>(inputs.clone());
+    assert_eq!(outputs.len(), 4);
+
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T5, 5>::new(inputs);
+    let degree = degree_for(PoseidonCircuit::<Fp, P128Pow5T5, 5>::rows_for(n));
+    let prover = MockProver::run(degree, &circuit, vec![outputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    outputs[3] += Fp::one();
+    let f_prover = MockProver::run(degree, &circuit, vec![outputs]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case([0, 1, 2])]
+#[case([3, 3, 0])]
+#[case([2, 0, 1])]
+// a leaf at the given position of each layer of a 4-ary tree of depth 3
+fn function_quad_merkle(#[case] positions: [usize; 3]) {
+    use circuit_samples::circuits::quad_merkle_circuit::QuadMerkleCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+    use utils::p128_pow5_t5::P128Pow5T5;
+
+    let leaf = Fp::from(42);
+    let mut node = leaf;
+    let mut siblings = vec![];
+    let mut public = vec![leaf];
+    for (layer, &p) in positions.iter().enumerate() {
+        let s = [0, 1, 2].map(|k| Fp::from((10 * layer + k) as u64));
+        let mut children = s.to_vec();
+        children.insert(p, node);
+        node = hash::<Fp, P128Pow5T5, 5>(children)[0];
+        siblings.push(s);
+        public.extend([Fp::from((p & 1) as u64), Fp::from((p >> 1) as u64)]);
+    }
+    public.push(node);
+
+    let circuit = QuadMerkleCircuit::<Fp, P128Pow5T5, 3, 5>::new(siblings);
+    let degree = min_k(&circuit, std::slice::from_ref(&public)).unwrap();
+    let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // any other position moves the node among its siblings
+    for bit in 1..7 {
+        let mut wrong = public.clone();
+        wrong[bit] = Fp::one() - wrong[bit];
+        let f_prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+        assert!(f_prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 8)]
+#[case(5, 8)]
+#[case(8, 8)]
+// leaves of 4 values, the root sits at rows M + 4.. of the instance
+fn function_merkle_wide(#[case] n: usize, #[case] m: usize) {
+    use utils::p128_pow5_t5::P128Pow5T5;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T5, 8, 5, 4>::rows_for(
+        false,
+    ));
+
+    let path = gen_merkle_path::<Fp, P128Pow5T5, 5>(n, m);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T5, 8, 5, 4>::from_layers(
+        path.get_left_value(),
+        path.get_right_value(),
+    );
+    let mut public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    assert_eq!(public.len(), 4 + 8 + 4);
+
+    let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // every value of the root is bound
+    public[4 + 8 + 3] += Fp::one();
+    let f_prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 64)]
+#[case(2, 64)]
+// most of the path is padding, the root is copied through every layer
+fn function_merkle_64(#[case] n: usize, #[case] m: usize) {
+    use circuit_samples::native::poseidon::hash;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 64, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+    if n == 1 {
+        // a single hash of the leaf and its sibling
+        let (leaf, sibling) = (path.get_leaf(), path.get_siblings().remove(0));
+        let inputs = match path.get_index()[0] == Fp::one() {
+            true => [sibling, leaf].concat(),
+            false => [leaf, sibling].concat(),
+        };
+        assert_eq!(path.get_root(), hash::<Fp, P128Pow5T3, 3>(inputs));
+    }
+
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 64, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(m),
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let mut wrong = public;
+    *wrong.last_mut().unwrap() += Fp::one();
+    let f_prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(None)]
+#[case(Some(1))]
+// a batch of three merkle proofs sharing keys, rejecting a proof whose public
+// inputs were swapped for those of another path
+fn full_merkle_batch(#[case] invalid: Option<usize>) {
+    use circuit_samples::verifier::verify_batch;
+    use halo2_proofs::pasta::EqAffine;
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+    use halo2_proofs::poly::commitment::Params;
+    use utils::prover::prove;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let empty = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::empty();
+    let params: Params<EqAffine> = Params::new(degree);
+    let vk = keygen_vk(&params, &empty).unwrap();
+    let pk = keygen_pk(&params, vk, &empty).unwrap();
+
+    let mut proofs = [1, 2, 4]
+        .into_iter()
+        .map(|n| {
+            let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+            let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+                path.get_left_value(),
+                path.get_right_value(),
+                path.get_copy_value(4),
+            );
+            let public = path
+                .get_leaf()
+                .into_iter()
+                .chain(path.get_index())
+                .chain(path.get_root())
+                .collect::<Vec<_>>();
+            let proof = prove(&params, &pk, circuit, std::slice::from_ref(&public)).unwrap();
+            (proof, vec![public])
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(i) = invalid {
+        proofs[i].1 = proofs[(i + 1) % 3].1.clone();
+    }
+
+    assert_eq!(
+        verify_batch(&params, pk.get_vk(), &proofs),
+        invalid.map_or(Ok(()), |i| Err(vec![i]))
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 32)]
+#[case(16, 32)]
+#[case(32, 32)]
+// keys generated from the empty circuit verify paths of any length
+fn full_merkle_circuit(#[case] n: usize, #[case] m: usize) {
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+
+    let prover_circuit = MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(m),
+    );
+    let empty_circuit = MerklePathCircuit::<Fp, P128Pow5T3, 32, 3, 2>::empty();
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+
+    circuit_samples::debug::validate_public(&prover_circuit, std::slice::from_ref(&public))
+        .expect("public inputs do not fit");
+
+    assert!(prove_and_verify(
+        degree,
+        prover_circuit,
+        &empty_circuit,
+        &[public]
+    ));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 5, 35)]
+#[case(2, 5, 50)]
+// the real prover accepts demo1 only for (x^3+x)y=z
+fn full_demo1(#[case] x: u64, #[case] y: u64, #[case] z: u64) {
+    let circuit = arth_circuit::DemoCircuit1::new(Fp::from(x));
+    let empty = arth_circuit::DemoCircuit1::default();
+    let public = vec![vec![Fp::from(y), Fp::from(z)]];
+
+    assert_eq!(
+        prove_and_verify(4, circuit, &empty, &public),
+        y * (x.pow(3) + x) == z
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(5, 4)]
+#[case(0, 4)]
+#[case(0, 0)]
+#[should_panic(expected = "depth")]
+// a path needs a real depth n between 1 and the max depth m
+fn merkle_gen_path_bounds(#[case] n: usize, #[case] m: usize) {
+    gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 4)]
+#[case(16, 32)]
+#[case(32, 32)]
+fn merkle_copy_flags(#[case] n: usize, #[case] m: usize) {
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+    let expected = path.get_copy_value(m);
+    let flags = copy_flags::<Fp>(n, m);
+
+    assert_eq!(flags.len(), expected.len());
+    for (f, e) in flags.into_iter().zip(expected) {
+        f.zip(e).assert_if_known(|(f, e)| f == e);
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 4)]
+#[case(4, 4)]
+// copy flags derived from M match the ones of the reference path
+fn merkle_from_layers(#[case] n: usize, #[case] m: usize) {
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::from_layers(
+        path.get_left_value(),
+        path.get_right_value(),
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    let prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "needs M + 1 copy flags")]
+// copy flags built for another depth are rejected before synthesis
+fn merkle_wrong_copy_length() {
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(2, 8);
+
+    MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(8),
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1)]
+#[case(2)]
+#[case(3)]
+fn merkle_tampered_padding(#[case] n: usize) {
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+
+    // the padding layers duplicate the root, replace the right copy
+    let mut right = path.get_right_value();
+    right[n] = vec![Value::known(Fp::from(7)); 2];
+
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        right,
+        copy_flags(n, 4),
+    );
+    let prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 4)]
+#[case(4, 4)]
+fn native_merkle_root(#[case] n: usize, #[case] m: usize) {
+    use circuit_samples::native::poseidon::merkle_root;
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+    let index = path
+        .get_index()
+        .into_iter()
+        .take(n)
+        .map(|b| b == Fp::one())
+        .collect::<Vec<_>>();
+    let root = merkle_root::<Fp, P128Pow5T3, 3>(path.get_leaf(), &path.get_siblings(), &index);
+    assert_eq!(root, path.get_root());
+}
+
+#[derive(Default)]
+struct NonZero;
+
+impl Gadget<Fp> for NonZero {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        chip.assert_nonzero(layouter, inputs[0].clone())
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1)]
+#[case(5)]
+#[case(0)]
+fn gadget_nonzero(#[case] a: u64) {
+    let circuit = GadgetCircuit::<Fp, NonZero>::new(vec![Fp::from(a)]);
+    let prover = MockProver::run(4, &circuit, vec![vec![]]).unwrap();
+
+    if a != 0 {
+        assert_eq!(prover.verify(), Ok(()));
+    } else {
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2, 2)]
+#[case(4, 2)]
+#[case(6, 8)]
+// proves y=poseidon(poseidon(a), poseidon(b))
+fn function_nested_poseidon(#[case] a_len: usize, #[case] b_len: usize) {
+    use circuit_samples::circuits::nested_circuit::NestedHashCircuit;
+    use circuit_samples::native::poseidon::hash;
+    use ff::Field;
+
+    // the outer sponge absorbs both inner digests
+    let rows_for = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for;
+    let size = <P128Pow5T3 as Spec<Fp, 3>>::element_size();
+    let degree = degree_for(rows_for(a_len) + rows_for(b_len) + rows_for(2 * size));
+
+    let mut rng = rand::thread_rng();
+    let a: Vec<Fp> = (0..a_len)
+        .map(|_| <Fp as Field>::random(&mut rng))
+        .collect();
+    let b: Vec<Fp> = (0..b_len)
+        .map(|_| <Fp as Field>::random(&mut rng))
+        .collect();
+
+    let inner = hash::<Fp, P128Pow5T3, 3>(a.clone())
+        .into_iter()
+        .chain(hash::<Fp, P128Pow5T3, 3>(b.clone()))
+        .collect::<Vec<_>>();
+    let mut outputs = hash::<Fp, P128Pow5T3, 3>(inner);
+
+    let circuit = NestedHashCircuit::<Fp, P128Pow5T3, 3>::new(a, b);
+
+    let prover = MockProver::run(degree, &circuit, vec![outputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    outputs[0] += Fp::from_u128(1);
+    let f_prover = MockProver::run(degree, &circuit, vec![outputs]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![7, 3, 200])]
+#[case(vec![9, 9, 0, 255, 4])]
+#[case(vec![1])]
+// proves m is the median of the values committed to by poseidon(x)
+fn function_median(#[case] x: Vec<u64>) {
+    use circuit_samples::circuits::median_circuit::MedianCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+    use utils::p128_pow5_t2::P128Pow5T2;
+
+    let mut sorted = x.clone();
+    sorted.sort();
+    let median = Fp::from(sorted[x.len() / 2]);
+
+    let x = x.into_iter().map(Fp::from).collect::<Vec<_>>();
+    let commitment = hash::<Fp, P128Pow5T2, 3>(x.clone());
+    let circuit = MedianCircuit::<Fp, P128Pow5T2, 3>::new(x, 8);
+
+    let public = vec![commitment.clone(), vec![median]];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // the keys come from the circuit without witnesses
+    let empty = halo2_proofs::plonk::Circuit::without_witnesses(&circuit);
+    assert!(prove_and_verify(degree, circuit.clone(), &empty, &public));
+
+    // any other value is rejected, including the other members
+    let members = sorted.iter().map(|&v| Fp::from(v));
+    for wrong in members.chain([median + Fp::one()]).filter(|&v| v != median) {
+        let public = vec![commitment.clone(), vec![wrong]];
+        let f_prover = MockProver::run(degree, &circuit, public).unwrap();
+        assert!(f_prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "the median needs an odd count, got 4 values")]
+// an even count has no middle element
+fn function_median_even() {
+    use circuit_samples::circuits::median_circuit::MedianCircuit;
+
+    let x = [4, 1, 3, 2].map(Fp::from).to_vec();
+    MedianCircuit::<Fp, utils::p128_pow5_t2::P128Pow5T2, 3>::new(x, 8);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0x1234_5678, 16)]
+#[case(u64::MAX - 1, 32)]
+#[case(5, 64)]
+// proves x=poseidon(hi, lo) for the halves of a private v
+fn function_split_hash(#[case] v: u64, #[case] bits: usize) {
+    use circuit_samples::circuits::split_circuit::SplitHashCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+
+    let (hi, lo) = (v as u128 >> bits, v as u128 & ((1 << bits) - 1));
+    let (hi, lo) = (Fp::from_u128(hi), Fp::from_u128(lo));
+    let circuit = SplitHashCircuit::<Fp, P128Pow5T3, 3>::new(Fp::from(v), bits);
+
+    let public = vec![hash::<Fp, P128Pow5T3, 3>(vec![hi, lo]), vec![]];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // a tampered half, or the halves swapped, hash to another digest
+    for (hi, lo) in [(hi + Fp::one(), lo), (hi, lo + Fp::one()), (lo, hi)] {
+        let public = vec![hash::<Fp, P128Pow5T3, 3>(vec![hi, lo]), vec![]];
+        let f_prover = MockProver::run(degree, &circuit, public).unwrap();
+        assert!(f_prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 5, true)]
+#[case(0, 1, true)]
+#[case(7, 7, false)]
+// proves the preimages of poseidon(a) and poseidon(b) differ
+fn function_distinct(#[case] a: u64, #[case] b: u64, #[case] distinct: bool) {
+    use circuit_samples::circuits::distinct_circuit::DistinctCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+    use utils::p128_pow5_t2::P128Pow5T2;
+
+    let (a, b) = (Fp::from(a), Fp::from(b));
+    let digests = hash::<Fp, P128Pow5T2, 3>(vec![a])
+        .into_iter()
+        .chain(hash::<Fp, P128Pow5T2, 3>(vec![b]))
+        .collect::<Vec<_>>();
+    let circuit = DistinctCircuit::<Fp, P128Pow5T2, 3>::new(a, b);
+
+    let public = vec![digests.clone(), vec![]];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), distinct);
+
+    // the digests are bound in order
+    if distinct {
+        let public = vec![vec![digests[1], digests[0]], vec![]];
+        let f_prover = MockProver::run(degree, &circuit, public).unwrap();
+        assert!(f_prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+// raw witness slices convert into circuits only with the right shape
+fn circuit_try_from_slice() {
+    use circuit_samples::debug::CircuitError;
+    use poseidon_circuit::PoseidonCircuit;
+
+    let inputs = [Fp::from(1), Fp::from(2), Fp::from(3), Fp::from(4)];
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T3, 3>::try_from(&inputs[..]).unwrap();
+    let public =
+        vec![circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.to_vec())];
+    let degree = degree_for(PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(4));
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    for x in [&inputs[..3], &[]] {
+        assert!(matches!(
+            PoseidonCircuit::<Fp, P128Pow5T3, 3>::try_from(x),
+            Err(CircuitError::WitnessNotChunked { chunk: 2, .. })
+        ));
+    }
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(2, 4);
+    let layers = path.get_layers();
+    assert_eq!(layers.len(), 3 * 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::try_from(&layers[..]).unwrap();
+    let public = vec![path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>()];
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    assert!(matches!(
+        MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::try_from(&layers[..10]),
+        Err(CircuitError::WitnessNotChunked {
+            chunk: 4,
+            supplied: 10
+        })
+    ));
+    let deep = vec![Fp::one(); 6 * 4];
+    let err = match MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::try_from(&deep[..]) {
+        Err(err) => err,
+        Ok(_) => panic!("a path deeper than M is accepted"),
+    };
+    assert_eq!(
+        err.to_string(),
+        "the circuit takes at most 20 witness values, got 24"
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0)]
+#[case(1)]
+#[case(0x0102_0304_0506_0708)]
+// field elements round trip through both byte orders
+fn native_byte_encoding(#[case] v: u64) {
+    use circuit_samples::native::encoding::*;
+
+    let v = Fp::from(v) + Fp::from_u128(1 << 100);
+    let le = field_to_le_bytes(&v);
+    let be = field_to_be_bytes(&v);
+    assert_eq!(le.len(), 32);
+    assert_eq!(be.iter().rev().copied().collect::<Vec<_>>(), le);
+
+    // the low byte comes first in little-endian and last in big-endian
+    assert_eq!(le[12], 0x10);
+    assert_eq!(be[31 - 12], 0x10);
+
+    assert_eq!(field_from_le_bytes::<Fp>(&le), Some(v));
+    assert_eq!(field_from_be_bytes::<Fp>(&be), Some(v));
+
+    // wrong lengths and values past the modulus are rejected
+    assert_eq!(field_from_be_bytes::<Fp>(&be[1..]), None);
+    assert_eq!(field_from_be_bytes::<Fp>(&[0xff; 32]), None);
+}
+
+#[cfg(test)]
+#[rstest]
+// public vectors of the wrong shape are rejected before proving
+fn debug_validate_public() {
+    use circuit_samples::debug::{validate_public, CircuitError};
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(2, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::from_layers(
+        path.get_left_value(),
+        path.get_right_value(),
+    );
+    // leaf, index and root
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    assert_eq!(public.len(), 2 + 4 + 2);
+    assert!(validate_public(&circuit, std::slice::from_ref(&public)).is_ok());
+
+    let short = public[..public.len() - 1].to_vec();
+    let err = validate_public(&circuit, &[short]).unwrap_err();
+    assert!(matches!(
+        err,
+        CircuitError::InstanceTooShort {
+            column: 0,
+            expected: 8,
+            supplied: 7
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "instance column 0 binds 8 rows, got only 7 values"
+    );
+
+    let long = [public.clone(), vec![Fp::one()]].concat();
+    assert!(matches!(
+        validate_public(&circuit, &[long]),
+        Err(CircuitError::InstanceTooLong { expected: 8, .. })
+    ));
+    assert!(matches!(
+        validate_public(&circuit, &[public, vec![]]),
+        Err(CircuitError::InstanceColumns {
+            expected: 1,
+            supplied: 2
+        })
+    ));
+}
+
+#[cfg(test)]
+#[rstest]
+// the prover rejects a too short public vector before creating a proof
+fn debug_prove_short_public() {
+    use circuit_samples::debug::CircuitError;
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+    use halo2_proofs::poly::commitment::Params;
+    use utils::prover::prove;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let empty = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::empty();
+    let params: Params<halo2_proofs::pasta::EqAffine> = Params::new(degree);
+    let vk = keygen_vk(&params, &empty).unwrap();
+    let pk = keygen_pk(&params, vk, &empty).unwrap();
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(2, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(4),
+    );
+    // the root is missing
+    let short = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .collect::<Vec<_>>();
+    assert!(matches!(
+        prove(&params, &pk, circuit, &[short]),
+        Err(CircuitError::InstanceTooShort {
+            column: 0,
+            expected: 8,
+            supplied: 6
+        })
+    ));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 5, 150)]
+#[case(3, 5, 35)]
+// report the public rows whose value differs from the witness
+fn debug_instance_mismatch(#[case] x: u64, #[case] y: u64, #[case] z: u64) {
+    use circuit_samples::debug::diagnose_instances;
+
+    let circuit = arth_circuit::DemoCircuit1::new(Fp::from(x));
+    let public = vec![vec![Fp::from(y), Fp::from(z)]];
+    let mismatches = diagnose_instances(&circuit, &public).unwrap();
+
+    let expected = y * (x.pow(3) + x);
+    if expected == z {
+        assert!(mismatches.is_empty());
+    } else {
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].row, 1);
+        assert_eq!(mismatches[0].expected, Fp::from(expected));
+        assert_eq!(mismatches[0].supplied, Some(Fp::from(z)));
+    }
+}
+
+#[test]
+// a digest missing from the public vector is reported without a supplied value
+fn debug_instance_missing() {
+    use circuit_samples::debug::diagnose_instances;
+    use ff::Field;
+
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Fp> = (0..4).map(|_| <Fp as Field>::random(&mut rng)).collect();
+    let outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.clone());
+
+    let circuit = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs);
+    let mismatches = diagnose_instances(&circuit, &[outputs[..1].to_vec()]).unwrap();
+
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].row, 1);
+    assert_eq!(mismatches[0].expected, outputs[1]);
+    assert_eq!(mismatches[0].supplied, None);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0)]
+#[case(1)]
+// every element of a wide leaf is selected by the same index bit
+fn merkle_select_wide_leaf(#[case] bit: u64) {
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = loop {
+        let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(2, 4);
+        if path.get_index()[0] == Fp::from(bit) {
+            break path;
+        }
+    };
+    let siblings = path.get_siblings();
+    let leaf = path.get_leaf();
+    assert_ne!(leaf[0], leaf[1]);
+
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        copy_flags(2, 4),
+    );
+    let public = |leaf: Vec<Fp>| {
+        leaf.into_iter()
+            .chain(path.get_index())
+            .chain(path.get_root())
+            .collect::<Vec<_>>()
+    };
+
+    let prover = MockProver::run(degree, &circuit, vec![public(leaf.clone())]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // mixing elements of the selected leaf and its sibling must fail
+    let mixed = vec![leaf[0], siblings[0][1]];
+    let prover = MockProver::run(degree, &circuit, vec![public(mixed)]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+// hashing through a spec picked at runtime matches the generic call
+fn native_hash_by_name() {
+    use circuit_samples::native::registry::{Error, Registry};
+    use ff::Field;
+    use utils::p128_pow5_t2::P128Pow5T2;
+
+    let mut registry = Registry::<Fp>::default();
+    registry.register::<P128Pow5T3, 3>("p128pow5t3");
+    registry.register::<P128Pow5T2, 3>("p128pow5t2");
+    assert_eq!(
+        registry.names().collect::<Vec<_>>(),
+        vec!["p128pow5t2", "p128pow5t3"]
+    );
+
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Fp> = (0..4).map(|_| <Fp as Field>::random(&mut rng)).collect();
+
+    assert_eq!(
+        registry.hash_by_name("p128pow5t3", inputs.clone()),
+        Ok(circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.clone()))
+    );
+    assert_eq!(
+        registry.hash_by_name("p128pow5t2", inputs.clone()),
+        Ok(circuit_samples::native::poseidon::hash::<Fp, P128Pow5T2, 3>(inputs.clone()))
+    );
+    assert_eq!(
+        registry.hash_by_name("p256", inputs),
+        Err(Error::UnknownSpec("p256".into()))
+    );
+}
+
+#[derive(Default)]
+struct Affine<const A: u64, const B: u64>;
+
+impl<const A: u64, const B: u64> Gadget<Fp> for Affine<A, B> {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let y = chip.affine(
+            layouter.namespace(|| "affine"),
+            Fp::from(A),
+            inputs[0].clone(),
+            -Fp::from(B),
+        )?;
+        chip.expose_public(layouter.namespace(|| "expose y"), y, 0)
+    }
+}
+
+fn check_affine<const A: u64, const B: u64>(x: u64) {
+    let circuit = GadgetCircuit::<Fp, Affine<A, B>>::new(vec![Fp::from(x)]);
+    let y = Fp::from(A) * Fp::from(x) - Fp::from(B);
+
+    let prover = MockProver::run(4, &circuit, vec![vec![y]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(4, &circuit, vec![vec![y + Fp::one()]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0)]
+#[case(3)]
+#[case(1 << 40)]
+// proves y = a * x - b for a few (a, b)
+fn gadget_affine(#[case] x: u64) {
+    check_affine::<0, 0>(x);
+    check_affine::<1, 0>(x);
+    check_affine::<2, 7>(x);
+    check_affine::<12345, 1>(x);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2, true)]
+#[case(4, true)]
+#[case(2, false)]
+// the public input is poseidon(root || nonce) instead of the root
+fn merkle_committed_root(#[case] n: usize, #[case] matching: bool) {
+    use circuit_samples::circuits::committed_root_circuit::CommittedRootCircuit;
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+    use circuit_samples::native::poseidon::hash;
+    use ff::Field;
+
+    let degree = degree_for(
+        MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(false)
+            + poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(4),
+    );
+
+    let mut rng = rand::thread_rng();
+    let nonce: Vec<Fp> = (0..2).map(|_| <Fp as Field>::random(&mut rng)).collect();
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let commitment = hash::<Fp, P128Pow5T3, 3>(
+        path.get_root()
+            .into_iter()
+            .chain(nonce.iter().copied())
+            .collect(),
+    );
+
+    let circuit_nonce = match matching {
+        true => nonce,
+        false => vec![nonce[0], nonce[1] + Fp::one()],
+    };
+    let circuit = CommittedRootCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        MerklePathCircuit::new(
+            path.get_left_value(),
+            path.get_right_value(),
+            copy_flags(n, 4),
+        ),
+        circuit_nonce,
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(commitment)
+        .collect::<Vec<_>>();
+    let public = vec![public];
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+
+    if matching {
+        assert_eq!(prover.verify(), Ok(()));
+    } else {
+        assert!(prover.verify().is_err());
+    }
+
+    // the keys come from the circuit without witnesses
+    let empty = halo2_proofs::plonk::Circuit::without_witnesses(&circuit);
+    assert_eq!(prove_and_verify(degree, circuit, &empty, &public), matching);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, true)]
+#[case(3, false)]
+// only the salted root poseidon(root || salt) is public, and only for its salt
+fn merkle_salted_root(#[case] n: usize, #[case] correct: bool) {
+    use circuit_samples::circuits::committed_root_circuit::SaltedRootCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+
+    let salt = vec![Fp::from(0x5a17), Fp::from(0x5a18)];
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let salted = hash::<Fp, P128Pow5T3, 3>([path.get_root(), salt.clone()].concat());
+
+    let witness = match correct {
+        true => salt,
+        false => vec![Fp::from(0x5a17), Fp::from(0x5a19)],
+    };
+    let circuit = SaltedRootCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        MerklePathCircuit::from_layers(path.get_left_value(), path.get_right_value()),
+        witness,
+    );
+    let public = vec![[path.get_leaf(), path.get_index(), salted].concat()];
+    assert!(path.get_root().iter().all(|r| !public[0].contains(r)));
+
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), correct);
+}
+
+#[derive(Default)]
+struct DemoExpr;
+
+impl Gadget<Fp> for DemoExpr {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        use circuit_samples::chips::arth_expr::{synthesize_expr, Expr};
+
+        // (x^3+x)y
+        let x = Expr::input(0);
+        let expr = x.clone().cube().add(x).mul(Expr::input(1));
+
+        let y = chip.load_public(layouter.namespace(|| "load y"), 0)?;
+        let z = synthesize_expr(chip, &mut layouter, &expr, &[inputs[0].clone(), y])?;
+        chip.expose_public(layouter.namespace(|| "expose z"), z, 1)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 5, 150)]
+#[case(3, 5, 35)]
+#[case(2, 5, 50)]
+// the recorded expression proves the same statement as DemoCircuit1
+fn expr_demo1(#[case] x: u64, #[case] y: u64, #[case] z: u64) {
+    let public = vec![Fp::from(y), Fp::from(z)];
+
+    let demo = arth_circuit::DemoCircuit1::new(Fp::from(x));
+    let demo_prover = MockProver::run(4, &demo, vec![public.clone()]).unwrap();
+
+    let circuit = GadgetCircuit::<Fp, DemoExpr>::new(vec![Fp::from(x)]);
+    let prover = MockProver::run(4, &circuit, vec![public]).unwrap();
+
+    assert_eq!(prover.verify().is_ok(), demo_prover.verify().is_ok());
+    assert_eq!(prover.verify().is_ok(), y * (x.pow(3) + x) == z);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 5, 5)]
+#[case(3, 5, 6)]
+#[case(2, 5, 0)]
+// the public y loaded by DemoCircuit1 is bound to the instance, a prover
+// witnessing another y (with z matching it) must be rejected
+fn arth_load_public_binding(#[case] x: u64, #[case] y: u64, #[case] tampered_y: u64) {
+    use halo2_proofs::dev::VerifyFailure;
+    use utils::tamper::tamper_instance;
+
+    let circuit = arth_circuit::DemoCircuit1::new(Fp::from(x));
+    let z = Fp::from(tampered_y * (x.pow(3) + x));
+    let public = vec![Fp::from(y), z];
+
+    let mut prover = MockProver::run(4, &circuit, vec![public]).unwrap();
+    tamper_instance(&mut prover, &circuit, 0, Fp::from(tampered_y)).unwrap();
+    if y == tampered_y {
+        assert_eq!(prover.verify(), Ok(()));
+        return;
+    }
+
+    // every gate and the exposed z hold, only the public binding fails
+    let failures = prover.verify().unwrap_err();
+    assert!(!failures.is_empty());
+    assert!(failures
+        .iter()
+        .all(|f| matches!(f, VerifyFailure::Permutation { .. })));
+}
+
+fn check_unrolled<const R: usize>(k: u32, inputs: Vec<Fp>) {
+    let mut outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.clone());
+    let circuit = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3, R>::new(inputs);
+
+    let prover = MockProver::run(k, &circuit, vec![outputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    outputs[0] += Fp::one();
+    let prover = MockProver::run(k, &circuit, vec![outputs]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2, 7)]
+#[case(4, 8)]
+// more rounds per row give the same hash with fewer rows
+fn function_poseidon_unrolled(#[case] n: usize, #[case] k: u32) {
+    use ff::Field;
+
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Fp> = (0..n).map(|_| <Fp as Field>::random(&mut rng)).collect();
+    check_unrolled::<1>(k, inputs.clone());
+    check_unrolled::<2>(k - 1, inputs.clone());
+    check_unrolled::<4>(k - 2, inputs.clone());
+
+    // a single lane does not fit in the smaller circuit
+    let circuit = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs.clone());
+    let outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs);
+    assert!(MockProver::run(k - 1, &circuit, vec![outputs]).is_err());
+}
+
+const XOR: u8 = 0;
+const AND: u8 = 1;
+const OR: u8 = 2;
+
+// exposes `a op b` for the boolean gate selected by OP
+#[derive(Clone, Default)]
+struct Bitwise<const OP: u8>;
+
+impl<const OP: u8> Gadget<Fp> for Bitwise<OP> {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let (a, b) = (inputs[0].clone(), inputs[1].clone());
+        let out = match OP {
+            XOR => chip.xor(layouter.namespace(|| "xor"), a, b)?,
+            AND => chip.and(layouter.namespace(|| "and"), a, b)?,
+            _ => chip.or(layouter.namespace(|| "or"), a, b)?,
+        };
+        chip.expose_public(layouter.namespace(|| "expose out"), out, 0)
+    }
+}
+
+fn verify_bitwise<const OP: u8>(a: Fp, b: Fp, out: Fp) -> bool {
+    let circuit = GadgetCircuit::<Fp, Bitwise<OP>>::new(vec![a, b]);
+    let prover = MockProver::run(4, &circuit, vec![vec![out]]).unwrap();
+    prover.verify().is_ok()
+}
+
+fn check_bitwise<const OP: u8>(a: u64, b: u64, out: u64) {
+    assert!(verify_bitwise::<OP>(
+        Fp::from(a),
+        Fp::from(b),
+        Fp::from(out)
+    ));
+    assert!(!verify_bitwise::<OP>(
+        Fp::from(a),
+        Fp::from(b),
+        Fp::from(1 - out)
+    ));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0, 0)]
+#[case(0, 1)]
+#[case(1, 0)]
+#[case(1, 1)]
+// proves the truth tables of xor, and, or
+fn gadget_bitwise(#[case] a: u64, #[case] b: u64) {
+    check_bitwise::<XOR>(a, b, a ^ b);
+    check_bitwise::<AND>(a, b, a & b);
+    check_bitwise::<OR>(a, b, a | b);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2, 0)]
+#[case(1, 3)]
+// non boolean inputs are rejected even when the output matches the formula
+fn gadget_bitwise_non_boolean(#[case] a: u64, #[case] b: u64) {
+    let (a, b) = (Fp::from(a), Fp::from(b));
+
+    assert!(!verify_bitwise::<XOR>(a, b, a + b - a * b.double()));
+    assert!(!verify_bitwise::<AND>(a, b, a * b));
+    assert!(!verify_bitwise::<OR>(a, b, a + b - a * b));
+}
+
+// constrains the inputs to be sorted as 8 bits numbers
+#[derive(Clone, Default)]
+struct Sorted<const STRICT: bool>;
+
+impl<const STRICT: bool> Gadget<Fp> for Sorted<STRICT> {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        chip.assert_sorted(layouter.namespace(|| "sorted"), &inputs, 8, STRICT)
+    }
+}
+
+fn verify_sorted<const STRICT: bool>(nums: &[u64]) -> bool {
+    let circuit =
+        GadgetCircuit::<Fp, Sorted<STRICT>>::new(nums.iter().map(|&x| Fp::from(x)).collect());
+    let prover = MockProver::run(8, &circuit, vec![vec![]]).unwrap();
+    prover.verify().is_ok()
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(&[1, 5, 9, 255], true, true)]
+#[case(&[0, 3], true, true)]
+#[case(&[1, 9, 5], false, false)]
+#[case(&[3, 0], false, false)]
+#[case(&[1, 5, 5, 9], false, true)]
+#[case(&[7, 7], false, true)]
+#[case(&[1, 256], false, false)]
+// ascending order, equal neighbours only pass the non strict check,
+// numbers beyond the bit size are rejected
+fn gadget_assert_sorted(#[case] nums: &[u64], #[case] strict: bool, #[case] non_strict: bool) {
+    assert_eq!(verify_sorted::<true>(nums), strict);
+    assert_eq!(verify_sorted::<false>(nums), non_strict);
+}
+
+// constrains the inputs to be strictly increasing 8 bits numbers
+#[derive(Clone, Default)]
+struct Increasing;
+
+impl Gadget<Fp> for Increasing {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        chip.assert_strictly_increasing(layouter.namespace(|| "increasing"), &inputs, 8)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(&[0, 1, 2, 200], true)]
+#[case(&[4, 9], true)]
+#[case(&[0, 1, 1, 2], false)]
+#[case(&[3, 3], false)]
+#[case(&[9, 4, 1], false)]
+// log indices must grow by at least one at every step
+fn gadget_assert_strictly_increasing(#[case] nums: &[u64], #[case] increasing: bool) {
+    let circuit = GadgetCircuit::<Fp, Increasing>::new(nums.iter().map(|&x| Fp::from(x)).collect());
+    let prover = MockProver::run(8, &circuit, vec![vec![]]).unwrap();
+    assert_eq!(prover.verify().is_ok(), increasing);
+}
+
+// exposes a * b mod MODULUS for 8 bits inputs
+#[derive(Clone, Default)]
+struct MulMod<const MODULUS: u64>;
+
+impl<const MODULUS: u64> Gadget<Fp> for MulMod<MODULUS> {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let (a, b) = (inputs[0].clone(), inputs[1].clone());
+        let modulus = Fp::from(MODULUS);
+        let r = chip.mul_mod_small(layouter.namespace(|| "a * b mod m"), a, b, modulus, 8)?;
+        chip.expose_public(layouter.namespace(|| "expose r"), r, 0)
+    }
+}
+
+fn verify_mul_mod<const MODULUS: u64>(a: u64, b: u64, r: u64) -> bool {
+    let circuit = GadgetCircuit::<Fp, MulMod<MODULUS>>::new(vec![Fp::from(a), Fp::from(b)]);
+    let prover = MockProver::run(7, &circuit, vec![vec![Fp::from(r)]]).unwrap();
+    prover.verify().is_ok()
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(7, 9)]
+#[case(0, 200)]
+#[case(255, 255)]
+#[case(12, 1)]
+// proves a * b mod m against the native product
+fn gadget_mul_mod_small(#[case] a: u64, #[case] b: u64) {
+    assert!(verify_mul_mod::<10>(a, b, a * b % 10));
+    assert!(verify_mul_mod::<13>(a, b, a * b % 13));
+    assert!(verify_mul_mod::<256>(a, b, a * b % 256));
+
+    // any other remainder is rejected
+    assert!(!verify_mul_mod::<13>(a, b, a * b % 13 + 13));
+    assert!(!verify_mul_mod::<13>(a, b, (a * b + 1) % 13));
+}
+
+// exposes both outputs of swapping the last two inputs on the first one
+#[derive(Clone, Default)]
+struct Swap;
+
+impl Gadget<Fp> for Swap {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let (cond, a, b) = (inputs[0].clone(), inputs[1].clone(), inputs[2].clone());
+        let (first, second) = chip.conditional_swap(layouter.namespace(|| "swap"), cond, a, b)?;
+        chip.expose_public(layouter.namespace(|| "expose first"), first, 0)?;
+        chip.expose_public(layouter.namespace(|| "expose second"), second, 1)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0, true)]
+#[case(1, true)]
+#[case(2, false)]
+// (a, b) is kept for cond = 0 and swapped for cond = 1
+fn gadget_conditional_swap(#[case] cond: u64, #[case] boolean: bool) {
+    let (a, b) = (Fp::from(3), Fp::from(8));
+    let circuit = GadgetCircuit::<Fp, Swap>::new(vec![Fp::from(cond), a, b]);
+    let run = |public: Vec<Fp>| {
+        MockProver::run(5, &circuit, vec![public])
+            .unwrap()
+            .verify()
+            .is_ok()
+    };
+
+    let swapped = cond == 1;
+    assert_eq!(run(vec![a, b]), boolean && !swapped);
+    assert_eq!(run(vec![b, a]), boolean && swapped);
+}
+
+// checks the input is a nonnegative fixed-point number with 4 integer and
+// 4 fractional bits
+#[derive(Clone, Default)]
+struct NonNeg;
+
+impl Gadget<Fp> for NonNeg {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        chip.assert_nonneg(layouter.namespace(|| "a >= 0"), inputs[0].clone(), 4, 4)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3.25, true)]
+#[case(0.0, true)]
+#[case(7.9375, true)]
+#[case(8.0, false)]
+#[case(-3.25, false)]
+// fixed-point values with 4 integer bits, the sign bit included
+fn gadget_assert_nonneg(#[case] x: f64, #[case] nonneg: bool) {
+    let scaled = (x * 16.0) as i64;
+    let encoded = match scaled < 0 {
+        // negative values wrap around the modulus
+        true => -Fp::from(scaled.unsigned_abs()),
+        false => Fp::from(scaled as u64),
+    };
+    let circuit = GadgetCircuit::<Fp, NonNeg>::new(vec![encoded]);
+    let prover = MockProver::run(5, &circuit, vec![vec![]]).unwrap();
+    assert_eq!(prover.verify().is_ok(), nonneg);
+
+    // the two's complement of a negative value has the sign bit set
+    if scaled < 0 {
+        let twos = Fp::from((256 + scaled) as u64);
+        let circuit = GadgetCircuit::<Fp, NonNeg>::new(vec![twos]);
+        let prover = MockProver::run(5, &circuit, vec![vec![]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+// checks the input is nonnegative with nothing but the sign bit, or with no
+// integer bit at all when `NO_SIGN` is set
+#[derive(Clone, Default)]
+struct SignBitOnly<const NO_SIGN: bool>;
+
+impl<const NO_SIGN: bool> Gadget<Fp> for SignBitOnly<NO_SIGN> {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let (int_bits, frac_bits) = if NO_SIGN { (0, 1) } else { (1, 0) };
+        chip.assert_nonneg(
+            layouter.namespace(|| "a >= 0"),
+            inputs[0].clone(),
+            int_bits,
+            frac_bits,
+        )
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0, true)]
+#[case(1, false)]
+#[case(2, false)]
+// a single bit is the sign bit, only zero is nonnegative
+fn gadget_assert_nonneg_sign_bit(#[case] x: u64, #[case] nonneg: bool) {
+    let circuit = GadgetCircuit::<Fp, SignBitOnly<false>>::new(vec![Fp::from(x)]);
+    let prover = MockProver::run(5, &circuit, vec![vec![]]).unwrap();
+    assert_eq!(prover.verify().is_ok(), nonneg);
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "assert_nonneg needs int_bits >= 1 to hold the sign bit, got 0")]
+// without integer bits there is no sign bit to check
+fn gadget_assert_nonneg_no_sign_bit() {
+    let circuit = GadgetCircuit::<Fp, SignBitOnly<true>>::new(vec![Fp::zero()]);
+    MockProver::run(5, &circuit, vec![vec![]]).unwrap();
+}
+
+// checks the first input is within 0.25 of the second, both fixed-point
+// numbers with 4 fractional bits
+#[derive(Clone, Default)]
+struct ApproxEqual;
+
+impl Gadget<Fp> for ApproxEqual {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let epsilon = Fp::from((0.25 * 16.0) as u64);
+        chip.assert_approx_equal(
+            layouter.namespace(|| "a ~ target"),
+            inputs[0].clone(),
+            inputs[1].clone(),
+            epsilon,
+            8,
+        )
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3.25, 3.25, true)]
+#[case(3.5, 3.25, true)]
+#[case(3.0, 3.25, true)]
+#[case(0.0, 0.25, true)]
+#[case(3.5625, 3.25, false)]
+#[case(2.9375, 3.25, false)]
+#[case(0.0, 7.5, false)]
+// a tolerance of 0.25 holds on both sides, its bounds included
+fn gadget_approx_equal(#[case] a: f64, #[case] target: f64, #[case] within: bool) {
+    let encode = |x: f64| Fp::from((x * 16.0) as u64);
+    let circuit = GadgetCircuit::<Fp, ApproxEqual>::new(vec![encode(a), encode(target)]);
+    let prover = MockProver::run(6, &circuit, vec![vec![]]).unwrap();
+    assert_eq!(prover.verify().is_ok(), within);
+}
+
+// checks the first input is within `EPS` of the second, with the 8 bits
+// range checks of `ApproxEqual`
+#[derive(Clone, Default)]
+struct WideApproxEqual<const EPS: u64>;
+
+impl<const EPS: u64> Gadget<Fp> for WideApproxEqual<EPS> {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        chip.assert_approx_equal(
+            layouter.namespace(|| "a ~ target"),
+            inputs[0].clone(),
+            inputs[1].clone(),
+            Fp::from(EPS),
+            8,
+        )
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(200, 73, true)]
+#[case(73, 200, true)]
+#[case(201, 73, false)]
+#[case(72, 200, false)]
+// the largest epsilon for 8 bits still holds at |a - target| == epsilon,
+// where twice epsilon is range checked
+fn gadget_approx_equal_wide(#[case] a: u64, #[case] target: u64, #[case] within: bool) {
+    let circuit =
+        GadgetCircuit::<Fp, WideApproxEqual<127>>::new(vec![Fp::from(a), Fp::from(target)]);
+    let prover = MockProver::run(6, &circuit, vec![vec![]]).unwrap();
+    assert_eq!(prover.verify().is_ok(), within);
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "epsilon must be below 2^7, twice it is range checked to 8 bits")]
+// an epsilon that would reject values inside its own tolerance
+fn gadget_approx_equal_epsilon_too_large() {
+    let circuit = GadgetCircuit::<Fp, WideApproxEqual<128>>::new(vec![Fp::from(128), Fp::zero()]);
+    MockProver::run(6, &circuit, vec![vec![]]).unwrap();
+}
+
+// exposes the parity of a 4 bits index
+#[derive(Clone, Default)]
+struct Parity;
+
+impl Gadget<Fp> for Parity {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let bits = chip.unpack_flags(layouter.namespace(|| "bits"), inputs[0].clone(), 4)?;
+        let parity = chip.index_parity(layouter.namespace(|| "parity"), &bits)?;
+        chip.expose_public(layouter.namespace(|| "expose"), parity, 0)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0)]
+#[case(6)]
+#[case(7)]
+#[case(15)]
+// even indices are left children, odd ones right children
+fn gadget_index_parity(#[case] index: u64) {
+    let circuit = GadgetCircuit::<Fp, Parity>::new(vec![Fp::from(index)]);
+    let parity = Fp::from(index % 2);
+
+    let prover = MockProver::run(5, &circuit, vec![vec![parity]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(5, &circuit, vec![vec![Fp::one() - parity]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+// exposes a == b
+#[derive(Clone, Default)]
+struct IsEqual;
+
+impl Gadget<Fp> for IsEqual {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let eq = chip.is_equal(
+            layouter.namespace(|| "a == b"),
+            inputs[0].clone(),
+            inputs[1].clone(),
+        )?;
+        chip.expose_public(layouter.namespace(|| "expose"), eq, 0)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(7, 7)]
+#[case(7, 8)]
+#[case(0, 0)]
+#[case(0, 1)]
+// is_equal is 1 for equal inputs and 0 otherwise, never the other way
+fn gadget_is_equal(#[case] a: u64, #[case] b: u64) {
+    let circuit = GadgetCircuit::<Fp, IsEqual>::new(vec![Fp::from(a), Fp::from(b)]);
+    let eq = Fp::from((a == b) as u64);
+
+    let prover = MockProver::run(4, &circuit, vec![vec![eq]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(4, &circuit, vec![vec![Fp::one() - eq]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+// exposes the 5 flags packed in the input
+#[derive(Clone, Default)]
+struct Flags;
+
+impl Gadget<Fp> for Flags {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let flags = chip.unpack_flags(layouter.namespace(|| "unpack"), inputs[0].clone(), 5)?;
+        for (row, flag) in flags.into_iter().enumerate() {
+            chip.expose_public(layouter.namespace(|| "expose flag"), flag, row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case([true, false, true, true, false])]
+#[case([false; 5])]
+#[case([true; 5])]
+// packs 5 flags into one value and unpacks them
+fn gadget_unpack_flags(#[case] flags: [bool; 5]) {
+    let packed = flags
+        .iter()
+        .enumerate()
+        .fold(0u64, |acc, (i, f)| acc | (*f as u64) << i);
+    let public: Vec<Fp> = flags.iter().map(|f| Fp::from(*f as u64)).collect();
+
+    let circuit = GadgetCircuit::<Fp, Flags>::new(vec![Fp::from(packed)]);
+    let prover = MockProver::run(5, &circuit, vec![public.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // flipping any flag is rejected
+    for i in 0..5 {
+        let mut public = public.clone();
+        public[i] = Fp::one() - public[i];
+        let f_prover = MockProver::run(5, &circuit, vec![public]).unwrap();
+        assert!(f_prover.verify().is_err());
+    }
+
+    // a sixth flag does not fit
+    let circuit = GadgetCircuit::<Fp, Flags>::new(vec![Fp::from(packed | 1 << 5)]);
+    let f_prover = MockProver::run(5, &circuit, vec![public]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+// exposes the inner product of the two halves of the inputs
+#[derive(Clone, Default)]
+struct InnerProduct;
+
+impl Gadget<Fp> for InnerProduct {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let (a, b) = inputs.split_at(inputs.len() / 2);
+        let out = chip.inner_product(layouter.namespace(|| "a . b"), a, b)?;
+        chip.expose_public(layouter.namespace(|| "expose out"), out, 0)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(&[1, 2, 3], &[4, 5, 6], 32)]
+#[case(&[7], &[6], 42)]
+#[case(&[0, 9, 3, 1], &[5, 0, 2, 10], 16)]
+// proves a . b for private a and b
+fn gadget_inner_product(#[case] a: &[u64], #[case] b: &[u64], #[case] out: u64) {
+    let inputs = a.iter().chain(b).map(|&x| Fp::from(x)).collect();
+    let circuit = GadgetCircuit::<Fp, InnerProduct>::new(inputs);
+
+    let prover = MockProver::run(5, &circuit, vec![vec![Fp::from(out)]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let f_prover = MockProver::run(5, &circuit, vec![vec![Fp::from(out + 1)]]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(4)]
+#[case(10)]
+#[case(20)]
+// the params estimate doubles with every k, past the size of the struct
+fn debug_params_memory(#[case] k: u32) {
+    use circuit_samples::debug::{check_params_memory, params_bytes, CircuitError};
+    use halo2_proofs::pasta::EqAffine;
+
+    let fixed = std::mem::size_of::<halo2_proofs::poly::commitment::Params<EqAffine>>();
+    let bytes = params_bytes::<EqAffine>(k).unwrap();
+    assert_eq!(
+        params_bytes::<EqAffine>(k + 1).unwrap() - fixed,
+        2 * (bytes - fixed)
+    );
+    assert_eq!(
+        bytes - fixed,
+        (1 << k) * 2 * std::mem::size_of::<EqAffine>()
+    );
+
+    assert_eq!(check_params_memory::<EqAffine>(k, bytes).unwrap(), bytes);
+    assert!(matches!(
+        check_params_memory::<EqAffine>(k + 1, bytes),
+        Err(CircuitError::ParamsTooLarge { .. })
+    ));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(usize::BITS - 4)]
+#[case(usize::BITS - 1)]
+#[case(usize::BITS)]
+#[case(u32::MAX)]
+// a k too large for the estimate to fit in a usize is over any budget,
+// instead of panicking or wrapping around to a small estimate
+fn debug_params_memory_overflow(#[case] k: u32) {
+    use circuit_samples::debug::{check_params_memory, params_bytes, CircuitError};
+    use halo2_proofs::pasta::EqAffine;
+
+    assert_eq!(params_bytes::<EqAffine>(k), None);
+    assert!(matches!(
+        check_params_memory::<EqAffine>(k, usize::MAX - 1),
+        Err(CircuitError::ParamsTooLarge {
+            bytes: usize::MAX,
+            ..
+        })
+    ));
+}
+
+fn assert_min_k<C: halo2_proofs::plonk::Circuit<Fp>>(circuit: &C, public: Vec<Vec<Fp>>) {
+    use circuit_samples::debug::{check_k, min_k};
+
+    let k = min_k(circuit, &public).unwrap();
+    assert_eq!(check_k(circuit, &public, k + 3).unwrap(), k);
+
+    let prover = MockProver::run(k, circuit, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let smaller = MockProver::run(k - 1, circuit, public);
+    assert!(smaller.map_or(true, |p| p.verify().is_err()));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2)]
+#[case(6)]
+// the reported k is the smallest one the mock prover accepts
+fn debug_min_k(#[case] n: usize) {
+    use ff::Field;
+
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Fp> = (0..n).map(|_| <Fp as Field>::random(&mut rng)).collect();
+    let outputs = circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs.clone());
+
+    let circuit = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs.clone());
+    assert_min_k(&circuit, vec![outputs.clone()]);
+
+    let circuit = poseidon_circuit::PoseidonCircuit::<Fp, P128Pow5T3, 3, 4>::new(inputs);
+    assert_min_k(&circuit, vec![outputs]);
+
+    let circuit = arth_circuit::DemoCircuit1::new(Fp::from(3));
+    assert_min_k(&circuit, vec![vec![Fp::from(5), Fp::from(150)]]);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1)]
+#[case(4)]
+// the merkle statement holds under both floor planners, V1 packing its
+// regions in no more rows than the simple planner
+fn debug_floor_planner(#[case] n: usize) {
+    use circuit_samples::debug::{min_k, used_rows};
+    use halo2_proofs::circuit::{floor_planner::V1, SimpleFloorPlanner};
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(4),
+    );
+    let public = vec![path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>()];
+
+    let simple = WithPlanner::<_, SimpleFloorPlanner>::new(circuit.clone());
+    let v1 = WithPlanner::<_, V1>::new(circuit);
+    let k = min_k(&simple, &public).unwrap();
+    let prover = MockProver::run(k, &simple, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let k = min_k(&v1, &public).unwrap();
+    let prover = MockProver::run(k, &v1, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    assert!(used_rows(&v1, &public).unwrap() <= used_rows(&simple, &public).unwrap());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2, 1, true)]
+#[case(4, 2, true)]
+#[case(3, 2, false)]
+// the same leaf is included in a tree of depth 4 and a tree of depth 2
+fn merkle_dual_tree(#[case] n1: usize, #[case] n2: usize, #[case] shared: bool) {
+    use circuit_samples::circuits::dual_merkle_circuit::DualMerkleCircuit;
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+    use ff::Field;
+    use utils::poseidon_hash::gen_merkle_path_with_leaf;
+
+    let degree = degree_for(
+        MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(false)
+            + MerklePathCircuit::<Fp, P128Pow5T3, 2, 3, 2>::rows_for(false),
+    );
+
+    let mut rng = rand::thread_rng();
+    let leaf: Vec<Fp> = (0..2).map(|_| <Fp as Field>::random(&mut rng)).collect();
+    let other = match shared {
+        true => leaf.clone(),
+        false => vec![leaf[0] + Fp::one(), leaf[1]],
+    };
+    let first = gen_merkle_path_with_leaf::<Fp, P128Pow5T3, 3>(leaf, n1, 4);
+    let second = gen_merkle_path_with_leaf::<Fp, P128Pow5T3, 3>(other, n2, 2);
+
+    let circuit = DualMerkleCircuit::<Fp, P128Pow5T3, 4, 2, 3, 2>::new(
+        MerklePathCircuit::new(
+            first.get_left_value(),
+            first.get_right_value(),
+            copy_flags(n1, 4),
+        ),
+        MerklePathCircuit::new(
+            second.get_left_value(),
+            second.get_right_value(),
+            copy_flags(n2, 2),
+        ),
+    );
+    let public = |path: &utils::poseidon_hash::MerklePath<Fp>| {
+        path.get_leaf()
+            .into_iter()
+            .chain(path.get_index())
+            .chain(path.get_root())
+            .collect::<Vec<_>>()
+    };
+    let public = vec![public(&first), public(&second)];
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+
+    if shared {
+        assert_eq!(prover.verify(), Ok(()));
+    } else {
+        assert!(prover.verify().is_err());
+    }
+
+    // the keys come from the circuit without witnesses
+    let empty = halo2_proofs::plonk::Circuit::without_witnesses(&circuit);
+    assert_eq!(prove_and_verify(degree, circuit, &empty, &public), shared);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1)]
+#[case(2)]
+#[case(4)]
+// bool based construction matches the field based path
+fn merkle_from_bools(#[case] n: usize) {
+    use circuit_samples::circuits::merkle_circuit::{copy_flags, MerkleInstance};
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let bits = path
+        .get_index()
+        .into_iter()
+        .map(|b| b == Fp::one())
+        .collect::<Vec<_>>();
+
+    let field_public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    let instance = MerkleInstance::<Fp, 4>::from_bools(path.get_leaf(), &bits, path.get_root());
+    assert_eq!(instance.to_vec(), field_public);
+
+    let from_fields = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        copy_flags(n, 4),
+    );
+    let from_bools = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::from_siblings(
+        path.get_leaf(),
+        &path.get_siblings(),
+        &bits[..n],
+    );
+    for circuit in [from_fields, from_bools.clone()] {
+        let prover = MockProver::run(degree, &circuit, vec![instance.to_vec()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // only the bits of the real layers matter
+    let short = MerkleInstance::<Fp, 4>::from_bools(path.get_leaf(), &bits[..n], path.get_root());
+    let prover = MockProver::run(degree, &from_bools, vec![short.to_vec()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(6)]
+#[case(13)]
+// nullifier = poseidon(secret, index) for the private leaf poseidon(secret) of a path
+fn merkle_nullifier(#[case] index: u64) {
+    use circuit_samples::circuits::nullifier_circuit::NullifierCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::{hash, merkle_root};
+
+    let nullifier = |secret: &[Fp], index: u64| {
+        hash::<Fp, P128Pow5T3, 3>(
+            secret
+                .iter()
+                .copied()
+                .chain([Fp::from(index), Fp::zero()])
+                .collect(),
+        )
+    };
+
+    let secret = vec![Fp::from(7), Fp::from(11)];
+    let leaf = hash::<Fp, P128Pow5T3, 3>(secret.clone());
+    let siblings = (0..4)
+        .map(|i| vec![Fp::from(700 + i), Fp::from(800 + i)])
+        .collect::<Vec<_>>();
+    let bits = (0..4).map(|i| (index >> i) & 1 == 1).collect::<Vec<_>>();
+    let root = merkle_root::<Fp, P128Pow5T3, 3>(leaf, &siblings, &bits);
+    let public = |nullifier: Vec<Fp>| vec![[root.clone(), nullifier].concat()];
+
+    let circuit =
+        NullifierCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(secret.clone(), index, siblings.clone());
+
+    // the same secret and index always give the same nullifier
+    assert_eq!(nullifier(&secret, index), nullifier(&secret, index));
+
+    let degree = min_k(&circuit, &public(nullifier(&secret, index))).unwrap();
+    let prover = MockProver::run(degree, &circuit, public(nullifier(&secret, index))).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // a nullifier for another index or secret is rejected
+    let other_index = nullifier(&secret, index + 1);
+    let prover = MockProver::run(degree, &circuit, public(other_index)).unwrap();
+    assert!(prover.verify().is_err());
+
+    let other_secret = nullifier(&[Fp::from(7), Fp::from(12)], index);
+    let prover = MockProver::run(degree, &circuit, public(other_secret)).unwrap();
+    assert!(prover.verify().is_err());
+
+    // a bit above the depth selects no layer, setting it would give the same
+    // leaf a second nullifier
+    let alias = index + (1 << 4);
+    let circuit = NullifierCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(secret.clone(), alias, siblings);
+    let prover = MockProver::run(degree, &circuit, public(nullifier(&secret, alias))).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[should_panic(expected = "the tree has at least one layer")]
+// a nullifier needs a tree to be a member of
+fn merkle_nullifier_no_layer() {
+    use circuit_samples::circuits::nullifier_circuit::NullifierCircuit;
+
+    let circuit = NullifierCircuit::<Fp, P128Pow5T3, 0, 3, 2>::new(vec![Fp::one(); 2], 0, vec![]);
+    let _ = MockProver::run(10, &circuit, vec![vec![Fp::zero(); 4]]);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0, 2, true)]
+#[case(0, 1, false)]
+#[case(1, 2, false)]
+#[case(1, 3, true)]
+// leaf 1 of subtree 0 and leaf 2 of subtree `k` share their ancestor at `depth`
+fn merkle_shared_subtree(#[case] k: usize, #[case] depth: usize, #[case] shared: bool) {
+    use circuit_samples::circuits::merkle_circuit::MerkleInstance;
+    use circuit_samples::circuits::subtree_circuit::SubtreeCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+
+    let h = |l: &[Fp], r: &[Fp]| hash::<Fp, P128Pow5T3, 3>([l, r].concat());
+    let leaf = |i: u64| vec![Fp::from(i), Fp::from(i + 100)];
+
+    // two subtrees of depth 2 below the siblings [s, t] of layer 2 and 3
+    let subtrees = (0..2u64)
+        .map(|k| (0..4).map(|i| leaf(4 * k + i)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let root = |l: &[Vec<Fp>]| h(&h(&l[0], &l[1]), &h(&l[2], &l[3]));
+    let t = leaf(99);
+
+    // leaf 1 of subtree 0 or leaf 2 of subtree 1, the subtrees are left and right of layer 2
+    let path = |k: usize, i: usize| {
+        let l = &subtrees[k];
+        let (siblings, bits) = match i {
+            1 => (vec![l[0].clone(), h(&l[2], &l[3])], [true, false]),
+            _ => (vec![l[3].clone(), h(&l[0], &l[1])], [false, true]),
+        };
+        let other = root(&subtrees[1 - k]);
+        let siblings = [siblings, vec![other, t.clone()]].concat();
+        let bits = [&bits[..], &[k == 1, false]].concat();
+        let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::from_siblings(
+            l[i].clone(),
+            &siblings,
+            &bits,
+        );
+        let top = h(&root(&subtrees[0]), &root(&subtrees[1]));
+        let instance = MerkleInstance::<Fp, 4>::from_bools(l[i].clone(), &bits, h(&top, &t));
+        (circuit, instance.to_vec())
+    };
+
+    let (first, first_public) = path(0, 1);
+    let (second, second_public) = path(k, 2);
+    assert_eq!(first_public[2 + 4..], second_public[2 + 4..]);
+
+    let circuit = SubtreeCircuit::new(first, second, depth);
+    let public = vec![first_public, second_public];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), shared);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(&[3, 9, 27], true)]
+#[case(&[9, 9, 9], true)]
+#[case(&[3, 27, 81], false)]
+// a leaf of the tree passes only if it is one of the candidates
+fn merkle_whitelist(#[case] candidates: &[u64], #[case] listed: bool) {
+    use circuit_samples::circuits::whitelist_circuit::WhitelistCircuit;
+    use circuit_samples::debug::min_k;
+    use utils::p128_pow5_t2::P128Pow5T2;
+    use utils::poseidon_hash::gen_merkle_path_with_leaf;
+
+    let leaf = Fp::from(9);
+    let path = gen_merkle_path_with_leaf::<Fp, P128Pow5T2, 3>(vec![leaf], 4, 4);
+    let siblings = path.get_siblings().into_iter().flatten().collect();
+    let public = vec![candidates
+        .iter()
+        .map(|&c| Fp::from(c))
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>()];
+
+    let circuit = WhitelistCircuit::<Fp, P128Pow5T2, 4, 3, 3>::new(leaf, siblings);
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+    assert_eq!(prover.verify().is_ok(), listed);
+
+    // a listed leaf must still be in the tree
+    let mut wrong = public[0].clone();
+    wrong[3 + 4] += Fp::one();
+    let prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(5, 5, true)]
+#[case(0, 0, true)]
+#[case(5, 4, false)]
+#[case(5, 13, false)]
+#[case(5, 21, false)]
+// inserting at index, with the roots claimed for an insertion at claimed
+fn merkle_insert(#[case] index: u64, #[case] claimed: u64, #[case] valid: bool) {
+    use circuit_samples::circuits::insert_circuit::InsertCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::merkle_root;
+
+    let leaf = vec![Fp::from(7), Fp::from(11)];
+    let siblings = (0..4)
+        .map(|i| vec![Fp::from(100 + i), Fp::from(200 + i)])
+        .collect::<Vec<_>>();
+    let bits = (0..4).map(|i| (index >> i) & 1 == 1).collect::<Vec<_>>();
+
+    let old_root = merkle_root::<Fp, P128Pow5T3, 3>(vec![Fp::zero(); 2], &siblings, &bits);
+    let new_root = merkle_root::<Fp, P128Pow5T3, 3>(leaf.clone(), &siblings, &bits);
+    let public = vec![[vec![Fp::from(claimed)], old_root, new_root].concat()];
+
+    let circuit = InsertCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(leaf, siblings);
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), valid);
+}
+
+#[cfg(test)]
+#[rstest]
+// ordering (current, sibling) in circuit reaches the root of the left/right path
+fn merkle_sibling_path() {
+    use circuit_samples::circuits::sibling_path_circuit::SiblingPathCircuit;
+    use circuit_samples::debug::min_k;
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(4, 4);
+    let public = vec![path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>()];
+
+    let layers = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::from_layers(
+        path.get_left_value(),
+        path.get_right_value(),
+    );
+    let siblings = SiblingPathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(path.get_siblings());
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let prover = MockProver::run(degree, &layers, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let degree = min_k(&siblings, &public).unwrap();
+    let prover = MockProver::run(degree, &siblings, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // flipping a direction hashes the pair in the wrong order
+    let mut wrong = public[0].clone();
+    wrong[2 + 1] = Fp::one() - wrong[2 + 1];
+    let f_prover = MockProver::run(degree, &siblings, vec![wrong]).unwrap();
+    assert!(f_prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1)]
+#[case(3)]
+#[case(4)]
+// skipping the duplicated root layer exposes the same root
+fn merkle_without_root_layer(#[case] n: usize) {
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        copy_flags(n, 4),
+    );
+    let mut public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+
+    for circuit in [circuit.clone(), circuit.without_root_layer()] {
+        let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // the witnessed root is still bound to the path
+    let mut left = path.get_left_value();
+    let mut right = path.get_right_value();
+    left[n][0] = left[n][0] + Value::known(Fp::one());
+    right[n][0] = right[n][0] + Value::known(Fp::one());
+    let tampered = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(left, right, copy_flags(n, 4))
+        .without_root_layer();
+    // the public root follows the tampered witness
+    public[2 + 4] += Fp::one();
+    let prover = MockProver::run(degree, &tampered, vec![public]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(feature = "trace")]
+#[rstest]
+#[case(1, false)]
+#[case(3, true)]
+// an oversized k is reported as a warning event, not on stderr
+fn trace_oversized_k(#[case] extra: u32, #[case] warned: bool) {
+    use circuit_samples::debug::check_k;
+    use utils::trace::Recorder;
+
+    let circuit = arth_circuit::DemoCircuit1::new(Fp::from(3));
+    let public = vec![vec![Fp::from(5), Fp::from(150)]];
+    let (k, _) = Recorder::capture(|| check_k(&circuit, &public, 0).unwrap());
+    let (min, lines) = Recorder::capture(|| check_k(&circuit, &public, k + extra).unwrap());
+    assert_eq!(min, k);
+    let warning = format!("k={} min={k}", k + extra);
+    assert_eq!(
+        lines.iter().any(|l| l.contains(&warning)),
+        warned,
+        "{lines:?}"
+    );
+}
+
+#[cfg(feature = "trace")]
+#[rstest]
+#[case(2)]
+#[case(4)]
+// the trace names the poseidon and merkle regions with their row ranges
+fn trace_region_rows(#[case] n: usize) {
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+    use utils::trace::Recorder;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        copy_flags(n, 4),
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+
+    let (prover, lines) = Recorder::capture(|| MockProver::run(degree, &circuit, vec![public]));
+    assert_eq!(prover.unwrap().verify(), Ok(()));
+
+    let rounds =
+        <P128Pow5T3 as Spec<Fp, 3>>::full_rounds() + <P128Pow5T3 as Spec<Fp, 3>>::partial_rounds();
+    let permutation = format!("region=\"permutation\" rows=0..{} lanes=1", rounds + 1);
+    let load_path = format!("region=\"load path\" rows=0..15 hashed={n}");
+
+    assert!(lines.iter().any(|l| l.contains(&permutation)), "{lines:?}");
+    assert!(lines.iter().any(|l| l.contains(&load_path)), "{lines:?}");
+}
+
+#[cfg(feature = "trace")]
+#[rstest]
+#[case(1, false)]
+#[case(4, false)]
+#[case(2, true)]
+// the predicted permutations match the permutation regions in the trace
+fn trace_permutations(#[case] n: usize, #[case] skip_root_layer: bool) {
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+    use poseidon_circuit::PoseidonCircuit;
+    use utils::trace::Recorder;
+
+    let count = |lines: Vec<String>| {
+        lines
+            .iter()
+            .filter(|l| l.contains("region=\"permutation\""))
+            .count()
+    };
+
+    let inputs = vec![Fp::one(); 2 * n];
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs.clone());
+    let public = vec![circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(inputs)];
+    let degree = degree_for(PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(2 * n));
+    let (_, lines) = Recorder::capture(|| MockProver::run(degree, &circuit, public));
+    assert_eq!(circuit.permutations(), n);
+    assert_eq!(count(lines), circuit.permutations());
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let mut circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        copy_flags(n, 4),
+    );
+    if skip_root_layer {
+        circuit = circuit.without_root_layer();
+    }
+    let public = vec![path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>()];
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let (_, lines) = Recorder::capture(|| MockProver::run(degree, &circuit, public));
+    assert_eq!(count(lines), circuit.permutations());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2)]
+#[case(6)]
+// the row formulas match the rows the floor planner actually uses
+fn debug_rows_for(#[case] n: usize) {
+    use circuit_samples::circuits::merkle_circuit::copy_flags;
+    use circuit_samples::debug::used_rows;
+    use poseidon_circuit::PoseidonCircuit;
+
+    // a single permutation
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(vec![Fp::one(); 2]);
+    assert_eq!(
+        used_rows(&circuit, &[vec![]]).unwrap(),
+        PoseidonChip::<Fp, 3>::INITIATE_ROWS
+            + PoseidonChip::<Fp, 3>::LOAD_ROWS
+            + PoseidonChip::<Fp, 3>::permutation_rows(
+                <P128Pow5T3 as Spec<Fp, 3>>::full_rounds(),
+                <P128Pow5T3 as Spec<Fp, 3>>::partial_rounds(),
+                1
+            )
+    );
+
+    let inputs = vec![Fp::one(); n];
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T3, 3>::new(inputs.clone());
+    assert_eq!(
+        used_rows(&circuit, &[vec![]]).unwrap(),
+        PoseidonCircuit::<Fp, P128Pow5T3, 3>::rows_for(n)
+    );
+    let circuit = PoseidonCircuit::<Fp, P128Pow5T3, 3, 4>::new(inputs);
+    assert_eq!(
+        used_rows(&circuit, &[vec![]]).unwrap(),
+        PoseidonCircuit::<Fp, P128Pow5T3, 3, 4>::rows_for(n)
+    );
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(2, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        copy_flags(2, 4),
+    );
+    let public = [vec![Fp::zero(); 8]];
+    assert_eq!(
+        used_rows(&circuit, &public).unwrap(),
+        MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(false)
+    );
+    assert_eq!(
+        used_rows(&circuit.without_root_layer(), &public).unwrap(),
+        MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(true)
+    );
+}
+
+// P128Pow5T3 with the first two rows of its mds swapped
+#[cfg(feature = "debug-check")]
+#[derive(Debug, Clone, Default)]
+struct SwappedMds;
+
+#[cfg(feature = "debug-check")]
+impl Spec<Fp, 3> for SwappedMds {
+    fn full_rounds() -> usize {
+        P128Pow5T3::full_rounds()
+    }
+
+    fn partial_rounds() -> usize {
+        P128Pow5T3::partial_rounds()
+    }
+
+    fn mds() -> [[Fp; 3]; 3] {
+        let mut mds = P128Pow5T3::mds();
+        mds.swap(0, 1);
+        mds
+    }
+
+    fn arks() -> Vec<[Fp; 3]> {
+        P128Pow5T3::arks()
+    }
+
+    fn capacity() -> u128 {
+        P128Pow5T3::capacity()
+    }
+
+    fn pad() -> Vec<Fp> {
+        P128Pow5T3::pad()
+    }
+
+    fn element_size() -> usize {
+        P128Pow5T3::element_size()
+    }
+}
+
+// hashes two values with a chip whose mds has its first two rows swapped,
+// checked against the rounds of P128Pow5T3
+#[cfg(feature = "debug-check")]
+#[derive(Clone, Default)]
+struct SwappedMdsCircuit([Value<Fp>; 2]);
+
+#[cfg(feature = "debug-check")]
+impl halo2_proofs::plonk::Circuit<Fp> for SwappedMdsCircuit {
+    type Config = PoseidonArthConfig<Fp, 3>;
+
+    type FloorPlanner = halo2_proofs::circuit::SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<Fp>) -> Self::Config {
+        let state = [(); 3].map(|_| meta.advice_column());
+        let arc = [(); 3].map(|_| meta.fixed_column());
+        let output = meta.instance_column();
+
+        PoseidonChip::configure::<SwappedMds>(
+            meta,
+            state,
+            output,
+            arc,
+            SwappedMds::mds(),
+            SwappedMds::arks(),
+            SwappedMds::capacity(),
+        )
+        .unwrap()
+        .check_against::<P128Pow5T3>()
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = PoseidonChip::new(config);
+        let state = chip.hash_array::<P128Pow5T3, 2>(&mut layouter, self.0)?;
+        chip.expose_public(&mut layouter, state, P128Pow5T3::element_size())
+    }
+}
+
+#[cfg(feature = "debug-check")]
+#[rstest]
+#[should_panic(expected = "round 0 differs from the native permutation")]
+// a chip configured with a wrong mds stops synthesis at its first round
+fn debug_check_wrong_mds() {
+    let circuit = SwappedMdsCircuit([Fp::from(3), Fp::from(5)].map(Value::known));
+    let _ = MockProver::run(8, &circuit, vec![vec![Fp::zero(); 2]]);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case([1, 2, 3, 4], [9, 8, 3, 7], true)]
+#[case([1, 2, 3, 4], [4, 4, 4, 4], true)]
+#[case([1, 2, 3, 4], [5, 6, 7, 8], false)]
+#[case([0, 0, 0, 0], [1, 1, 1, 1], false)]
+// two committed sets of 4 values intersect iff some pair matches
+fn function_intersection(#[case] a: [u64; 4], #[case] b: [u64; 4], #[case] overlap: bool) {
+    use circuit_samples::circuits::intersection_circuit::IntersectionCircuit;
+    use circuit_samples::debug::min_k;
+
+    let (a, b) = (a.map(Fp::from), b.map(Fp::from));
+    let circuit = IntersectionCircuit::<Fp, P128Pow5T3, 4, 3>::new(a, b);
+    let public = [
+        circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(a.to_vec()),
+        circuit_samples::native::poseidon::hash::<Fp, P128Pow5T3, 3>(b.to_vec()),
+    ]
+    .concat();
+    let degree = min_k(&circuit, std::slice::from_ref(&public)).unwrap();
+
+    let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+    assert_eq!(prover.verify().is_ok(), overlap);
+
+    // the commitments bind the sets
+    let mut wrong = public;
+    wrong[0] += Fp::one();
+    let prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 5, 3, 5, true)]
+#[case(3, 5, 4, 5, false)]
+#[case(3, 5, 3, 6, false)]
+// the prf output of (key, x) is reproduced by the same pair only
+fn function_prf(
+    #[case] key: u64,
+    #[case] x: u64,
+    #[case] other_key: u64,
+    #[case] other_x: u64,
+    #[case] same: bool,
+) {
+    use circuit_samples::circuits::prf_circuit::PrfCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::permutation;
+
+    let capacity = Fp::from_u128(P128Pow5T3::capacity());
+    let prf = |key: u64, x: u64| {
+        permutation::<Fp, P128Pow5T3, 3>([Fp::from(key), Fp::from(x), capacity])[..2].to_vec()
+    };
+    let output = prf(key, x);
+    assert_eq!(prf(other_key, other_x) == output, same);
+
+    let public = vec![[vec![Fp::from(x)], output].concat()];
+    let circuit = PrfCircuit::<Fp, P128Pow5T3, 3>::new(Fp::from(key));
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // the output of one pair only verifies for the other if they are the same
+    let other = PrfCircuit::<Fp, P128Pow5T3, 3>::new(Fp::from(other_key));
+    let mut claimed = public[0].clone();
+    claimed[0] = Fp::from(other_x);
+    let prover = MockProver::run(degree, &other, vec![claimed]).unwrap();
+    assert_eq!(prover.verify().is_ok(), same);
+}
+
+// a stand-in leaf hash mapping [x0, x1] to [x0^5 + x1, x0 * x1]
+#[derive(Clone)]
+struct MockLeafHash;
+
+impl circuit_samples::chips::hash_instruction::HashInstruction<Fp> for MockLeafHash {
+    fn hash_native(preimage: &[Fp]) -> Vec<Fp> {
+        vec![
+            ff::Field::pow(&preimage[0], [5]) + preimage[1],
+            preimage[0] * preimage[1],
+        ]
+    }
+
+    fn hash(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        preimage: &[Number<Fp>],
+    ) -> Result<Vec<Number<Fp>>, Error> {
+        let fifth = chip.pow(layouter.namespace(|| "x0^5"), preimage[0].clone(), 5)?;
+        let first = chip.add(layouter.namespace(|| "add"), fifth, preimage[1].clone())?;
+        let second = chip.mul(
+            layouter.namespace(|| "mul"),
+            preimage[0].clone(),
+            preimage[1].clone(),
+        )?;
+        Ok(vec![first, second])
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(6, true)]
+#[case(9, true)]
+#[case(6, false)]
+// the leaf comes from the mock hash and the inner nodes from poseidon
+fn merkle_hybrid_leaf(#[case] index: u64, #[case] mock_leaf: bool) {
+    use circuit_samples::chips::hash_instruction::HashInstruction;
+    use circuit_samples::circuits::hybrid_leaf_circuit::HybridLeafCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::{hash, merkle_root};
+
+    let preimage = vec![Fp::from(3), Fp::from(5)];
+    let siblings = (0..4)
+        .map(|i| vec![Fp::from(300 + i), Fp::from(400 + i)])
+        .collect::<Vec<_>>();
+    let bits = (0..4).map(|i| (index >> i) & 1 == 1).collect::<Vec<_>>();
+
+    // a root over the poseidon digest of the preimage must not pass
+    let leaf = if mock_leaf {
+        MockLeafHash::hash_native(&preimage)
+    } else {
+        hash::<Fp, P128Pow5T3, 3>(preimage.clone())
+    };
+    let root = merkle_root::<Fp, P128Pow5T3, 3>(leaf, &siblings, &bits);
+    let public = vec![[bits.iter().map(|&b| Fp::from(b as u64)).collect(), root].concat()];
+
+    let circuit =
+        HybridLeafCircuit::<Fp, P128Pow5T3, MockLeafHash, 4, 3, 2>::new(preimage, siblings);
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), mock_leaf);
+}
+
+// proves the public inputs of a path with MerklePathCircuit of max depth 8
+fn prove_merkle_path(path: &utils::poseidon_hash::MerklePath<Fp>, m: usize) -> Vec<Fp> {
+    use circuit_samples::circuits::merkle_circuit::MerklePathCircuit;
+
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 8, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(m),
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 8, 3, 2>::rows_for(
+        false,
+    ));
+    let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    public
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, 8)]
+#[case(5, 8)]
+#[case(8, 8)]
+// a path written to a fixture loads back to the same path and proof
+fn merkle_fixture_roundtrip(#[case] n: usize, #[case] m: usize) {
+    use utils::poseidon_hash::MerklePath;
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, m);
+    let file = std::env::temp_dir().join(format!("merkle_fixture_{n}_{m}.json"));
+    path.to_fixture(&file).unwrap();
+    let loaded = MerklePath::<Fp>::from_fixture(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+
+    assert_eq!(loaded.get_layers(), path.get_layers());
+    assert_eq!(loaded.get_index(), path.get_index());
+    assert_eq!(prove_merkle_path(&loaded, m), prove_merkle_path(&path, m));
+}
+
+#[cfg(test)]
+#[rstest]
+// the golden path shipped in tests/fixtures still proves
+fn merkle_fixture_golden() {
+    use utils::poseidon_hash::MerklePath;
+
+    let file = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/merkle_path_t3.json"
+    );
+    let path = MerklePath::<Fp>::from_fixture(file).unwrap();
+    assert_eq!(path.get_left().len(), 4 + 1);
+    prove_merkle_path(&path, 8);
+
+    // a root replaced by the leaf no longer matches the layers
+    let json = std::fs::read_to_string(file).unwrap();
+    let line = |key: &str| json.lines().find(|l| l.contains(key)).unwrap();
+    let tampered = json.replacen(
+        line("\"root\""),
+        &line("\"leaf\"").replace("leaf", "root"),
+        1,
+    );
+    let copy = std::env::temp_dir().join("merkle_fixture_tampered.json");
+    std::fs::write(&copy, tampered).unwrap();
+    let err = MerklePath::<Fp>::from_fixture(&copy).err().unwrap();
+    std::fs::remove_file(&copy).unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[cfg(test)]
+#[rstest]
+#[ignore]
+// regenerate the golden path with `cargo test merkle_fixture_write -- --ignored`
+fn merkle_fixture_write() {
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1954);
+    let path =
+        circuit_samples::native::merkle::gen_merkle_path::<Fp, P128Pow5T3, 3>(&mut rng, 4, 8);
+    let file = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/merkle_path_t3.json"
+    );
+    path.to_fixture(file).unwrap();
+}
+
+// exposes the number of differing bits of two 16 bit values
+#[derive(Clone, Default)]
+struct BitDifference;
+
+impl Gadget<Fp> for BitDifference {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let count = chip.bit_difference_count(
+            layouter.namespace(|| "distance"),
+            inputs[0].clone(),
+            inputs[1].clone(),
+            16,
+        )?;
+        chip.expose_public(layouter.namespace(|| "expose"), count, 0)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0, 0)]
+#[case(0b1011, 0b0110)]
+#[case(0xffff, 0)]
+#[case(0x1234, 0xabcd)]
+// the distance is the popcount of a XOR b, and off by one is rejected
+fn gadget_bit_difference_count(#[case] a: u64, #[case] b: u64) {
+    let circuit = GadgetCircuit::<Fp, BitDifference>::new(vec![Fp::from(a), Fp::from(b)]);
+    let count = Fp::from((a ^ b).count_ones() as u64);
+
+    let prover = MockProver::run(8, &circuit, vec![vec![count]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(8, &circuit, vec![vec![count + Fp::one()]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // inputs wider than the bits are rejected
+    let wide = GadgetCircuit::<Fp, BitDifference>::new(vec![Fp::from(a + (1 << 16)), Fp::from(b)]);
+    let prover = MockProver::run(8, &wide, vec![vec![count + Fp::one()]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(35, 3, true)]
+#[case(30, 3, true)]
+#[case(39, 3, true)]
+#[case(40, 3, false)]
+#[case(29, 3, false)]
+#[case(0, 0, true)]
+// the value is in the public [endpoints[index], endpoints[index + 1]) of a
+// tree of sorted endpoints 0, 10, .., 70
+fn merkle_range_tree(#[case] value: u64, #[case] index: usize, #[case] valid: bool) {
+    use circuit_samples::circuits::range_tree_circuit::RangeTreeCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::{hash, merkle_root};
+    use utils::p128_pow5_t2::P128Pow5T2;
+    use utils::poseidon_hash::tree_siblings;
+
+    let leaves = (0..8).map(|i| vec![Fp::from(10 * i)]).collect::<Vec<_>>();
+    let siblings = [index, index + 1].map(|i| tree_siblings::<Fp, P128Pow5T2, 3>(&leaves, i));
+    let bits = (0..3).map(|i| (index >> i) & 1 == 1).collect::<Vec<_>>();
+    let root = merkle_root::<Fp, P128Pow5T2, 3>(leaves[index].clone(), &siblings[0], &bits);
+    let commitment = hash::<Fp, P128Pow5T2, 3>(vec![Fp::from(value)]);
+    let public = |lo: usize, hi: usize| {
+        vec![[
+            commitment.clone(),
+            leaves[lo].clone(),
+            leaves[hi].clone(),
+            root.clone(),
+        ]
+        .concat()]
+    };
+
+    let circuit = RangeTreeCircuit::<Fp, P128Pow5T2, 3, 3>::new(
+        Fp::from(value),
+        [leaves[index][0], leaves[index + 1][0]],
+        index as u64,
+        siblings.clone().map(|s| s.into_iter().flatten().collect()),
+        8,
+    );
+    let degree = min_k(&circuit, &public(index, index + 1)).unwrap();
+    let prover = MockProver::run(degree, &circuit, public(index, index + 1)).unwrap();
+    assert_eq!(prover.verify().is_ok(), valid);
+
+    // the proof is for the interval it claims, not the next one
+    let prover = MockProver::run(degree, &circuit, public(index + 1, index + 2)).unwrap();
+    assert!(prover.verify().is_err());
+
+    // endpoints that are not adjacent leaves are rejected
+    if index + 2 < leaves.len() {
+        let far = tree_siblings::<Fp, P128Pow5T2, 3>(&leaves, index + 2);
+        let circuit = RangeTreeCircuit::<Fp, P128Pow5T2, 3, 3>::new(
+            Fp::from(value),
+            [leaves[index][0], leaves[index + 2][0]],
+            index as u64,
+            [siblings[0].clone(), far].map(|s| s.into_iter().flatten().collect()),
+            8,
+        );
+        let prover = MockProver::run(degree, &circuit, public(index, index + 2)).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(all(test, feature = "parallel"))]
+#[rstest]
+// merkle proofs made on the thread pool verify and equal the serial ones for
+// the same seeds
+fn full_merkle_parallel() {
+    use circuit_samples::prover::{prove, prove_parallel};
+    use circuit_samples::verifier::verify_batch;
+    use halo2_proofs::pasta::EqAffine;
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+    use halo2_proofs::poly::commitment::Params;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let empty = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::empty();
+    let params: Params<EqAffine> = Params::new(degree);
+    let vk = keygen_vk(&params, &empty).unwrap();
+    let pk = keygen_pk(&params, vk, &empty).unwrap();
+
+    let statements = [1, 2, 3, 4]
+        .into_iter()
+        .map(|n| {
+            let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+            let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+                path.get_left_value(),
+                path.get_right_value(),
+                path.get_copy_value(4),
+            );
+            let public = path
+                .get_leaf()
+                .into_iter()
+                .chain(path.get_index())
+                .chain(path.get_root())
+                .collect::<Vec<_>>();
+            (circuit, vec![public])
+        })
+        .collect::<Vec<_>>();
+
+    let seed = |i: usize| StdRng::seed_from_u64(i as u64);
+    let parallel = prove_parallel(&params, &pk, statements.clone(), seed).unwrap();
+    let serial = statements
+        .iter()
+        .enumerate()
+        .map(|(i, (circuit, public))| prove(&params, &pk, circuit.clone(), public, seed(i)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(parallel, serial);
+
+    let proofs = parallel
+        .into_iter()
+        .zip(statements)
+        .map(|(proof, (_, public))| (proof, public))
+        .collect::<Vec<_>>();
+    assert_eq!(verify_batch(&params, pk.get_vk(), &proofs), Ok(()));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2)]
+#[case(4)]
+#[case(8)]
+// the digest of every whole prefix matches the native hash, and no other
+// length or digest passes
+fn function_prefix_hash(#[case] k: usize) {
+    use circuit_samples::circuits::prefix_circuit::PrefixHashCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+
+    let x: [Fp; 8] = std::array::from_fn(|i| Fp::from(3 * i as u64 + 1));
+    let prefix = hash::<Fp, P128Pow5T3, 3>(x[..k].to_vec());
+    let whole = hash::<Fp, P128Pow5T3, 3>(x.to_vec());
+    let public = vec![[vec![Fp::from(k as u64)], prefix.clone(), whole.clone()].concat()];
+
+    let circuit = PrefixHashCircuit::<Fp, P128Pow5T3, 8, 3>::new(x);
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // the digest of a longer prefix
+    let other = (k % 8) + 2;
+    let claimed = [
+        vec![Fp::from(k as u64)],
+        hash::<Fp, P128Pow5T3, 3>(x[..other].to_vec()),
+        whole.clone(),
+    ];
+    let prover = MockProver::run(degree, &circuit, vec![claimed.concat()]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // a length ending inside an element, or past the inputs
+    for bad in [k - 1, 10] {
+        let claimed = [vec![Fp::from(bad as u64)], prefix.clone(), whole.clone()];
+        let prover = MockProver::run(degree, &circuit, vec![claimed.concat()]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+// constrains the input to be a root of unity of order ORDER
+#[derive(Clone, Default)]
+struct RootOfUnity<const ORDER: u64>;
+
+impl<const ORDER: u64> Gadget<Fp> for RootOfUnity<ORDER> {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        chip.assert_root_of_unity(layouter.namespace(|| "root"), inputs[0].clone(), ORDER)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(8, 8, true)]
+#[case(8, 4, true)]
+#[case(8, 16, false)]
+#[case(1, 1, true)]
+#[case(1, 2, false)]
+// a generator of the 2-adic subgroup of size `size` has order `order` only if
+// size divides order
+fn gadget_root_of_unity(#[case] order: u64, #[case] size: u64, #[case] valid: bool) {
+    use ff::Field;
+
+    // Fp::ROOT_OF_UNITY generates the subgroup of size 2^Fp::S
+    let generator = Fp::ROOT_OF_UNITY.pow([(1u64 << Fp::S) / size]);
+    let input = vec![generator];
+    let prover = match order {
+        8 => MockProver::run(
+            5,
+            &GadgetCircuit::<Fp, RootOfUnity<8>>::new(input),
+            vec![vec![]],
+        ),
+        _ => MockProver::run(
+            5,
+            &GadgetCircuit::<Fp, RootOfUnity<1>>::new(input),
+            vec![vec![]],
+        ),
+    };
+    assert_eq!(prover.unwrap().verify().is_ok(), valid);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(4, vec![2])]
+#[case(2, vec![2])]
+#[case(3, vec![1, 2, 3])]
+#[case(4, vec![0])]
+// the nodes on the path at the chosen depths follow the root, a node of the
+// other child at that depth is rejected
+fn merkle_path_nodes(#[case] n: usize, #[case] depths: Vec<usize>) {
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(4),
+    )
+    .with_nodes(depths.clone());
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .chain(depths.iter().flat_map(|&d| path.get_node(d)))
+        .collect::<Vec<_>>();
+    assert_eq!(path.get_node(0), path.get_leaf());
+
+    let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // the sibling of the first exposed node, unless the layer only holds
+    // the duplicated root
+    let d = depths[0];
+    if d < n {
+        let sibling = match path.get_index()[d] == Fp::one() {
+            true => path.get_left()[d].clone(),
+            false => path.get_right()[d].clone(),
+        };
+        let mut wrong = public.clone();
+        // after the leaf, the index and the root
+        wrong[8..10].copy_from_slice(&sibling);
+        let prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    let mut wrong = public;
+    *wrong.last_mut().unwrap() += Fp::one();
+    let prover = MockProver::run(degree, &circuit, vec![wrong]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+// exposes the index of the set element of a one-hot vector
+#[derive(Clone, Default)]
+struct OneHot;
+
+impl Gadget<Fp> for OneHot {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let index = chip.assert_one_hot(layouter.namespace(|| "one hot"), &inputs)?;
+        chip.expose_public(layouter.namespace(|| "expose"), index, 0)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(&[0, 0, 1, 0], Some(2))]
+#[case(&[1, 0, 0, 0], Some(0))]
+#[case(&[0, 0, 0, 1], Some(3))]
+#[case(&[0, 0, 0, 0], None)]
+#[case(&[0, 1, 1, 0], None)]
+#[case(&[2, 0, 0, 0], None)]
+// exactly one boolean element is set, and its index is exposed
+fn gadget_one_hot(#[case] v: &[u64], #[case] index: Option<u64>) {
+    let circuit = GadgetCircuit::<Fp, OneHot>::new(v.iter().map(|&b| Fp::from(b)).collect());
+
+    // any claimed index fails for an invalid vector
+    let claimed = index.unwrap_or(1);
+    let prover = MockProver::run(6, &circuit, vec![vec![Fp::from(claimed)]]).unwrap();
+    assert_eq!(prover.verify().is_ok(), index.is_some());
+
+    let prover = MockProver::run(6, &circuit, vec![vec![Fp::from(claimed + 1)]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(5, 0, true)]
+#[case(11, 0, true)]
+#[case(5, 1, false)]
+#[case(5, 2, false)]
+// the note opens its commitment in the tree only with the right blinding
+// and value
+fn merkle_note(#[case] index: u64, #[case] tamper: usize, #[case] valid: bool) {
+    use circuit_samples::circuits::note_circuit::NoteCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::{hash, merkle_root};
+
+    let (value, blinding) = (Fp::from(1000), Fp::from(0xb11d));
+    let siblings = (0..4)
+        .map(|i| vec![Fp::from(500 + i), Fp::from(600 + i)])
+        .collect::<Vec<_>>();
+    let bits = (0..4).map(|i| (index >> i) & 1 == 1).collect::<Vec<_>>();
+    let commitment = hash::<Fp, P128Pow5T3, 3>(vec![value, blinding]);
+    let public = vec![merkle_root::<Fp, P128Pow5T3, 3>(
+        commitment, &siblings, &bits,
+    )];
+
+    let (value, blinding) = match tamper {
+        1 => (value, blinding + Fp::one()),
+        2 => (value + Fp::one(), blinding),
+        _ => (value, blinding),
+    };
+    let circuit = NoteCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(value, blinding, index, siblings);
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+    assert_eq!(prover.verify().is_ok(), valid);
+
+    // and with the real prover
+    let empty = halo2_proofs::plonk::Circuit::without_witnesses(&circuit);
+    assert_eq!(prove_and_verify(degree, circuit, &empty, &public), valid);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(false, true)]
+#[case(false, false)]
+#[case(true, true)]
+#[case(true, false)]
+// only the secret key opens the public key, on its own or registered in a tree
+fn merkle_owner(#[case] registered: bool, #[case] valid: bool) {
+    use circuit_samples::circuits::owner_circuit::OwnerCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::{hash, merkle_root};
+
+    let secret = vec![Fp::from(0x5ec7e7), Fp::from(42)];
+    let key = hash::<Fp, P128Pow5T3, 3>(secret.clone());
+    let witness = match valid {
+        true => secret,
+        false => vec![Fp::from(0x5ec7e7), Fp::from(43)],
+    };
+
+    let verified = if registered {
+        let index = 6;
+        let siblings = (0..4)
+            .map(|i| vec![Fp::from(700 + i), Fp::from(800 + i)])
+            .collect::<Vec<_>>();
+        let bits = (0..4).map(|i| (index >> i) & 1 == 1).collect::<Vec<_>>();
+        let root = merkle_root::<Fp, P128Pow5T3, 3>(key.clone(), &siblings, &bits);
+        let public = vec![[key, root].concat()];
+        let circuit = OwnerCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(witness, index, siblings);
+        let degree = min_k(&circuit, &public).unwrap();
+        MockProver::run(degree, &circuit, public).unwrap().verify()
+    } else {
+        let public = vec![key];
+        let circuit = OwnerCircuit::<Fp, P128Pow5T3, 0, 3, 2>::new(witness, 0, vec![]);
+        let degree = min_k(&circuit, &public).unwrap();
+        MockProver::run(degree, &circuit, public).unwrap().verify()
+    };
+    assert_eq!(verified.is_ok(), valid);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(0, true)]
+#[case(1, false)]
+#[case(2, false)]
+#[case(3, false)]
+// the roots of two halves of a tree merge into the root of the whole tree,
+// and into nothing else
+fn merkle_merge(#[case] tamper: usize, #[case] valid: bool) {
+    use circuit_samples::circuits::merge_circuit::MergeCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::merkle::MerkleTree;
+
+    let leaves = (0..8u64)
+        .map(|i| vec![Fp::from(i), Fp::from(100 + i)])
+        .collect::<Vec<_>>();
+    let whole = MerkleTree::new::<P128Pow5T3, 3>(leaves.clone());
+    let left = MerkleTree::new::<P128Pow5T3, 3>(leaves[..4].to_vec()).root();
+    let right = MerkleTree::new::<P128Pow5T3, 3>(leaves[4..].to_vec()).root();
+
+    let mut public = vec![[left.clone(), right.clone(), whole.root()].concat()];
+    match tamper {
+        // swapped halves
+        1 => public[0][..4].rotate_left(2),
+        // another right subtree
+        2 => public[0][2] += Fp::one(),
+        // another merged root
+        3 => public[0][4] += Fp::one(),
+        _ => {}
+    }
+    let circuit = MergeCircuit::<Fp, P128Pow5T3, 3, 2>::new(left, right);
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), valid);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1)]
+#[case(4)]
+// the round constants of one permutation bound the distinct fixed cells of a
+// merkle path, while every permutation assigns them again
+fn debug_fixed_usage(#[case] n: usize) {
+    use circuit_samples::debug::fixed_usage;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(4),
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    let usage = fixed_usage(degree, &circuit, &[public]).unwrap();
+
+    let rounds = P128Pow5T3::full_rounds() + P128Pow5T3::partial_rounds();
+    let once = rounds * 3;
+    assert!(usage.distinct <= once, "{usage:?}");
+    assert_eq!(usage.assigned, circuit.permutations() * once, "{usage:?}");
+}
+
+// exposes max(a - b, 0) for 8 bit inputs
+#[derive(Clone, Default)]
+struct SubSaturating;
+
+impl Gadget<Fp> for SubSaturating {
+    fn synthesize(
+        chip: &ArthChip<Fp>,
+        mut layouter: impl Layouter<Fp>,
+        inputs: Vec<Number<Fp>>,
+    ) -> Result<(), Error> {
+        let diff = chip.sub_saturating(
+            layouter.namespace(|| "a - b"),
+            inputs[0].clone(),
+            inputs[1].clone(),
+            8,
+        )?;
+        chip.expose_public(layouter.namespace(|| "expose"), diff, 0)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(9, 4)]
+#[case(4, 4)]
+#[case(4, 9)]
+#[case(0, 255)]
+#[case(255, 0)]
+// the difference is clamped at zero instead of wrapping around the field
+fn gadget_sub_saturating(#[case] a: u64, #[case] b: u64) {
+    let circuit = GadgetCircuit::<Fp, SubSaturating>::new(vec![Fp::from(a), Fp::from(b)]);
+    let diff = Fp::from(a.saturating_sub(b));
+
+    let prover = MockProver::run(6, &circuit, vec![vec![diff]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // the wrapped difference is rejected
+    let wrapped = Fp::from(a) - Fp::from(b);
+    if wrapped != diff {
+        let prover = MockProver::run(6, &circuit, vec![vec![wrapped]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+    let prover = MockProver::run(6, &circuit, vec![vec![diff + Fp::one()]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2, 0)]
+#[case(2, 1)]
+#[case(2, 3)]
+#[case(4, 2)]
+// the index bits loaded by the merkle chip are copies of the instance, a
+// prover witnessing a flipped bit breaks the copy to the public index
+fn merkle_index_binding(#[case] n: usize, #[case] layer: usize) {
+    use halo2_proofs::dev::VerifyFailure;
+    use utils::tamper::tamper_instance;
+
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(4),
+    );
+    let public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+
+    let row = 2 + layer;
+    let flipped = Fp::one() - public[row];
+    let mut prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+    tamper_instance(&mut prover, &circuit, row, flipped).unwrap();
+
+    let failures = prover.verify().unwrap_err();
+    assert!(failures
+        .iter()
+        .any(|f| matches!(f, VerifyFailure::Permutation { .. })));
+    // layers above the real depth hold the root twice, so the flipped bit
+    // satisfies every gate and only the copy catches it
+    if layer >= n {
+        assert!(failures
+            .iter()
+            .all(|f| matches!(f, VerifyFailure::Permutation { .. })));
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, false)]
+#[case(4, false)]
+#[case(4, true)]
+// a pipeline of load, merkle path and expose steps accepts the public inputs
+// of MerklePathCircuit for a full path, and rejects a wrong root
+fn pipeline_merkle(#[case] n: usize, #[case] tamper: bool) {
+    use circuit_samples::circuits::pipeline::Pipeline;
+    use circuit_samples::debug::min_k;
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, n);
+    let mut public = path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .collect::<Vec<_>>();
+    // the same public inputs prove the path with the dedicated circuit
+    if n == 4 {
+        let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(
+            path.get_left_value(),
+            path.get_right_value(),
+            path.get_copy_value(n),
+        );
+        let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+            false,
+        ));
+        let prover = MockProver::run(degree, &circuit, vec![public.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    let pipeline = Pipeline::<Fp, P128Pow5T3, 3>::new()
+        .load_public(0)
+        .load_public(1)
+        .merkle_path(path.get_siblings(), 2)
+        .expose(2, 2 + n);
+    if tamper {
+        *public.last_mut().unwrap() += Fp::one();
+    }
+    let public = vec![public];
+    let degree = min_k(&pipeline, &public).unwrap();
+    let prover = MockProver::run(degree, &pipeline, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), !tamper);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(5, true)]
+#[case(5, false)]
+// a pipeline hashing private values into a leaf proves the root of the
+// tree holding that leaf
+fn pipeline_hashed_leaf(#[case] index: u64, #[case] valid: bool) {
+    use circuit_samples::circuits::pipeline::Pipeline;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::{hash, merkle_root};
+
+    let siblings = (0..4)
+        .map(|i| vec![Fp::from(500 + i), Fp::from(600 + i)])
+        .collect::<Vec<_>>();
+    let bits = (0..4).map(|i| (index >> i) & 1 == 1).collect::<Vec<_>>();
+    let leaf = hash::<Fp, P128Pow5T3, 3>(vec![Fp::from(7), Fp::from(11)]);
+    let root = merkle_root::<Fp, P128Pow5T3, 3>(leaf, &siblings, &bits);
+    let public = vec![[bits.iter().map(|&b| Fp::from(b as u64)).collect(), root].concat()];
+
+    let pipeline = Pipeline::<Fp, P128Pow5T3, 3>::new()
+        .load_private(Fp::from(7))
+        .load_private(Fp::from(if valid { 11 } else { 12 }))
+        .hash(2)
+        .merkle_path(siblings, 0)
+        .expose(2, 4);
+    let degree = min_k(&pipeline, &public).unwrap();
+    let prover = MockProver::run(degree, &pipeline, public.clone()).unwrap();
+    assert_eq!(prover.verify().is_ok(), valid);
+
+    let empty = halo2_proofs::plonk::Circuit::without_witnesses(&pipeline);
+    assert_eq!(prove_and_verify(degree, pipeline, &empty, &public), valid);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![("owner", 7), ("amount", 100), ("nonce", 1)])]
+#[case(vec![("x", 3), ("y", 3)])]
+// identical structs share a digest, another order of the fields or other
+// names for the same values do not
+fn function_hash_struct(#[case] fields: Vec<(&str, u64)>) {
+    use circuit_samples::native::poseidon::hash_struct;
+
+    let fields = fields
+        .into_iter()
+        .map(|(name, v)| (name, Fp::from(v)))
+        .collect::<Vec<_>>();
+    let digest = hash_struct::<Fp, P128Pow5T3, 3>(&fields);
+    assert_eq!(digest, hash_struct::<Fp, P128Pow5T3, 3>(&fields.clone()));
+
+    let mut reordered = fields.clone();
+    reordered.reverse();
+    assert_ne!(digest, hash_struct::<Fp, P128Pow5T3, 3>(&reordered));
+
+    // the same values in the same order under other names
+    let renamed = fields
+        .iter()
+        .map(|&(name, v)| (if name == fields[0].0 { "other" } else { name }, v))
+        .collect::<Vec<_>>();
+    assert_ne!(digest, hash_struct::<Fp, P128Pow5T3, 3>(&renamed));
+
+    // a name boundary moved between two fields
+    let (a, b) = (fields[0].0, fields[1].0);
+    let shifted = [
+        (&format!("{a}{}", &b[..1])[..], fields[0].1),
+        (&b[1..], fields[1].1),
+    ];
+    assert_ne!(
+        hash_struct::<Fp, P128Pow5T3, 3>(&fields[..2]),
+        hash_struct::<Fp, P128Pow5T3, 3>(&shifted)
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(3, 8, true)]
+#[case(3, 5, true)]
+#[case(4, 16, true)]
+#[case(1, 2, true)]
+#[case(3, 4, false)]
+#[case(3, 9, false)]
+#[case(2, 16, false)]
+// a path proves membership only if its length is the depth of a tree of the
+// public leaf count
+fn merkle_leaf_count(#[case] n: usize, #[case] count: u64, #[case] valid: bool) {
+    use circuit_samples::circuits::leaf_count_circuit::LeafCountCircuit;
+    use circuit_samples::debug::min_k;
+
+    assert_eq!(
+        LeafCountCircuit::<Fp, P128Pow5T3, 4, 3, 2>::depth_for(count) == n,
+        valid
+    );
+
+    let path = gen_merkle_path::<Fp, P128Pow5T3, 3>(n, 4);
+    let circuit = LeafCountCircuit::<Fp, P128Pow5T3, 4, 3, 2>::new(MerklePathCircuit::new(
+        path.get_left_value(),
+        path.get_right_value(),
+        path.get_copy_value(4),
+    ));
+    let public = vec![path
+        .get_leaf()
+        .into_iter()
+        .chain(path.get_index())
+        .chain(path.get_root())
+        .chain([Fp::from(count)])
+        .collect::<Vec<_>>()];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), valid);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(5, vec![0, 3, 4])]
+#[case(16, vec![1, 10, 15])]
+#[case(3, vec![0, 2])]
+// paths and instances taken from a tree prove membership of its leaves, and
+// not at another position
+fn merkle_from_tree(#[case] size: u64, #[case] indices: Vec<usize>) {
+    use circuit_samples::circuits::merkle_circuit::MerkleInstance;
+    use circuit_samples::native::merkle::MerkleTree;
+    use circuit_samples::native::poseidon::merkle_root;
+
+    let leaves = (0..size)
+        .map(|i| vec![Fp::from(100 + i), Fp::from(200 + i)])
+        .collect::<Vec<_>>();
+    let tree = MerkleTree::new::<P128Pow5T3, 3>(leaves);
+    let degree = degree_for(MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::rows_for(
+        false,
+    ));
+
+    for &i in &indices {
+        assert_eq!(
+            tree.leaf(i),
+            vec![Fp::from(100 + i as u64), Fp::from(200 + i as u64)]
+        );
+        assert_eq!(
+            merkle_root::<Fp, P128Pow5T3, 3>(tree.leaf(i), &tree.siblings(i), &tree.index_bits(i)),
+            tree.root()
+        );
+
+        let circuit = MerklePathCircuit::<Fp, P128Pow5T3, 4, 3, 2>::from_tree(&tree, i);
+        let public = MerkleInstance::<Fp, 4>::from_tree(&tree, i).to_vec();
+        let prover = MockProver::run(degree, &circuit, vec![public]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the leaf claimed at the position of another subtree, the leaf
+        // next to it shares the layers of the witness and would verify
+        let bits = tree.index_bits(i ^ 2);
+        let other = MerkleInstance::<Fp, 4>::from_bools(tree.leaf(i), &bits, tree.root()).to_vec();
+        let prover = MockProver::run(degree, &circuit, vec![other]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![1, 2, 3, 4, 5], None)]
+#[case(vec![1, 2, 3, 2, 5], Some((1, 3)))]
+#[case(vec![7, 7], Some((0, 1)))]
+// new_unique builds the same tree as new unless a leaf repeats, and then
+// names both positions
+fn native_merkle_unique_leaves(
+    #[case] values: Vec<u64>,
+    #[case] duplicate: Option<(usize, usize)>,
+) {
+    use circuit_samples::native::merkle::{Error, MerkleTree};
+
+    let leaves = values
+        .iter()
+        .map(|&v| vec![Fp::from(v), Fp::from(v + 100)])
+        .collect::<Vec<_>>();
+    let unique = MerkleTree::new_unique::<P128Pow5T3, 3>(leaves.clone());
+    match duplicate {
+        None => assert_eq!(unique, Ok(MerkleTree::new::<P128Pow5T3, 3>(leaves))),
+        Some((first, second)) => {
+            assert_eq!(unique, Err(Error::DuplicateLeaf { first, second }))
+        }
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![true, false, true, false], 25, true)]
+#[case(vec![true, false, true, false], 26, false)]
+#[case(vec![true, true, true, true], 36, true)]
+#[case(vec![false, false, false, false], 1, false)]
+#[case(vec![false, false, false, false], 0, true)]
+// the selected weights reach the public threshold exactly when the public
+// result says so
+fn function_threshold(#[case] votes: Vec<bool>, #[case] threshold: u64, #[case] met: bool) {
+    let weights = [5u64, 10, 20, 1];
+    let total: u64 = weights
+        .iter()
+        .zip(&votes)
+        .filter(|(_, &v)| v)
+        .map(|(w, _)| w)
+        .sum();
+    assert_eq!(total >= threshold, met);
+
+    let circuit = arth_circuit::ThresholdCircuit::<Fp>::new(votes, 8);
+    let public = |met: bool| {
+        weights
+            .iter()
+            .map(|&w| Fp::from(w))
+            .chain([Fp::from(threshold), Fp::from(met as u64)])
+            .collect::<Vec<_>>()
+    };
+    let prover = MockProver::run(8, &circuit, vec![public(met)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(8, &circuit, vec![public(!met)]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(4, true, 4)]
+#[case(8, true, 8)]
+#[case(4, false, 4)]
+#[case(4, true, 5)]
+// a nonce whose digest with the data has its low k bits zero meets the
+// public difficulty k, another nonce or a higher claimed difficulty does not
+fn function_proof_of_work(#[case] k: usize, #[case] solved: bool, #[case] claimed: u64) {
+    use circuit_samples::circuits::pow_circuit::ProofOfWorkCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+
+    let data = Fp::from(0xda7a);
+    let low_zero = |nonce: u64| {
+        let digest = hash::<Fp, P128Pow5T3, 3>(vec![Fp::from(nonce), data]);
+        let low = u32::from_le_bytes(digest[0].to_repr()[..4].try_into().unwrap());
+        low & ((1 << k) - 1) == 0
+    };
+    let nonce = (0..).find(|&n| low_zero(n) == solved).unwrap();
+
+    let circuit = ProofOfWorkCircuit::<Fp, P128Pow5T3, 3>::new(Fp::from(nonce), 1, k);
+    let public = vec![vec![data, Fp::from(claimed)]];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), solved && claimed == k as u64);
+}
+
+/// advice columns allocated by the configure of circuit `C`
+fn allocated_advice<C: halo2_proofs::plonk::Circuit<Fp>>() -> usize {
+    let mut meta = halo2_proofs::plonk::ConstraintSystem::<Fp>::default();
+    C::configure(&mut meta);
+    // the count is private, but printed by the pinned constraint system
+    let pinned = format!("{:?}", meta.pinned());
+    let count = pinned.split("num_advice_columns: ").nth(1).unwrap();
+    count[..count.find(',').unwrap()].parse().unwrap()
+}
+
+#[cfg(test)]
+#[rstest]
+// the column counts of the chips add up to the advice columns allocated by
+// circuits combining them
+fn debug_advice_columns() {
+    use circuit_samples::chips::merkle_chip::MerklePathChip;
+    use circuit_samples::circuits::leaf_count_circuit::LeafCountCircuit;
+    use circuit_samples::circuits::note_circuit::NoteCircuit;
+    use circuit_samples::circuits::nullifier_circuit::NullifierCircuit;
+
+    let poseidon = PoseidonChip::<Fp, 3>::num_advice_columns();
+    let merkle = MerklePathChip::<Fp, 2>::num_advice_columns();
+    let arth = ArthChip::<Fp>::num_advice_columns();
+    assert_eq!((poseidon, merkle, arth), (3, 4, 2));
+
+    assert_eq!(allocated_advice::<arth_circuit::PolyCircuit<Fp>>(), arth);
+    assert_eq!(
+        allocated_advice::<poseidon_circuit::PoseidonCircuit<Fp, P128Pow5T3, 3>>(),
+        poseidon
+    );
+    // one state per round laid out in a row
+    assert_eq!(
+        allocated_advice::<poseidon_circuit::PoseidonCircuit<Fp, P128Pow5T3, 3, 2>>(),
+        2 * poseidon
+    );
+    assert_eq!(
+        allocated_advice::<MerklePathCircuit<Fp, P128Pow5T3, 4, 3, 2>>(),
+        poseidon + merkle
+    );
+    assert_eq!(
+        allocated_advice::<LeafCountCircuit<Fp, P128Pow5T3, 4, 3, 2>>(),
+        poseidon + merkle + arth
+    );
+    assert_eq!(
+        allocated_advice::<NullifierCircuit<Fp, P128Pow5T3, 4, 3, 2>>(),
+        poseidon + arth
+    );
+    assert_eq!(
+        allocated_advice::<NoteCircuit<Fp, P128Pow5T3, 4, 3, 2>>(),
+        poseidon + arth
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![])]
+#[case(vec![3, 0, 7])]
+// witnesses round trip through values, an unknown one gives none back
+fn debug_values_round_trip(#[case] x: Vec<u64>) {
+    let x = x.into_iter().map(Fp::from).collect::<Vec<_>>();
+    let values = to_values(&x);
+    assert_eq!(unwrap_values(&values), Some(x));
+
+    let mut values = values;
+    values.push(Value::unknown());
+    assert_eq!(unwrap_values(&values), None);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case([5, 33, 61], true)]
+#[case([5, 30, 61], false)]
+#[case([0, 33, 61], false)]
+#[case([254, 1, 1], true)]
+// a set shares no element with the tree of sorted leaves 0, 10, .., 60, 255
+// only if every element falls strictly between two adjacent leaves
+fn merkle_disjoint(#[case] elements: [u64; 3], #[case] disjoint: bool) {
+    use circuit_samples::circuits::disjoint_circuit::DisjointCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::merkle::MerkleTree;
+    use circuit_samples::native::poseidon::hash;
+    use utils::p128_pow5_t2::P128Pow5T2;
+
+    let values = [0, 10, 20, 30, 40, 50, 60, 255];
+    let leaves = values
+        .iter()
+        .map(|&v| vec![Fp::from(v)])
+        .collect::<Vec<_>>();
+    let tree = MerkleTree::new::<P128Pow5T2, 3>(leaves);
+
+    // the last leaf not above the element, a shared element sits on its own
+    // leaf and has no gap around it
+    let indices = elements.map(|e| values.iter().rposition(|&v| v <= e).unwrap().min(6));
+    let path = |i: usize| tree.siblings(i).into_iter().flatten().collect::<Vec<_>>();
+    let circuit = DisjointCircuit::<Fp, P128Pow5T2, 3, 3, 3>::new(
+        elements.map(Fp::from),
+        indices.map(|i| [Fp::from(values[i]), Fp::from(values[i + 1])]),
+        indices.map(|i| i as u64),
+        indices.iter().map(|&i| [path(i), path(i + 1)]).collect(),
+        8,
+    );
+    let commitment = hash::<Fp, P128Pow5T2, 3>(elements.map(Fp::from).to_vec());
+    let public = vec![[commitment, tree.root()].concat()];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), disjoint);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case((1, 1), (0, 1), 10, 55)]
+#[case((1, 1), (0, 1), 0, 0)]
+#[case((1, 1), (0, 1), 1, 1)]
+#[case((1, 1), (2, 1), 6, 18)]
+#[case((1, 2), (0, 1), 8, 408)]
+// the sequence circuit proves the n-th term of a_{k+2} = p a_k + q a_{k+1}:
+// Fibonacci and Lucas for (1, 1), Pell for (1, 2), and rejects a wrong term
+fn function_sequence(
+    #[case] coeffs: (u64, u64),
+    #[case] start: (u64, u64),
+    #[case] n: usize,
+    #[case] an: u64,
+) {
+    let ((p, q), (a0, a1)) = (coeffs, start);
+    let mut terms = vec![a0, a1];
+    while terms.len() <= n {
+        let k = terms.len();
+        terms.push(p * terms[k - 2] + q * terms[k - 1]);
+    }
+    assert_eq!(terms[n], an);
+
+    let circuit = match (p, q) {
+        (1, 1) => arth_circuit::SequenceCircuit::new(n),
+        _ => arth_circuit::SequenceCircuit::with_coeffs(n, Fp::from(p), Fp::from(q)),
+    };
+    let public = |an: u64| vec![vec![Fp::from(a0), Fp::from(a1), Fp::from(an)]];
+    let prover = MockProver::run(7, &circuit, public(an)).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(7, &circuit, public(an + 1)).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(vec![1, 2])]
+#[case(vec![7, 8, 9, 10])]
+// the high and low limbs of the digest in their own instance columns add up
+// to the digest, swapped or shifted limbs are rejected
+fn full_poseidon_limbs(#[case] input: Vec<u64>) {
+    use circuit_samples::circuits::poseidon_circuit::PoseidonLimbCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+
+    let input = input.into_iter().map(Fp::from).collect::<Vec<_>>();
+    let public = PoseidonLimbCircuit::<Fp, P128Pow5T3, 3>::expected_public(&input);
+    let shift = Fp::from_u128(1 << 64).square();
+    for (i, d) in hash::<Fp, P128Pow5T3, 3>(input.clone()).iter().enumerate() {
+        assert_eq!(public[0][i] * shift + public[1][i], *d);
+    }
+
+    let circuit = PoseidonLimbCircuit::<Fp, P128Pow5T3, 3>::new(input);
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public.clone()).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let swapped = vec![public[1].clone(), public[0].clone()];
+    let prover = MockProver::run(degree, &circuit, swapped).unwrap();
+    assert!(prover.verify().is_err());
+
+    // the same digest with one unit moved from hi to lo
+    let mut shifted = public;
+    shifted[0][0] -= Fp::one();
+    shifted[1][0] += shift;
+    let prover = MockProver::run(degree, &circuit, shifted).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(2)]
+#[case(5)]
+#[case(8)]
+#[case(11)]
+// the batcher network sorts every 0/1 vector of small sizes, so any vector
+fn native_batcher_network(#[case] n: usize) {
+    use circuit_samples::chips::arth_chips::batcher_comparators;
+
+    for mask in 0u32..1 << n {
+        let mut x = (0..n).map(|i| (mask >> i) & 1).collect::<Vec<_>>();
+        for (i, j) in batcher_comparators(n) {
+            if x[i] > x[j] {
+                x.swap(i, j);
+            }
+        }
+        assert!(x.windows(2).all(|w| w[0] <= w[1]), "{mask:b} is not sorted");
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case(1, true)]
+#[case(6, true)]
+#[case(6, false)]
+// the sorting network outputs the committed random vector in order, an
+// unsorted output is rejected
+fn function_sort(#[case] n: usize, #[case] honest: bool) {
+    use circuit_samples::circuits::sort_circuit::SortCircuit;
+    use circuit_samples::debug::min_k;
+    use circuit_samples::native::poseidon::hash;
+    use rand::Rng;
+    use utils::p128_pow5_t2::P128Pow5T2;
+
+    let mut rng = rand::thread_rng();
+    let x = (0..n).map(|_| rng.gen_range(0..256u64)).collect::<Vec<_>>();
+    let mut y = x.clone();
+    y.sort();
+    if !honest {
+        y.swap(0, n - 1);
+        if y[0] == y[n - 1] {
+            y[0] += 1;
+        }
+    }
+
+    let x = x.into_iter().map(Fp::from).collect::<Vec<_>>();
+    let circuit = SortCircuit::<Fp, P128Pow5T2, 3>::new(x.clone(), 8);
+    let public = vec![[
+        hash::<Fp, P128Pow5T2, 3>(x),
+        y.into_iter().map(Fp::from).collect(),
+    ]
+    .concat()];
+    let degree = min_k(&circuit, &public).unwrap();
+    let prover = MockProver::run(degree, &circuit, public).unwrap();
+    assert_eq!(prover.verify().is_ok(), honest);
 }
@@ -0,0 +1,70 @@
+use std::marker::PhantomData;
+
+use circuit_samples::chips::arth_chips::{ArthChip, ArthConfig, Number, NumericInstructions};
+use circuit_samples::circuits::to_values;
+use ff::PrimeField;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+
+/// A statement built from `ArthChip` gadgets over some private inputs
+pub trait Gadget<F: PrimeField> {
+    fn synthesize(
+        chip: &ArthChip<F>,
+        layouter: impl Layouter<F>,
+        inputs: Vec<Number<F>>,
+    ) -> Result<(), Error>;
+}
+
+/// Loads each input as a private number and hands them to the gadget
+#[derive(Clone, Default)]
+pub struct GadgetCircuit<F: PrimeField, G: Gadget<F>> {
+    inputs: Vec<Value<F>>,
+    _marker: PhantomData<G>,
+}
+
+impl<F: PrimeField, G: Gadget<F>> GadgetCircuit<F, G> {
+    pub fn new(inputs: Vec<F>) -> Self {
+        GadgetCircuit {
+            inputs: to_values(&inputs),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, G: Gadget<F> + Default> Circuit<F> for GadgetCircuit<F, G> {
+    type Config = ArthConfig;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        GadgetCircuit {
+            inputs: vec![Value::unknown(); self.inputs.len()],
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let instance = meta.instance_column();
+        let coeff = [meta.fixed_column(), meta.fixed_column()];
+
+        ArthChip::configure(meta, advice, instance, coeff)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = ArthChip::new(config);
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|x| chip.load_private(layouter.namespace(|| "load input"), *x))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        G::synthesize(&chip, layouter.namespace(|| "gadget"), inputs)
+    }
+}
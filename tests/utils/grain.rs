@@ -0,0 +1,108 @@
+//! The Grain LFSR deriving Poseidon parameters, following the reference
+//! script `generate_parameters_grain.sage` for prime fields and the x^5 sbox.
+
+use std::collections::VecDeque;
+
+use ff::PrimeField;
+
+const STATE: usize = 80;
+
+pub struct Grain(VecDeque<bool>);
+
+impl Grain {
+    /// Seed the state with the field size, the width and the rounds, then
+    /// discard the first 160 bits.
+    pub fn new<F: PrimeField>(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut state = VecDeque::with_capacity(STATE);
+        let mut push = |len: usize, value: usize| {
+            state.extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+        };
+        // prime field, x^alpha sbox
+        push(2, 1);
+        push(4, 0);
+        push(12, F::NUM_BITS as usize);
+        push(12, width);
+        push(10, full_rounds);
+        push(10, partial_rounds);
+        push(30, (1 << 30) - 1);
+
+        let mut grain = Grain(state);
+        for _ in 0..160 {
+            grain.next_bit();
+        }
+        grain
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let s = &self.0;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.0.pop_front();
+        self.0.push_back(bit);
+        bit
+    }
+
+    /// Bits are drawn in pairs, the second one is kept if the first is set
+    fn next_filtered(&mut self) -> bool {
+        while !self.next_bit() {
+            self.next_bit();
+        }
+        self.next_bit()
+    }
+
+    /// `F::NUM_BITS` bits read from the most significant one
+    fn next_bits<F: PrimeField>(&mut self) -> Vec<bool> {
+        (0..F::NUM_BITS).map(|_| self.next_filtered()).collect()
+    }
+
+    /// A round constant, sampling again until the bits are below the modulus
+    pub fn next_field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            let mut repr = F::Repr::default();
+            let bytes = repr.as_mut();
+            for (i, bit) in self.next_bits::<F>().into_iter().rev().enumerate() {
+                bytes[i / 8] |= (bit as u8) << (i % 8);
+            }
+            if let Some(f) = F::from_repr(repr).into() {
+                return f;
+            }
+        }
+    }
+
+    /// An MDS sample, the bits reduced modulo the field
+    pub fn next_mds_element<F: PrimeField>(&mut self) -> F {
+        self.next_bits::<F>()
+            .into_iter()
+            .fold(F::ZERO, |acc, bit| acc.double() + F::from(bit as u64))
+    }
+}
+
+/// Round constants then the Cauchy MDS matrix `1 / (x_i + y_j)` from the
+/// first distinct `xs`, `ys` sampled. The reference script also checks the
+/// matrix against invariant subspace attacks and samples again if needed,
+/// which is not done here.
+pub fn generate<F: PrimeField, const W: usize>(
+    full_rounds: usize,
+    partial_rounds: usize,
+) -> (Vec<[F; W]>, [[F; W]; W]) {
+    let mut grain = Grain::new::<F>(W, full_rounds, partial_rounds);
+    let arks = (0..full_rounds + partial_rounds)
+        .map(|_| [(); W].map(|_| grain.next_field_element()))
+        .collect();
+
+    let (xs, ys) = loop {
+        let vals = (0..2 * W)
+            .map(|_| grain.next_mds_element::<F>())
+            .collect::<Vec<_>>();
+        let distinct = vals.iter().enumerate().all(|(i, v)| !vals[..i].contains(v));
+        if distinct {
+            break (vals[..W].to_vec(), vals[W..].to_vec());
+        }
+    };
+    let mut mds = [[F::ZERO; W]; W];
+    for (i, row) in mds.iter_mut().enumerate() {
+        for (j, m) in row.iter_mut().enumerate() {
+            *m = (xs[i] + ys[j]).invert().unwrap();
+        }
+    }
+    (arks, mds)
+}
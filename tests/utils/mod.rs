@@ -1,4 +1,12 @@
+#![allow(dead_code)]
 mod fp3;
+pub(super) mod gadget;
+pub(super) mod grain;
 pub(super) mod p128_pow5_t2;
 pub(super) mod p128_pow5_t3;
+pub(super) mod p128_pow5_t5;
 pub(super) mod poseidon_hash;
+pub(super) mod prover;
+pub(super) mod tamper;
+#[cfg(feature = "trace")]
+pub(super) mod trace;
@@ -0,0 +1,56 @@
+use std::sync::OnceLock;
+
+use circuit_samples::circuits::poseidon_circuit::utils::{Mds, Spec};
+use halo2_proofs::pasta::Fp;
+
+use super::grain;
+
+/// generated once, the hash and chips ask for them on every call
+fn parameters() -> &'static (Vec<[Fp; 5]>, Mds<Fp, 5>) {
+    static PARAMETERS: OnceLock<(Vec<[Fp; 5]>, Mds<Fp, 5>)> = OnceLock::new();
+    PARAMETERS.get_or_init(|| {
+        grain::generate::<Fp, 5>(P128Pow5T5::full_rounds(), P128Pow5T5::partial_rounds())
+    })
+}
+
+/// A width 5 spec absorbing elements of 4 values, for 4:1 compression and
+/// wide merkle leaves.
+///
+/// The parameters come from the Grain LFSR as for `P128Pow5T3`, with 60
+/// partial rounds. The MDS matrix is the first one sampled, see
+/// `grain::generate`.
+#[derive(Debug, Default, Clone)]
+pub struct P128Pow5T5;
+
+impl Spec<Fp, 5> for P128Pow5T5 {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        60
+    }
+
+    /// Generates `mds` corresponding to this specification.
+    fn mds() -> Mds<Fp, 5> {
+        parameters().1
+    }
+
+    /// Generates `ARKS` corresponding to this specification.
+    fn arks() -> Vec<[Fp; 5]> {
+        parameters().0.clone()
+    }
+
+    // Generate the capacity
+    fn capacity() -> u128 {
+        u128::pow(2, 65)
+    }
+
+    fn pad() -> Vec<Fp> {
+        vec![]
+    }
+
+    fn element_size() -> usize {
+        4
+    }
+}
@@ -0,0 +1,37 @@
+use circuit_samples::debug::CircuitError;
+use halo2_proofs::{
+    pasta::{EqAffine, Fp},
+    plonk::{keygen_pk, keygen_vk, verify_proof, Circuit, ProvingKey, SingleVerifier},
+    poly::commitment::Params,
+    transcript::{Blake2bRead, Challenge255},
+};
+use rand_core::OsRng;
+
+/// Generate keys from `empty`, prove `circuit` with the real prover and
+/// verify the proof against `public`, one vector per instance column.
+/// Returns false if either proving or verifying fails.
+pub fn prove_and_verify<C: Circuit<Fp>>(k: u32, circuit: C, empty: &C, public: &[Vec<Fp>]) -> bool {
+    let params: Params<EqAffine> = Params::new(k);
+    let vk = keygen_vk(&params, empty).expect("failed to generate vk");
+    let pk = keygen_pk(&params, vk, empty).expect("failed to generate pk");
+
+    let Ok(proof) = prove(&params, &pk, circuit, public) else {
+        return false;
+    };
+
+    let public = public.iter().map(|c| &c[..]).collect::<Vec<_>>();
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    let strategy = SingleVerifier::new(&params);
+    verify_proof(&params, pk.get_vk(), strategy, &[&public], &mut transcript).is_ok()
+}
+
+/// Prove `circuit` with the real prover for `public`, one vector per
+/// instance column, and return the proof bytes.
+pub fn prove<C: Circuit<Fp>>(
+    params: &Params<EqAffine>,
+    pk: &ProvingKey<EqAffine>,
+    circuit: C,
+    public: &[Vec<Fp>],
+) -> Result<Vec<u8>, CircuitError> {
+    circuit_samples::prover::prove(params, pk, circuit, public, OsRng)
+}
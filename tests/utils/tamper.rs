@@ -0,0 +1,170 @@
+use ff::Field;
+use halo2_proofs::{
+    circuit::Value,
+    dev::MockProver,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+        FloorPlanner, Instance, Selector,
+    },
+};
+
+/// Re-synthesize `circuit` into `prover` while the witness reads `value`
+/// for instance row `row` of the first instance column. The verifier still
+/// checks against the instance the prover was created with, so this models
+/// a prover that loads a public input but witnesses something else.
+pub fn tamper_instance<F: Field, C: Circuit<F>>(
+    prover: &mut MockProver<F>,
+    circuit: &C,
+    row: usize,
+    value: F,
+) -> Result<(), Error> {
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+
+    let instance = {
+        let mut meta = ConstraintSystem::<F>::default();
+        meta.instance_column()
+    };
+    let mut tampered = Tampered {
+        prover,
+        instance: Some((instance, row, value)),
+        advice: None,
+    };
+    C::FloorPlanner::synthesize(&mut tampered, circuit, config, vec![])
+}
+
+/// Re-synthesize `circuit` into `prover` while every advice cell annotated
+/// `annotation` is assigned `value`. The cells the circuit copies them to
+/// keep the honest value, so this models a prover that witnesses another
+/// value in a row and copies it on as if it were the honest one.
+pub fn tamper_advice<F: Field, C: Circuit<F>>(
+    prover: &mut MockProver<F>,
+    circuit: &C,
+    annotation: &str,
+    value: F,
+) -> Result<(), Error> {
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+
+    let mut tampered = Tampered {
+        prover,
+        instance: None,
+        advice: Some((annotation, value)),
+    };
+    C::FloorPlanner::synthesize(&mut tampered, circuit, config, vec![])
+}
+
+struct Tampered<'a, F: Field> {
+    prover: &'a mut MockProver<F>,
+    instance: Option<(Column<Instance>, usize, F)>,
+    advice: Option<(&'a str, F)>,
+}
+
+impl<'a, F: Field> Assignment<F> for Tampered<'a, F> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.prover.enter_region(name_fn)
+    }
+
+    fn exit_region(&mut self) {
+        self.prover.exit_region()
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        annotation: A,
+        selector: &Selector,
+        row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.prover.enable_selector(annotation, selector, row)
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+        if let Some((instance, tampered_row, value)) = self.instance {
+            if column == instance && row == tampered_row {
+                return Ok(Value::known(value));
+            }
+        }
+        self.prover.query_instance(column, row)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let annotation: String = annotation().into();
+        match self.advice {
+            Some((tampered, value)) if annotation == tampered => {
+                // the region reads the honest value of the cell out of `to`
+                let _ = to();
+                self.prover
+                    .assign_advice(|| annotation, column, row, || Value::known(value))
+            }
+            _ => self.prover.assign_advice(|| annotation, column, row, to),
+        }
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.prover.assign_fixed(annotation, column, row, to)
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.prover
+            .copy(left_column, left_row, right_column, right_row)
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        row: usize,
+        to: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        self.prover.fill_from_row(column, row, to)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.prover.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.prover.pop_namespace(gadget_name)
+    }
+}
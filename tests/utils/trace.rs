@@ -0,0 +1,51 @@
+use std::{
+    fmt::{Debug, Write},
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    field::Field,
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+
+/// Collects every event as a line of `name=value` pairs
+#[derive(Clone, Default)]
+pub struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Recorder {
+    /// Run `f` with this recorder as the default subscriber and return the
+    /// lines it collected.
+    pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+        let recorder = Recorder::default();
+        let result = tracing::subscriber::with_default(recorder.clone(), f);
+        let lines = recorder.0.lock().unwrap().clone();
+        (result, lines)
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut line = String::new();
+        event.record(&mut |field: &Field, value: &dyn Debug| {
+            write!(line, "{}={:?} ", field.name(), value).unwrap();
+        });
+        self.0.lock().unwrap().push(line.trim_end().to_string());
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}